@@ -1,5 +1,10 @@
 from pathlib import Path
-from typing import Iterator, Optional, Union
+from typing import Any, BinaryIO, Iterable, Iterator, Literal, Optional, Tuple, Union, overload
+
+RecordLike = Union["Record", Tuple[str, str], Tuple[str, str, Optional[str]]]
+
+class FastxParseError(Exception):
+    """Raised when a FASTA/FASTQ input cannot be parsed."""
 
 class FastxReader(Iterator[Record]):
     """
@@ -64,7 +69,11 @@ class Record:
     id: str
     seq: str
     qual: Optional[str]
+    name: str
+    description: Optional[str]
 
+    def __init__(self, id: str, seq: str, qual: Optional[str] = None) -> None: ...
+    def __len__(self) -> int: ...
     def is_fasta(self) -> bool:
         """
         Check if the object represents a FASTA record.
@@ -87,7 +96,7 @@ class Record:
         """
         pass
 
-    def normalize(self, iupac: bool) -> Record:
+    def normalize(self, iupac: bool = False) -> Record:
         """
         Normalize the sequence stored in the `seq` attribute of the object.
 
@@ -108,15 +117,71 @@ class Record:
         """
         pass
 
-def parse_fastx_file(path: Union[str, Path]) -> FastxReader:
+class FastxWriter:
+    """
+    A writer for FASTA/FASTQ records.
+
+    Parameters
+    ----------
+    output : str, pathlib.Path or binary file object
+        The path of the output file, '-' for stdout, or a writable binary
+        file-like object.
+    line_length : int, default: 60
+        The number of sequence characters per FASTA line. Use 0 to write
+        each sequence on a single line.
+    case : {'keep', 'upper', 'lower'}, default: 'keep'
+        The letter case of the written sequences.
+    format : {'auto', 'fasta', 'fastq'}, default: 'auto'
+        The output format. 'auto' writes records with qualities as FASTQ and
+        the others as FASTA. 'fasta' drops qualities, and 'fastq' raises a
+        `ValueError` for records without qualities.
+    input_phred : {33, 64}, default: 33
+        The Phred offset of the qualities passed to the writer.
+    output_phred : {33, 64}, default: 33
+        The Phred offset of the written qualities.
+    compression : str, optional
+        The compression codec ('gzip', 'bgzf', 'bzip2', 'xz', 'zstd' or
+        'none'). By default it is inferred from the file extension.
+
+    Notes
+    -----
+    The writer is a context manager that closes itself on exit.
+    """
+
+    closed: bool
+
+    def __init__(
+        self,
+        output: Union[str, Path, BinaryIO],
+        line_length: int = 60,
+        case: str = "keep",
+        format: str = "auto",
+        input_phred: int = 33,
+        output_phred: int = 33,
+        compression: Optional[str] = None,
+    ) -> None: ...
+    def write(self, record: RecordLike) -> None:
+        """Write a `Record` or an `(id, seq)` or `(id, seq, qual)` tuple."""
+    def write_records(self, records: Iterable[RecordLike]) -> int:
+        """Write all records of an iterable and return their number."""
+    def flush(self) -> None:
+        """Flush buffered records to the output."""
+    def close(self) -> None:
+        """Flush the output and finish the compressed stream."""
+    def __enter__(self) -> FastxWriter: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+
+def parse_fastx_file(path: Union[str, Path, bytes, BinaryIO]) -> FastxReader:
     """
     Returns an iterator that parses a FASTA/FASTQ file and yields sequence
     records.
 
     Parameters
     ----------
-    path : str or pathlib.Path
-        The path to a FASTA/FASTQ file.
+    path : str, pathlib.Path, bytes or binary file object
+        The path to a FASTA/FASTQ file, optionally compressed. Use '-' to
+        read from stdin. `bytes` are parsed as the file content, and binary
+        file-like objects (e.g. `io.BytesIO`) are read until exhausted.
 
     Returns
     -------
@@ -127,7 +192,7 @@ def parse_fastx_file(path: Union[str, Path]) -> FastxReader:
     Raises
     ------
     FastxParseError
-        If an error occurs while reading and parsing the input file.
+        If an error occurs while parsing the input.
 
     See also
     --------
@@ -168,7 +233,7 @@ def parse_fastx_string(fastx_string: str) -> FastxReader:
     """
     pass
 
-def normalize_seq(seq: str, iupac: bool) -> str:
+def normalize_seq(seq: str, iupac: bool = False) -> str:
     """
     Normalize the sequence string of nucleotide records by:
 
@@ -225,7 +290,7 @@ def reverse_complement(seq: str) -> str:
     """
     pass
 
-def decode_phred(qual: str, base_64: bool) -> tuple[int]:
+def decode_phred(qual: str, base_64: bool = False) -> tuple[int, ...]:
     """
     Decode Phred quality strings to quality scores.
 
@@ -244,3 +309,1203 @@ def decode_phred(qual: str, base_64: bool) -> tuple[int]:
         probability of a base-calling error using a logarithmic transformation.
     """
     pass
+
+def index_fasta(path: Union[str, Path]) -> str:
+    """
+    Build a `samtools faidx` compatible `.fai` index for a FASTA file.
+
+    The index is written to `<path>.fai`. For BGZF-compressed files a `.gzi`
+    block index is written to `<path>.gzi` as well.
+
+    Parameters
+    ----------
+    path : str or pathlib.Path
+        The path to a plain or BGZF-compressed FASTA file.
+
+    Returns
+    -------
+    str
+        The path of the `.fai` index.
+    """
+    pass
+
+class IndexedFasta:
+    """
+    Random access to the sequences of an indexed FASTA file.
+
+    Parameters
+    ----------
+    path : str or pathlib.Path
+        The path to a plain or BGZF-compressed FASTA file.
+    build_index : bool, default: True
+        Build the `.fai` (and `.gzi`) index next to the file if it is
+        missing. Otherwise a missing index raises `FileNotFoundError`.
+
+    Attributes
+    ----------
+    lengths : dict of str to int
+        The length of every sequence, keyed by name in file order.
+
+    Notes
+    -----
+    Coordinates are 0-based and half-open, like Python slices.
+    """
+
+    lengths: dict[str, int]
+
+    def __init__(self, path: Union[str, Path], build_index: bool = True) -> None: ...
+    def fetch(
+        self,
+        name: str,
+        start: Optional[int] = None,
+        end: Optional[int] = None,
+        strand: str = "+",
+    ) -> str:
+        """
+        Fetch a sequence or a subrange of it.
+
+        Parameters
+        ----------
+        name : str
+            The sequence name, as in the first column of the `.fai` index.
+        start : int, optional
+            The 0-based start position. Defaults to the sequence start.
+        end : int, optional
+            The exclusive end position, clipped to the sequence length.
+            Defaults to the sequence end.
+        strand : {'+', '-'}, default: '+'
+            Return the reverse complement for '-'.
+        """
+    def keys(self) -> list[str]:
+        """The names of the sequences, in file order."""
+    def get_length(self, name: str) -> int:
+        """The length of the sequence `name`."""
+    def __getitem__(self, name: str) -> str: ...
+    def __contains__(self, name: str) -> bool: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[str]: ...
+
+def extract_regions(
+    fasta: Union[str, Path, bytes, BinaryIO],
+    regions: Union[str, Path, bytes, BinaryIO],
+    output_file: Union[str, Path, BinaryIO],
+    format: Optional[str] = None,
+    feature_types: Optional[list[str]] = None,
+    join_cds: bool = False,
+    flank: int = 0,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Extract the sequences of BED, GFF3 or GTF regions from a FASTA file.
+
+    Parameters
+    ----------
+    fasta : str, pathlib.Path, bytes or binary file object
+        The FASTA file. If it is a path with a `.fai` index next to it, only
+        the regions are read; otherwise the referenced sequences are loaded.
+    regions : str, pathlib.Path, bytes or binary file object
+        A BED, GFF3 or GTF file.
+    output_file : str, pathlib.Path or binary file object
+        Where the extracted sequences are written as FASTA.
+    format : {'bed', 'gff3', 'gtf'}, optional
+        The format of `regions`. By default it is guessed from the file
+        extension or the first line.
+    feature_types : list of str, optional
+        GFF feature types to extract. Defaults to all features, or only
+        'CDS' with `join_cds`.
+    join_cds : bool, default: False
+        Join CDS features per `Parent` (or GTF transcript) in genomic order.
+    flank : int, default: 0
+        Bases added on both sides of every region, clipped to the sequence.
+    line_length : int, default: 60
+        The number of sequence characters per line, 0 for no wrapping.
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+
+    Returns
+    -------
+    int
+        The number of sequences written.
+
+    Notes
+    -----
+    Minus-strand regions are reverse complemented. Headers hold the region
+    name followed by its 1-based location including flanks, e.g.
+    `gene1 chr1:101-200(-)`.
+    """
+    pass
+
+def read_gff(
+    path: Union[str, Path, bytes, BinaryIO], format: Optional[str] = None
+) -> dict[str, list]:
+    """
+    Read a GFF3 or GTF file into a dict of columns.
+
+    Parameters
+    ----------
+    path : str, pathlib.Path, bytes or binary file object
+        The annotation file, optionally compressed. Use '-' for stdin.
+    format : {'gff3', 'gtf'}, optional
+        The file format. By default it is guessed from the file extension or
+        the first feature line.
+
+    Returns
+    -------
+    dict
+        Columns of equal length, ready for `polars.DataFrame`: `seqid`,
+        `source`, `type` (str), `start`, `end` (int, 1-based and inclusive),
+        `score` (float), `strand` (str), `phase` (int), `attributes` (dict of
+        str to list of str), `id` (str) and the reconstructed hierarchy as row
+        indices in `parents` and `children`. Missing values ('.') are `None`.
+
+    Notes
+    -----
+    GFF3 values are percent-decoded and multi-value attributes are split on
+    commas. Repeated GTF attributes (e.g. `tag`) are collected as multiple
+    values. In GTF files genes are identified by `gene_id`, transcripts by
+    `transcript_id` (separate namespaces, so a transcript may reuse its gene's
+    ID), and other features are children of their transcript.
+    Reading stops at a `##FASTA` section; see `read_gff_sequences`.
+    """
+    pass
+
+def read_gff_sequences(path: Union[str, Path, bytes, BinaryIO]) -> dict[str, str]:
+    """
+    Read the sequences embedded after the `##FASTA` directive of a GFF3 file.
+
+    Returns a dict mapping sequence names to sequences, in file order.
+    """
+    pass
+
+def write_gff(
+    features: dict[str, list],
+    output_file: Union[str, Path, BinaryIO],
+    format: str = "gff3",
+    sequences: Optional[dict[str, str]] = None,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Write features to a GFF3 or GTF file.
+
+    Parameters
+    ----------
+    features : dict
+        Columns as returned by `read_gff` (or a `polars.DataFrame.to_dict`
+        with the same names). `seqid`, `type`, `start` and `end` are
+        required; `id`, `parents` and `children` are ignored.
+    output_file : str, pathlib.Path or binary file object
+        Where the features are written. Use '-' for stdout.
+    format : {'gff3', 'gtf'}, default: 'gff3'
+        The output format.
+    sequences : dict of str to str, optional
+        Sequences written after a `##FASTA` directive (GFF3 only).
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+
+    Returns
+    -------
+    int
+        The number of features written.
+    """
+    pass
+
+class GeneticCode:
+    """
+    A genetic code: the amino acid of every codon and the start codons.
+
+    Parameters
+    ----------
+    code : int, str or GeneticCode, default: 1
+        An NCBI translation table id, or a name such as 'Standard',
+        'Bacterial' or 'Vertebrate Mitochondrial' (case-insensitive).
+
+    Attributes
+    ----------
+    id : int or None
+        The NCBI table id, None for custom codes.
+    name : str
+        The code name.
+    amino_acids : str
+        The amino acids of the 64 codons in NCBI order (TTT, TTC, ..., GGG).
+    codons : dict of str to str
+        The amino acid of every codon; stops are '*'.
+    starts : list of str
+        The codons that may start translation.
+    stops : list of str
+        The stop codons.
+
+    Raises
+    ------
+    ValueError
+        If there is no NCBI code with that id or name.
+    """
+
+    id: Optional[int]
+    name: str
+    amino_acids: str
+    codons: dict[str, str]
+    starts: list[str]
+    stops: list[str]
+
+    def __init__(self, code: Union[int, str, "GeneticCode"] = 1) -> None: ...
+    @staticmethod
+    def custom(
+        amino_acids: str, starts: Optional[list[str]] = None, name: str = "custom"
+    ) -> "GeneticCode":
+        """
+        Build a custom genetic code.
+
+        Parameters
+        ----------
+        amino_acids : str
+            The amino acids of the 64 codons in NCBI order (TTT, TTC, ...,
+            GGG), with '*' for stops.
+        starts : list of str, optional
+            The start codons. Defaults to ATG.
+        name : str, default: 'custom'
+            The code name.
+        """
+        ...
+    def reassign(self, changes: dict[str, str]) -> "GeneticCode":
+        """
+        Reassign codons to other amino acids, e.g. ``{"UGA": "W"}``,
+        ``{"UAG": "O"}`` (pyrrolysine) or ``{"UGA": "U"}`` (selenocysteine).
+        '*' makes a codon a stop. Returns a custom copy of this code.
+        """
+        ...
+    def readthrough(
+        self, codons: Optional[list[str]] = None, amino_acid: str = "X"
+    ) -> "GeneticCode":
+        """
+        Read through `codons` (every stop codon by default), translating
+        them as `amino_acid`. Returns a custom copy of this code.
+        """
+        ...
+    def translate_codon(self, codon: str) -> str:
+        """Translate one codon, resolving IUPAC ambiguity as `translate` does."""
+        ...
+
+def list_genetic_codes() -> list[tuple[int, str]]:
+    """The `(id, name)` of every NCBI genetic code."""
+    pass
+
+@overload
+def translate(
+    sequence: str,
+    frame: int = 1,
+    start: int = 0,
+    stop: Optional[int] = None,
+    to_protein: bool = True,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    gaps: str = "error",
+    lenient: bool = False,
+    report: Literal[False] = False,
+) -> str:
+    """
+    Translate a DNA or RNA sequence into an amino acid sequence.
+
+    Ambiguous codons translate to the amino acid all their expansions share
+    (`GCN` is A), otherwise to X.
+
+    Parameters
+    ----------
+    sequence : str
+        A DNA or RNA sequence.
+    frame : int, default: 1
+        The reading frame (1, 2, 3, -1, -2 or -3). Negative frames read the
+        reverse complement.
+    start, stop : int, optional
+        The 0-based, half-open window of the sequence to translate.
+    to_protein : bool, default: True
+        Return the amino acid sequence; otherwise the codons joined by
+        spaces. `translate_codons` returns them as tuples.
+    genetic_code, gaps, lenient
+        As in `translate_six_frames`.
+    report : bool, default: False
+        Return a `TranslationReport` with the codon coordinates, internal
+        stops and longest stop-free stretch instead of a string. Requires
+        `to_protein`.
+
+    Returns
+    -------
+    str or TranslationReport
+        The translation, or its report with `report=True`.
+    """
+    pass
+
+@overload
+def translate(
+    sequence: str,
+    frame: int = 1,
+    start: int = 0,
+    stop: Optional[int] = None,
+    to_protein: bool = True,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    gaps: str = "error",
+    lenient: bool = False,
+    *,
+    report: Literal[True],
+) -> "TranslationReport": ...
+
+def translate_six_frames(
+    sequence: str,
+    to_protein: bool = True,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    gaps: str = "error",
+    lenient: bool = False,
+    report: bool = False,
+) -> Union[list[tuple[int, str]], list[tuple[int, "TranslationReport"]]]:
+    """
+    Translate a DNA or RNA sequence in all six reading frames.
+
+    The sequence is validated and reverse complemented only once. Ambiguous
+    codons translate to the amino acid all their expansions share (`GCN` is
+    A), otherwise to X.
+
+    Parameters
+    ----------
+    sequence : str
+        A DNA or RNA sequence.
+    to_protein : bool, default: True
+        Return amino acid sequences; otherwise space-separated codons.
+    genetic_code : int, str or GeneticCode, default: 1
+        An NCBI genetic code id or name, or a `GeneticCode`. Unknown codes
+        raise `ValueError`.
+    gaps : {'error', 'keep', 'remove'}, default: 'error'
+        How alignment gaps (`-`, `.`) are handled. 'keep' translates `---`
+        to `-` and codons with one or two gaps to X.
+    lenient : bool, default: False
+        Read characters that are not IUPAC nucleotides as N instead of
+        raising `ValueError`.
+    report : bool, default: False
+        Return a `TranslationReport` per frame instead of strings.
+
+    Returns
+    -------
+    list of (int, str) or list of (int, TranslationReport)
+        `(frame, translation)` pairs for frames 1, 2, 3, -1, -2 and -3.
+    """
+    pass
+
+def translate_codons(
+    sequence: str,
+    frame: int = 1,
+    start: int = 0,
+    stop: Optional[int] = None,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    gaps: str = "error",
+    lenient: bool = False,
+    columns: bool = False,
+) -> Union[list[tuple[int, int, str, str]], dict[str, list]]:
+    """
+    Translate a DNA or RNA sequence codon by codon, with the coordinates of
+    every codon.
+
+    Parameters
+    ----------
+    sequence : str
+        A DNA or RNA sequence.
+    frame : int, default: 1
+        The reading frame (1, 2, 3, -1, -2 or -3). Negative frames read the
+        reverse complement.
+    start, stop : int, optional
+        The 0-based, half-open window of the sequence to translate.
+    genetic_code, gaps, lenient
+        As in `translate_six_frames`.
+    columns : bool, default: False
+        Return a dict of columns instead of tuples.
+
+    Returns
+    -------
+    list of (int, int, str, str) or dict of str to list
+        `(nt_start, nt_end, codon, amino_acid)` tuples in translation order,
+        or with `columns` a dict of these four lists, ready for
+        `polars.DataFrame` or `pyarrow.table`. Coordinates are 0-based and
+        half-open in the input sequence, whatever `start` and `frame`; codons
+        of negative frames are reverse complemented and run from the end of
+        the sequence towards its start.
+    """
+    pass
+
+class TranslationReport:
+    """
+    A translation with the input coordinates of every codon, returned by
+    `translate` and `translate_six_frames` with `report=True`.
+
+    Attributes
+    ----------
+    protein : str
+        The amino acid sequence, stops included as `*`.
+    frame : int
+        The reading frame translated.
+    codons : list of (int, int)
+        `(start, end)` of the codon of each amino acid in the input sequence,
+        0-based and half-open. Codons of negative frames run from the end of
+        the sequence towards its start.
+    internal_stops : list of int
+        Indices of the stops before the last codon.
+    terminal_stop : bool
+        Whether the last codon is a stop.
+    longest_stop_free : (int, int)
+        `(start, end)` amino acid indices of the longest stretch without
+        stops, the first one on ties.
+    """
+
+    protein: str
+    frame: int
+    codons: list[tuple[int, int]]
+    internal_stops: list[int]
+    terminal_stop: bool
+    longest_stop_free: tuple[int, int]
+
+    def __len__(self) -> int: ...
+
+class Frameshift:
+    """
+    A translation through a programmed frameshift, found by
+    `translate_frameshifts`.
+
+    Attributes
+    ----------
+    start, end : int
+        Input coordinates of the slippery heptamer.
+    frame : int
+        The frame translated up to the heptamer.
+    shift : int
+        The frameshift, -1 or 1.
+    motif : str
+        The heptamer, as read in `frame`.
+    translation : TranslationReport
+        The fused protein. Its codon coordinates step back or skip a base
+        after the heptamer.
+    """
+
+    start: int
+    end: int
+    frame: int
+    shift: int
+    motif: str
+    translation: TranslationReport
+
+def translate_frameshifts(
+    sequence: str,
+    frame: int = 1,
+    shifts: list[int] = [-1, 1],
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    gaps: str = "error",
+    lenient: bool = False,
+) -> list[Frameshift]:
+    """
+    Translate a sequence across candidate ribosomal frameshifts.
+
+    Slippery heptamers `X XXY YYZ` (X any base, Y A or U, Z not G) whose
+    `XXY YYZ` are codons of `frame` are candidate sites. At each site the
+    translation continues after `YYZ` in the shifted frame, so a -1 shift
+    reads the last base of the heptamer twice and a +1 shift skips the next
+    base.
+
+    Parameters
+    ----------
+    sequence : str
+        A DNA or RNA sequence.
+    frame : int, default: 1
+        The reading frame up to the sites (1, 2, 3, -1, -2 or -3).
+    shifts : list of int, default: [-1, 1]
+        The frameshifts tried at every site.
+    genetic_code, gaps, lenient
+        As in `translate_six_frames`.
+
+    Returns
+    -------
+    list of Frameshift
+        One per site and shift, ordered by site start and shift. Compare
+        their `translation.longest_stop_free` with that of
+        `translate(..., report=True)` to spot shifts that bypass a stop.
+    """
+    pass
+
+def translate_file(
+    file_path: Union[str, Path, bytes, BinaryIO],
+    frame: int = 1,
+    to_protein: bool = True,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    frames: Optional[Union[str, list[int]]] = None,
+    output_file: Optional[Union[str, Path, BinaryIO]] = None,
+    gaps: str = "error",
+    lenient: bool = False,
+    threads: Optional[int] = None,
+    header: Optional[str] = None,
+    min_length: int = 0,
+    trim_stops: bool = False,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> Union[list[tuple[str, str]], int]:
+    """
+    Translate the sequences of a FASTA/FASTQ file.
+
+    Parameters
+    ----------
+    file_path : str, pathlib.Path, bytes or binary file object
+        The input file, optionally compressed. Use '-' for stdin.
+    frame : int, default: 1
+        The reading frame (-3 to 3, excluding 0).
+    to_protein : bool, default: True
+        Return amino acid sequences; otherwise space-separated codons.
+    genetic_code : int, str or GeneticCode, default: 1
+        An NCBI genetic code id or name, or a `GeneticCode`. Unknown codes
+        raise `ValueError`.
+    frames : 'all' or list of int, optional
+        Translate these frames instead of `frame`, labelling each result
+        `name_frame=N`.
+    output_file : str, pathlib.Path or binary file object, optional
+        Stream the translations there as FASTA and return their number,
+        instead of holding them in memory. Use '-' for stdout.
+    gaps : {'error', 'keep', 'remove'}, default: 'error'
+        How alignment gaps (`-`, `.`) are handled. 'keep' translates `---`
+        to `-` and codons with one or two gaps to X.
+    lenient : bool, default: False
+        Read characters that are not IUPAC nucleotides as N instead of
+        raising `ValueError`.
+    threads : int, optional
+        The number of worker threads. Records are translated in parallel
+        batches; results keep the input order.
+    header : str, optional
+        Template of the translation headers, with the fields `{id}` (first
+        word of the record header), `{description}` (the rest), `{frame}`
+        and `{length}` (of the translation), e.g. '{id}_f{frame}'.
+    min_length : int, default: 0
+        Skip translations with fewer amino acids, after trimming.
+    trim_stops : bool, default: False
+        Remove trailing stops from the translations.
+    line_length : int, default: 60
+        FASTA line width of `output_file`; 0 writes each sequence on one line.
+    compression : str, optional
+        The compression codec of `output_file`. Inferred from the extension
+        by default.
+
+    Returns
+    -------
+    list of (str, str) or int
+        `(id, translation)` pairs, or the number of written records.
+    """
+    pass
+
+class Orf:
+    """
+    An open reading frame found by `find_orfs`.
+
+    Attributes
+    ----------
+    start, end : int
+        0-based, half-open coordinates on the forward strand, including the
+        stop codon. ORFs crossing the origin of a circular sequence end past
+        its length; `find_orfs_file` splits them into two BED rows.
+    strand : str
+        '+' or '-'.
+    frame : int
+        The reading frame, as in `translate` (1, 2, 3, -1, -2 or -3).
+    partial_start, partial_end : bool
+        Whether the ORF runs off the 5' or 3' end of the sequence.
+    sequence : str
+        The nucleotide sequence, reverse complemented on the minus strand.
+    protein : str
+        The translation, without the stop codon. Start codons translate to M.
+    """
+
+    start: int
+    end: int
+    strand: str
+    frame: int
+    partial_start: bool
+    partial_end: bool
+    sequence: str
+    protein: str
+
+    def __len__(self) -> int: ...
+
+def find_orfs(
+    sequence: str,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    min_length: int = 75,
+    starts: str = "atg",
+    stop_to_stop: bool = False,
+    nested: bool = False,
+    partial: bool = False,
+    circular: bool = False,
+) -> list[Orf]:
+    """
+    Find open reading frames in both strands of a sequence.
+
+    Parameters
+    ----------
+    sequence : str
+        A DNA or RNA sequence. Ambiguous codons are never start codons and
+        translate as in `translate`; those resolving to a stop end ORFs.
+    genetic_code : int, str or GeneticCode, default: 1
+        The genetic code providing the start and stop codons.
+    min_length : int, default: 75
+        The minimum ORF length in nucleotides, stop codon included.
+    starts : {'atg', 'alternative'}, default: 'atg'
+        Start at ATG only, or also at the alternative initiation codons of
+        the genetic code.
+    stop_to_stop : bool, default: False
+        Report every stretch between two in-frame stop codons instead of
+        starting at a start codon.
+    nested : bool, default: False
+        Also report ORFs starting at downstream start codons of a longer ORF.
+    partial : bool, default: False
+        Report ORFs without a start or stop codon at the sequence ends.
+    circular : bool, default: False
+        Treat the sequence as circular, so ORFs may span the origin. There
+        are no partial ORFs in circular sequences.
+
+    Returns
+    -------
+    list of Orf
+        The ORFs ordered by start, end and strand.
+    """
+    pass
+
+def find_orfs_file(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    output_file: Union[str, Path, BinaryIO],
+    output_format: str = "protein",
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    min_length: int = 75,
+    starts: str = "atg",
+    stop_to_stop: bool = False,
+    nested: bool = False,
+    partial: bool = False,
+    circular: bool = False,
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Find open reading frames in every sequence of a FASTA/FASTQ file.
+
+    ORFs are named `<sequence name>_ORF<n>`, numbered by position within each
+    sequence.
+
+    Parameters
+    ----------
+    input_file : str, pathlib.Path, bytes or binary file object
+        The sequences to search. Use '-' for stdin.
+    output_file : str, pathlib.Path or binary file object
+        Where the ORFs are written. Use '-' for stdout.
+    output_format : {'protein', 'nucleotide', 'bed', 'gff3'}, default: 'protein'
+        Protein or nucleotide FASTA, with coordinates in the description,
+        BED6 or GFF3 `ORF` features. With `circular`, BED rows of ORFs
+        crossing the origin are split in two and GFF3 sequences get a
+        `region` feature with `Is_circular=true`.
+    genetic_code, min_length, starts, stop_to_stop, nested, partial, circular
+        As in `find_orfs`.
+    threads : int, optional
+        The number of worker threads. Output order follows the input.
+    line_length : int, default: 60
+        FASTA line width; 0 writes each sequence on one line.
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+
+    Returns
+    -------
+    int
+        The number of ORFs written.
+    """
+    pass
+
+class CodonUsage:
+    """
+    Codon counts of one or more sequences under a genetic code.
+
+    Attributes
+    ----------
+    counts : dict of str to int
+        The count of every codon, in NCBI order (TTT, TTC, ..., GGG).
+    total : int
+        The number of codons counted.
+    genetic_code : GeneticCode
+        The code defining synonymous codons.
+
+    Notes
+    -----
+    Usages can be summed with `+` when they share a genetic code.
+    """
+
+    counts: dict[str, int]
+    total: int
+    genetic_code: GeneticCode
+
+    def frequencies(self) -> dict[str, float]:
+        """Codon frequencies, summing to one (NaN without codons)."""
+        ...
+    def rscu(self) -> dict[str, float]:
+        """
+        Relative synonymous codon usage of every codon: its count over the
+        mean count of its synonymous family; NaN when the amino acid (or
+        stop) does not occur.
+        """
+        ...
+    def enc(self) -> float:
+        """
+        Effective number of codons (Wright, 1990), from 20 (one codon per
+        amino acid) to 61 (uniform usage) in the standard code; NaN without
+        sense codons.
+        """
+        ...
+    def gc3(self) -> float:
+        """GC content of the third position of sense codons; NaN without sense codons."""
+        ...
+    def relative_adaptiveness(self) -> dict[str, Optional[float]]:
+        """
+        Relative adaptiveness of every codon when used as a CAI reference:
+        each codon count over that of the most used synonymous codon. Unused
+        codons count 0.5. Stops, single-codon amino acids and amino acids
+        absent from the reference are None.
+        """
+        ...
+    def cai(self, reference: Union["CodonUsage", dict[str, int]]) -> float:
+        """
+        Codon adaptation index (Sharp & Li, 1987) against the codon usage of
+        highly expressed genes: the geometric mean relative adaptiveness of
+        the codons, from 0 to 1; NaN when no codon has a weight.
+        """
+        ...
+    def __add__(self, other: "CodonUsage") -> "CodonUsage": ...
+
+def codon_usage(sequence: str, genetic_code: Union[int, str, "GeneticCode"] = 1) -> CodonUsage:
+    """
+    Count the codons of a sequence.
+
+    Parameters
+    ----------
+    sequence : str
+        A coding DNA or RNA sequence, read in frame 1. Codons with ambiguous
+        bases or gaps are skipped.
+    genetic_code : int, str or GeneticCode, default: 1
+        The code defining synonymous codons.
+
+    Returns
+    -------
+    CodonUsage
+    """
+    pass
+
+def codon_usage_file(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    per_sequence: bool = False,
+    threads: Optional[int] = None,
+) -> Union[CodonUsage, list[tuple[str, CodonUsage]]]:
+    """
+    Count the codons of every sequence in a FASTA/FASTQ file.
+
+    Parameters
+    ----------
+    input_file : str, pathlib.Path, bytes or binary file object
+        Coding sequences, read in frame 1. Use '-' for stdin.
+    genetic_code : int, str or GeneticCode, default: 1
+        The code defining synonymous codons.
+    per_sequence : bool, default: False
+        Return the usage of each sequence instead of their sum.
+    threads : int, optional
+        The number of worker threads.
+
+    Returns
+    -------
+    CodonUsage or list of (str, CodonUsage)
+        The summed usage, or `(id, usage)` pairs in input order.
+    """
+    pass
+
+def codon_usage_columns(
+    sequences: list[Optional[str]],
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    reference: Optional[Union[CodonUsage, dict[str, int]]] = None,
+    threads: Optional[int] = None,
+) -> dict[str, list]:
+    """
+    Codon usage statistics of many sequences at once, as columns.
+
+    Meant for `polars.Series.map_batches`: the result converts directly to a
+    `polars.DataFrame`.
+
+    Parameters
+    ----------
+    sequences : list of str or None
+        Coding sequences, read in frame 1. None gives null statistics.
+    genetic_code : int, str or GeneticCode, default: 1
+        The code defining synonymous codons.
+    reference : CodonUsage or dict of str to int, optional
+        A CAI reference. Without one, `cai` is not computed.
+    threads : int, optional
+        The number of worker threads.
+
+    Returns
+    -------
+    dict of str to list
+        `counts` (64 codon counts in NCBI order), `rscu` (64 values in the
+        same order), `enc`, `gc3` and, with a reference, `cai`.
+    """
+    pass
+
+def back_translate(
+    protein: str,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+    mode: str = "degenerate",
+    codon_usage: Optional[Union[CodonUsage, dict[str, int]]] = None,
+) -> str:
+    """
+    Back-translate a protein to DNA.
+
+    Parameters
+    ----------
+    protein : str
+        One-letter amino acid codes. `*` stands for a stop, B, Z and J for
+        D or N, E or Q and I or L, X for any codon (NNN) and gaps (`-`, `.`)
+        for `---`.
+    genetic_code : int, str or GeneticCode, default: 1
+        The genetic code giving the codons of each amino acid.
+    mode : {'degenerate', 'most_frequent'}, default: 'degenerate'
+        Give each amino acid the IUPAC codon covering all of its codons, or
+        its most used codon in `codon_usage`.
+    codon_usage : CodonUsage or dict of str to int, optional
+        The codon usage table, required by 'most_frequent'. Ties go to the
+        first codon in NCBI order.
+
+    Returns
+    -------
+    str
+        The DNA sequence, three bases per residue.
+
+    Notes
+    -----
+    IUPAC codes are set per codon position, so amino acids with six codons
+    get a codon covering more: serine (TCN, AGY) becomes WSN.
+    """
+    pass
+
+class Primer:
+    """
+    A degenerate primer designed by `degenerate_primers`.
+
+    Attributes
+    ----------
+    start, end : int
+        0-based, half-open coordinates on the back-translated protein.
+    strand : str
+        '+' for forward primers, '-' for reverse primers.
+    sequence : str
+        The primer, 5' to 3', in IUPAC codes.
+    degeneracy : int
+        The number of distinct sequences the primer stands for.
+    motif : str
+        The amino acids whose codons the primer overlaps.
+    """
+
+    start: int
+    end: int
+    strand: str
+    sequence: str
+    degeneracy: int
+    motif: str
+
+    def __len__(self) -> int: ...
+
+def degenerate_primers(
+    protein: str,
+    length: int = 20,
+    max_degeneracy: int = 256,
+    exact_3prime: int = 0,
+    genetic_code: Union[int, str, "GeneticCode"] = 1,
+) -> list[Primer]:
+    """
+    Design degenerate primers from a conserved protein motif.
+
+    Every window of `length` bases of the degenerate back-translation is a
+    forward primer and, reverse complemented, a reverse primer.
+
+    Parameters
+    ----------
+    protein : str
+        The conserved protein sequence, as in `back_translate`.
+    length : int, default: 20
+        The primer length in bases.
+    max_degeneracy : int, default: 256
+        The maximum number of distinct sequences a primer may stand for.
+    exact_3prime : int, default: 0
+        The number of bases at the 3' end of the primers that must not be
+        degenerate.
+    genetic_code : int, str or GeneticCode, default: 1
+        The genetic code giving the codons of each amino acid.
+
+    Returns
+    -------
+    list of Primer
+        The primers ordered by start and strand. Windows with gaps are
+        skipped.
+    """
+    pass
+
+def deduplicate_by_seq(
+    input_files: list[Union[str, Path, bytes, BinaryIO]],
+    output_file: Union[str, Path, BinaryIO],
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+    canonical: bool = False,
+    u_as_t: bool = False,
+    circular: bool = False,
+    duplicates_file: Optional[Union[str, Path, BinaryIO]] = None,
+    spill_dir: Optional[Union[str, Path]] = None,
+    keep: Union[str, int] = "first",
+) -> int:
+    """
+    Write one record of every distinct sequence, chosen by `keep`.
+
+    Sequences are compared case-insensitively by a 128-bit hash of their
+    key. Input files are read in order and the kept records are written in
+    input order, so the output is byte-identical across runs and thread
+    counts.
+
+    Parameters
+    ----------
+    input_files : list of str, pathlib.Path, bytes or binary file objects
+        The FASTA/FASTQ inputs. Use '-' for stdin.
+    output_file : str, pathlib.Path or binary file object
+        Where the unique records are written. Use '-' for stdout.
+    threads : int, optional
+        The number of worker threads hashing records and filling the shards
+        of the set of kept hashes.
+    line_length : int, default: 60
+        FASTA line width; 0 writes each sequence on one line.
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+    canonical : bool, default: False
+        Also collapse reverse complements, comparing the smaller of each
+        nucleotide sequence and its reverse complement.
+    u_as_t : bool, default: False
+        Read U as T, so DNA and RNA copies collapse.
+    circular : bool, default: False
+        Also collapse rotations, comparing circular sequences from their
+        lexicographically smallest rotation.
+    duplicates_file : str, pathlib.Path or binary file object, optional
+        Write a tab-separated table of the removed records: `duplicate`,
+        `kept` (their names), `strand` and `offset`. The duplicate is the
+        kept sequence rotated to start at `offset` (0-based) and reverse
+        complemented on the '-' strand.
+    spill_dir : str or pathlib.Path, optional
+        Directory for temporary partition files, for inputs larger than
+        memory. Records are partitioned by hash and each partition is
+        deduplicated on its own; the output is the same as in memory.
+    keep : str or int, default: 'first'
+        Which record of every sequence to keep: 'first', 'last',
+        'longest_header', 'best_quality' (the highest mean quality, FASTQ
+        only) or the index in `input_files` of the file whose records are
+        preferred. Ties go to the first record. Policies other than 'first'
+        hold the kept records in memory until all inputs are read, unless
+        `spill_dir` is given.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def deduplicate_by_id(
+    input_files: list[Union[str, Path, bytes, BinaryIO]],
+    output_file: Union[str, Path, BinaryIO],
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+    spill_dir: Optional[Union[str, Path]] = None,
+    keep: Union[str, int] = "first",
+) -> int:
+    """
+    Write one record of every distinct ID, chosen by `keep`.
+
+    The ID is the first word of the header. IDs are compared by a 128-bit
+    hash and the kept records are written in input order, so the output is
+    byte-identical across runs and thread counts.
+
+    Parameters
+    ----------
+    input_files, output_file, threads, line_length, compression, spill_dir
+        As in `deduplicate_by_seq`.
+    keep : str or int, default: 'first'
+        Which record of every ID to keep, as in `deduplicate_by_seq`.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def deduplicate_by_containment(
+    input_files: list[Union[str, Path, bytes, BinaryIO]],
+    output_file: Union[str, Path, BinaryIO],
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+    canonical: bool = True,
+    u_as_t: bool = False,
+    k: int = 31,
+    duplicates_file: Optional[Union[str, Path, BinaryIO]] = None,
+) -> int:
+    """
+    Write the records whose sequence is not contained in another one.
+
+    A sequence is removed when it is a substring of a longer sequence, or
+    equal to an earlier one, after uppercasing. Copies of a sequence are set
+    aside first, then every distinct sequence is indexed by the rarest of
+    several evenly spaced seeds of `k` bases and looked up along all others,
+    so shared low-complexity prefixes do not slow the search down. All
+    records are held in memory. Unique records are written in input order.
+
+    Parameters
+    ----------
+    input_files, output_file, threads, line_length, compression
+        As in `deduplicate_by_seq`.
+    canonical : bool, default: True
+        Also look for sequences on the reverse strand.
+    u_as_t : bool, default: False
+        Read U as T, so DNA and RNA copies collapse.
+    k : int, default: 31
+        The seed length. Longer seeds give fewer candidate matches to verify.
+    duplicates_file : str, pathlib.Path or binary file object, optional
+        Write a tab-separated table of the removed records: `duplicate`,
+        `kept` (their names), `strand` and `offset`. The duplicate is the kept
+        sequence from `offset` (0-based), reverse complemented on the '-'
+        strand.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def filter_by_header(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    patterns: list[str],
+    output_file: Union[str, Path, BinaryIO],
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Write the records whose header contains any of `patterns`.
+
+    Headers are matched in parallel batches and the records are written in
+    input order.
+
+    Parameters
+    ----------
+    input_file : str, pathlib.Path, bytes or binary file object
+        The FASTA/FASTQ input. Use '-' for stdin.
+    patterns : list of str
+        Substrings looked up in the full header line.
+    output_file : str, pathlib.Path or binary file object
+        Where the matching records are written. Use '-' for stdout.
+    threads : int, optional
+        The number of worker threads matching headers.
+    line_length : int, default: 60
+        FASTA line width; 0 writes each sequence on one line.
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def filter_by_header_invert(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    patterns: list[str],
+    output_file: Union[str, Path, BinaryIO],
+    threads: Optional[int] = None,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Write the records whose header contains none of `patterns`.
+
+    Parameters
+    ----------
+    input_file, patterns, output_file, threads, line_length, compression
+        As in `filter_by_header`.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def sample_sequences(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    output_file: Union[str, Path, BinaryIO],
+    n: int,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Write `n` records chosen at random.
+
+    All records are held in memory and shuffled, so the input is read once
+    and may be a stream. The sampled records are written in shuffled order.
+
+    Parameters
+    ----------
+    input_file : str, pathlib.Path, bytes or binary file object
+        The FASTA/FASTQ input. Use '-' for stdin.
+    output_file : str, pathlib.Path or binary file object
+        Where the sampled records are written. Use '-' for stdout.
+    n : int
+        The number of records to sample; all of them when the input has
+        fewer.
+    line_length : int, default: 60
+        FASTA line width; 0 writes each sequence on one line.
+    compression : str, optional
+        The output compression codec. Inferred from the extension by default.
+
+    Returns
+    -------
+    int
+        The number of records written.
+    """
+    pass
+
+def sample_sequences_by_proportion(
+    input_file: Union[str, Path, bytes, BinaryIO],
+    output_file: Union[str, Path, BinaryIO],
+    proportion: float,
+    line_length: int = 60,
+    compression: Optional[str] = None,
+) -> int:
+    """
+    Write a random share of the records.
+
+    Parameters
+    ----------
+    input_file, output_file, line_length, compression
+        As in `sample_sequences`.
+    proportion : float
+        The share of records to sample, rounded to the nearest count.
+
+    Returns
+    -------
+    int
+        The number of records written.
+
+    Raises
+    ------
+    ValueError
+        If `proportion` is not in (0, 1].
+    """
+    pass
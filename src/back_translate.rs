@@ -0,0 +1,309 @@
+//! Reverse translation of proteins to DNA
+//!
+//! Each amino acid becomes either the IUPAC codon covering all of its codons
+//! in the genetic code or its most used codon in a codon usage table. The
+//! degenerate sequence is also the template for degenerate primer design.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::codon_usage::UsageTable;
+use crate::genetic_code::{CodeSelection, GeneticCode};
+use crate::seq_utils::complement_base;
+
+/// IUPAC code of each base mask (T=1, C=2, A=4, G=8), as in the genetic code
+/// lookup
+const IUPAC_CODES: &[u8; 16] = b"-TCYAWMHGKSBRDVN";
+
+/// How amino acids are turned into codons
+pub enum BackTranslation<'a> {
+    /// The IUPAC codon covering every codon of the amino acid
+    Degenerate,
+    /// The most used codon of the amino acid, given codon counts
+    MostFrequent(&'a [u64; 64]),
+}
+
+/// The table indices of the codons a one-letter code stands for, including
+/// the ambiguity codes B (D or N), Z (E or Q) and J (I or L)
+fn amino_acid_codons(code: &GeneticCode, amino_acid: u8) -> Vec<usize> {
+    let members: &[u8] = match amino_acid {
+        b'B' => b"DN",
+        b'Z' => b"EQ",
+        b'J' => b"IL",
+        _ => std::slice::from_ref(&amino_acid),
+    };
+    (0..64)
+        .filter(|&i| members.contains(&code.amino_acid_at(i)))
+        .collect()
+}
+
+/// The IUPAC codon covering the codons at `indices`
+fn degenerate_codon(indices: &[usize]) -> [u8; 3] {
+    let mut masks = [0; 3];
+    for &index in indices {
+        masks[0] |= 1 << (index / 16);
+        masks[1] |= 1 << (index / 4 % 4);
+        masks[2] |= 1 << (index % 4);
+    }
+    masks.map(|mask| IUPAC_CODES[mask])
+}
+
+/// The codon of one amino acid, X being NNN and gaps `---`
+fn back_translate_residue(
+    code: &GeneticCode,
+    mode: &BackTranslation<'_>,
+    amino_acid: u8,
+) -> PyResult<[u8; 3]> {
+    let amino_acid = amino_acid.to_ascii_uppercase();
+    match amino_acid {
+        b'-' | b'.' => return Ok(*b"---"),
+        b'X' => return Ok(*b"NNN"),
+        _ => {}
+    }
+    let indices = amino_acid_codons(code, amino_acid);
+    if indices.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "Amino acid '{}' has no codon in the genetic code",
+            amino_acid as char
+        )));
+    }
+    match mode {
+        BackTranslation::Degenerate => Ok(degenerate_codon(&indices)),
+        BackTranslation::MostFrequent(counts) => {
+            // The first codon in table order wins ties
+            let mut best = indices[0];
+            for &index in &indices[1..] {
+                if counts[index] > counts[best] {
+                    best = index;
+                }
+            }
+            if counts[best] == 0 && indices.len() > 1 {
+                return Err(PyValueError::new_err(format!(
+                    "The codon usage table has no codon for amino acid '{}'",
+                    amino_acid as char
+                )));
+            }
+            Ok(degenerate_codon(&[best]))
+        }
+    }
+}
+
+/// Back-translate a protein, codon by codon
+pub fn back_translate_protein(
+    protein: &[u8],
+    code: &GeneticCode,
+    mode: &BackTranslation<'_>,
+) -> PyResult<Vec<u8>> {
+    let mut codons: [Option<[u8; 3]>; 256] = [None; 256];
+    let mut seq = Vec::with_capacity(protein.len() * 3);
+    for &amino_acid in protein {
+        let codon = match codons[amino_acid as usize] {
+            Some(codon) => codon,
+            None => {
+                let codon = back_translate_residue(code, mode, amino_acid)?;
+                codons[amino_acid as usize] = Some(codon);
+                codon
+            }
+        };
+        seq.extend_from_slice(&codon);
+    }
+    Ok(seq)
+}
+
+/// Number of bases an IUPAC code stands for, 0 for gaps
+fn base_count(base: u8) -> u64 {
+    let mask = IUPAC_CODES
+        .iter()
+        .position(|&code| code == base)
+        .unwrap_or(15);
+    mask.count_ones() as u64
+}
+
+/// A degenerate primer designed by `degenerate_primers`.
+///
+/// Attributes
+/// ----------
+/// start, end : int
+///     0-based, half-open coordinates on the back-translated protein.
+/// strand : str
+///     '+' for forward primers, '-' for reverse primers.
+/// sequence : str
+///     The primer, 5' to 3', in IUPAC codes.
+/// degeneracy : int
+///     The number of distinct sequences the primer stands for.
+/// motif : str
+///     The amino acids whose codons the primer overlaps.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Primer {
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    #[pyo3(get)]
+    pub strand: char,
+    #[pyo3(get)]
+    pub sequence: String,
+    #[pyo3(get)]
+    pub degeneracy: u64,
+    #[pyo3(get)]
+    pub motif: String,
+}
+
+#[pymethods]
+impl Primer {
+    fn __len__(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Primer(start={}, end={}, strand='{}', sequence='{}', degeneracy={})",
+            self.start, self.end, self.strand, self.sequence, self.degeneracy
+        )
+    }
+}
+
+/// Back-translate a protein to DNA.
+///
+/// Parameters
+/// ----------
+/// protein : str
+///     One-letter amino acid codes. `*` stands for a stop, B, Z and J for
+///     D or N, E or Q and I or L, X for any codon (NNN) and gaps (`-`, `.`)
+///     for `---`.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The genetic code giving the codons of each amino acid.
+/// mode : {'degenerate', 'most_frequent'}, default: 'degenerate'
+///     Give each amino acid the IUPAC codon covering all of its codons, or
+///     its most used codon in `codon_usage`.
+/// codon_usage : CodonUsage or dict of str to int, optional
+///     The codon usage table, required by 'most_frequent'. Ties go to the
+///     first codon in NCBI order.
+///
+/// Returns
+/// -------
+/// str
+///     The DNA sequence, three bases per residue.
+///
+/// Notes
+/// -----
+/// IUPAC codes are set per codon position, so amino acids with six codons
+/// get a codon covering more: serine (TCN, AGY) becomes WSN.
+#[pyfunction]
+#[pyo3(signature = (protein, genetic_code=CodeSelection::Id(1), mode="degenerate", codon_usage=None))]
+pub fn back_translate(
+    protein: &str,
+    genetic_code: CodeSelection,
+    mode: &str,
+    codon_usage: Option<UsageTable<'_>>,
+) -> PyResult<String> {
+    let code = genetic_code.resolve()?;
+    let usage = codon_usage.map(|u| u.into_usage(&code)).transpose()?;
+    let mode = match (mode.to_ascii_lowercase().as_str(), &usage) {
+        ("degenerate", _) => BackTranslation::Degenerate,
+        ("most_frequent", Some(usage)) => BackTranslation::MostFrequent(usage.codon_counts()),
+        ("most_frequent", None) => {
+            return Err(PyValueError::new_err(
+                "mode='most_frequent' needs a codon_usage table",
+            ))
+        }
+        (other, _) => {
+            return Err(PyValueError::new_err(format!(
+                "Invalid mode '{}'. Expected 'degenerate' or 'most_frequent'",
+                other
+            )))
+        }
+    };
+    let seq = back_translate_protein(protein.as_bytes(), &code, &mode)?;
+    Ok(String::from_utf8_lossy(&seq).into_owned())
+}
+
+/// Design degenerate primers from a conserved protein motif.
+///
+/// Every window of `length` bases of the degenerate back-translation is a
+/// forward primer and, reverse complemented, a reverse primer.
+///
+/// Parameters
+/// ----------
+/// protein : str
+///     The conserved protein sequence, as in `back_translate`.
+/// length : int, default: 20
+///     The primer length in bases.
+/// max_degeneracy : int, default: 256
+///     The maximum number of distinct sequences a primer may stand for.
+/// exact_3prime : int, default: 0
+///     The number of bases at the 3' end of the primers that must not be
+///     degenerate.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The genetic code giving the codons of each amino acid.
+///
+/// Returns
+/// -------
+/// list of Primer
+///     The primers ordered by start and strand. Windows with gaps are
+///     skipped.
+#[pyfunction]
+#[pyo3(signature = (protein, length=20, max_degeneracy=256, exact_3prime=0, genetic_code=CodeSelection::Id(1)))]
+pub fn degenerate_primers(
+    protein: &str,
+    length: usize,
+    max_degeneracy: u64,
+    exact_3prime: usize,
+    genetic_code: CodeSelection,
+) -> PyResult<Vec<Primer>> {
+    if length == 0 {
+        return Err(PyValueError::new_err("Primer length must be positive"));
+    }
+    if exact_3prime > length {
+        return Err(PyValueError::new_err(format!(
+            "exact_3prime ({}) is longer than the primers ({})",
+            exact_3prime, length
+        )));
+    }
+    let code = genetic_code.resolve()?;
+    let seq = back_translate_protein(protein.as_bytes(), &code, &BackTranslation::Degenerate)?;
+    let counts: Vec<u64> = seq.iter().map(|&base| base_count(base)).collect();
+
+    let mut primers = Vec::new();
+    for start in 0..seq.len().saturating_sub(length - 1) {
+        let end = start + length;
+        let window = &counts[start..end];
+        if window.contains(&0) {
+            continue;
+        }
+        let degeneracy = window.iter().fold(1u64, |d, &n| d.saturating_mul(n));
+        if degeneracy > max_degeneracy {
+            continue;
+        }
+        let motif = &protein[start / 3..end.div_ceil(3)];
+        // The 3' end of the reverse primer is the start of the window
+        let exact_forward = window[length - exact_3prime..].iter().all(|&n| n == 1);
+        let exact_reverse = window[..exact_3prime].iter().all(|&n| n == 1);
+        if exact_forward {
+            primers.push(Primer {
+                start,
+                end,
+                strand: '+',
+                sequence: String::from_utf8_lossy(&seq[start..end]).into_owned(),
+                degeneracy,
+                motif: motif.to_string(),
+            });
+        }
+        if exact_reverse {
+            primers.push(Primer {
+                start,
+                end,
+                strand: '-',
+                sequence: seq[start..end]
+                    .iter()
+                    .rev()
+                    .map(|&base| complement_base(base) as char)
+                    .collect(),
+                degeneracy,
+                motif: motif.to_string(),
+            });
+        }
+    }
+    Ok(primers)
+}
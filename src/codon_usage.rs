@@ -0,0 +1,535 @@
+//! Codon usage statistics
+//!
+//! Codons are counted in frame 1 of each sequence; codons with ambiguous
+//! bases or gaps are skipped. Synonymous codon families follow the chosen
+//! genetic code, so e.g. TGA belongs to the W family in code 4.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::fastx_io::RecordReader;
+use crate::genetic_code::{codon_at, codon_index, parse_codon, CodeSelection, GeneticCode};
+use crate::pool;
+use crate::pyfile::InputSource;
+
+/// Number of records counted per parallel batch
+const BATCH_SIZE: usize = 4096;
+
+/// Pseudocount used for codons missing from a CAI reference (Sharp & Li, 1987)
+const MISSING_CODON_COUNT: f64 = 0.5;
+
+/// Count the codons of frame 1
+fn count_codons(seq: &[u8]) -> [u64; 64] {
+    let mut counts = [0; 64];
+    for codon in seq.chunks_exact(3) {
+        if let Some(index) = codon_index(codon) {
+            counts[index] += 1;
+        }
+    }
+    counts
+}
+
+/// Synonymous codon families of a genetic code: the table indices of the
+/// codons of every amino acid, stops included, in table order
+fn codon_families(code: &GeneticCode) -> Vec<(u8, Vec<usize>)> {
+    let mut families: Vec<(u8, Vec<usize>)> = Vec::new();
+    for index in 0..64 {
+        let amino_acid = code.amino_acid_at(index);
+        match families.iter_mut().find(|(aa, _)| *aa == amino_acid) {
+            Some((_, codons)) => codons.push(index),
+            None => families.push((amino_acid, vec![index])),
+        }
+    }
+    families
+}
+
+/// Codon counts of one or more sequences under a genetic code.
+///
+/// Attributes
+/// ----------
+/// counts : dict of str to int
+///     The count of every codon, in NCBI order (TTT, TTC, ..., GGG).
+/// total : int
+///     The number of codons counted.
+/// genetic_code : GeneticCode
+///     The code defining synonymous codons.
+///
+/// Notes
+/// -----
+/// Usages can be summed with `+` when they share a genetic code.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct CodonUsage {
+    counts: [u64; 64],
+    code: GeneticCode,
+}
+
+impl CodonUsage {
+    pub fn from_sequence(seq: &[u8], code: GeneticCode) -> Self {
+        CodonUsage {
+            counts: count_codons(seq),
+            code,
+        }
+    }
+
+    /// The count of every codon, in table order
+    pub fn codon_counts(&self) -> &[u64; 64] {
+        &self.counts
+    }
+
+    /// Relative synonymous codon usage: the count of each codon over the
+    /// mean count of its family, NaN for families that do not occur
+    pub fn rscu_values(&self) -> [f64; 64] {
+        let mut rscu = [f64::NAN; 64];
+        for (_, codons) in codon_families(&self.code) {
+            let total: u64 = codons.iter().map(|&i| self.counts[i]).sum();
+            if total == 0 {
+                continue;
+            }
+            let expected = total as f64 / codons.len() as f64;
+            for &i in &codons {
+                rscu[i] = self.counts[i] as f64 / expected;
+            }
+        }
+        rscu
+    }
+
+    /// Effective number of codons (Wright, 1990), generalised to any code
+    ///
+    /// Families are grouped by their number of codons; a class without
+    /// estimates uses the mean of its neighbours for three-codon families
+    /// (as Wright does for isoleucine) and uniform usage otherwise.
+    pub fn effective_number_of_codons(&self) -> f64 {
+        let families: Vec<Vec<usize>> = codon_families(&self.code)
+            .into_iter()
+            .filter(|(aa, _)| *aa != b'*')
+            .map(|(_, codons)| codons)
+            .collect();
+        if self.sense_total() == 0 {
+            return f64::NAN;
+        }
+
+        // Homozygosity F of every family with at least two observations,
+        // grouped by family size
+        let mut homozygosity: BTreeMap<usize, Vec<f64>> = BTreeMap::new();
+        let mut sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        for codons in &families {
+            *sizes.entry(codons.len()).or_default() += 1;
+            let n: u64 = codons.iter().map(|&i| self.counts[i]).sum();
+            if codons.len() < 2 || n < 2 {
+                continue;
+            }
+            let n = n as f64;
+            let squares: f64 = codons
+                .iter()
+                .map(|&i| (self.counts[i] as f64 / n).powi(2))
+                .sum();
+            homozygosity
+                .entry(codons.len())
+                .or_default()
+                .push((n * squares - 1.0) / (n - 1.0));
+        }
+        let mean = |size: usize| {
+            homozygosity
+                .get(&size)
+                .map(|values| values.iter().sum::<f64>() / values.len() as f64)
+        };
+
+        let mut enc = 0.0;
+        for (&size, &count) in &sizes {
+            let f = match (size, mean(size)) {
+                (1, _) => 1.0,
+                (_, Some(f)) => f,
+                (3, None) => match (mean(2), mean(4)) {
+                    (Some(f2), Some(f4)) => (f2 + f4) / 2.0,
+                    _ => 1.0 / size as f64,
+                },
+                (_, None) => 1.0 / size as f64,
+            };
+            enc += count as f64 / f;
+        }
+        let sense_codons = families.iter().map(Vec::len).sum::<usize>() as f64;
+        enc.min(sense_codons)
+    }
+
+    /// GC content of the third position of sense codons, NaN without any
+    pub fn gc3_fraction(&self) -> f64 {
+        let sense_total = self.sense_total();
+        if sense_total == 0 {
+            return f64::NAN;
+        }
+        let mut gc = 0;
+        for index in (0..64).filter(|&i| self.code.amino_acid_at(i) != b'*') {
+            // Third base C or G: table index 1 or 3
+            if index % 2 == 1 {
+                gc += self.counts[index];
+            }
+        }
+        gc as f64 / sense_total as f64
+    }
+
+    /// Relative adaptiveness of every codon, as a CAI reference: its count
+    /// over that of the most used codon of its family. Codons of stops,
+    /// single-codon families and families absent from the reference are None.
+    pub fn weights(&self) -> [Option<f64>; 64] {
+        let mut weights = [None; 64];
+        for (amino_acid, codons) in codon_families(&self.code) {
+            let max = codons.iter().map(|&i| self.counts[i]).max().unwrap_or(0);
+            if amino_acid == b'*' || codons.len() < 2 || max == 0 {
+                continue;
+            }
+            for &i in &codons {
+                let count = match self.counts[i] {
+                    0 => MISSING_CODON_COUNT,
+                    count => count as f64,
+                };
+                weights[i] = Some(count / max as f64);
+            }
+        }
+        weights
+    }
+
+    /// Codon adaptation index given reference `weights`: the geometric mean
+    /// weight of the codons that have one, NaN when none has
+    pub fn adaptation_index(&self, weights: &[Option<f64>; 64]) -> f64 {
+        let mut log_sum = 0.0;
+        let mut length = 0;
+        for (count, weight) in self.counts.iter().zip(weights) {
+            if let Some(weight) = weight {
+                log_sum += *count as f64 * weight.ln();
+                length += count;
+            }
+        }
+        if length == 0 {
+            return f64::NAN;
+        }
+        (log_sum / length as f64).exp()
+    }
+
+    fn sense_total(&self) -> u64 {
+        (0..64)
+            .filter(|&i| self.code.amino_acid_at(i) != b'*')
+            .map(|i| self.counts[i])
+            .sum()
+    }
+
+    /// A dict of `value(index)` by codon in table order
+    fn codon_dict<'py, T: IntoPyObject<'py>>(
+        py: Python<'py>,
+        value: impl Fn(usize) -> T,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for index in 0..64 {
+            dict.set_item(codon_at(index), value(index))?;
+        }
+        Ok(dict)
+    }
+}
+
+/// A codon usage table given as a `CodonUsage` or codon counts
+#[derive(FromPyObject)]
+pub enum UsageTable<'py> {
+    Usage(PyRef<'py, CodonUsage>),
+    Counts(HashMap<String, u64>),
+}
+
+impl UsageTable<'_> {
+    /// The table as a `CodonUsage` under `code`
+    pub fn into_usage(self, code: &GeneticCode) -> PyResult<CodonUsage> {
+        match self {
+            UsageTable::Usage(usage) if usage.code == *code => Ok(usage.clone()),
+            UsageTable::Usage(_) => Err(PyValueError::new_err(
+                "The codon usage table has a different genetic code",
+            )),
+            UsageTable::Counts(counts) => {
+                let mut usage = CodonUsage {
+                    counts: [0; 64],
+                    code: code.clone(),
+                };
+                for (codon, count) in counts {
+                    usage.counts[parse_codon(&codon)?] += count;
+                }
+                Ok(usage)
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl CodonUsage {
+    #[getter]
+    fn counts<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        Self::codon_dict(py, |i| self.counts[i])
+    }
+
+    #[getter]
+    fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    #[getter]
+    fn genetic_code(&self) -> GeneticCode {
+        self.code.clone()
+    }
+
+    /// Codon frequencies, summing to one (NaN without codons).
+    fn frequencies<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let total = self.total() as f64;
+        Self::codon_dict(py, |i| self.counts[i] as f64 / total)
+    }
+
+    /// Relative synonymous codon usage of every codon.
+    ///
+    /// Returns
+    /// -------
+    /// dict of str to float
+    ///     The count of each codon over the mean count of its synonymous
+    ///     family; NaN when the amino acid (or stop) does not occur.
+    fn rscu<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let rscu = self.rscu_values();
+        Self::codon_dict(py, |i| rscu[i])
+    }
+
+    /// Effective number of codons (Wright, 1990), from 20 (one codon per
+    /// amino acid) to 61 (uniform usage) in the standard code; NaN without
+    /// sense codons.
+    fn enc(&self) -> f64 {
+        self.effective_number_of_codons()
+    }
+
+    /// GC content of the third position of sense codons; NaN without sense
+    /// codons.
+    fn gc3(&self) -> f64 {
+        self.gc3_fraction()
+    }
+
+    /// Relative adaptiveness of every codon when used as a CAI reference.
+    ///
+    /// Returns
+    /// -------
+    /// dict of str to float or None
+    ///     Each codon count over that of the most used synonymous codon.
+    ///     Unused codons count 0.5. Stops, single-codon amino acids and
+    ///     amino acids absent from the reference are None.
+    fn relative_adaptiveness<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let weights = self.weights();
+        Self::codon_dict(py, |i| weights[i])
+    }
+
+    /// Codon adaptation index (Sharp & Li, 1987) against a reference.
+    ///
+    /// Parameters
+    /// ----------
+    /// reference : CodonUsage or dict of str to int
+    ///     The codon usage of highly expressed genes.
+    ///
+    /// Returns
+    /// -------
+    /// float
+    ///     The geometric mean relative adaptiveness of the codons, from 0 to
+    ///     1; NaN when no codon has a weight.
+    fn cai(&self, reference: UsageTable<'_>) -> PyResult<f64> {
+        let weights = reference.into_usage(&self.code)?.weights();
+        Ok(self.adaptation_index(&weights))
+    }
+
+    fn __add__(&self, other: &CodonUsage) -> PyResult<CodonUsage> {
+        if self.code != other.code {
+            return Err(PyValueError::new_err(
+                "Cannot add codon usages with different genetic codes",
+            ));
+        }
+        let mut sum = self.clone();
+        for (count, other) in sum.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        Ok(sum)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CodonUsage(total={}, genetic_code={})",
+            self.total(),
+            self.code
+                .id()
+                .map_or_else(|| "custom".to_string(), |id| id.to_string())
+        )
+    }
+}
+
+/// Count the codons of a sequence.
+///
+/// Parameters
+/// ----------
+/// sequence : str
+///     A coding DNA or RNA sequence, read in frame 1. Codons with ambiguous
+///     bases or gaps are skipped.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The code defining synonymous codons.
+///
+/// Returns
+/// -------
+/// CodonUsage
+#[pyfunction]
+#[pyo3(signature = (sequence, genetic_code=CodeSelection::Id(1)))]
+pub fn codon_usage(sequence: &str, genetic_code: CodeSelection) -> PyResult<CodonUsage> {
+    Ok(CodonUsage::from_sequence(
+        sequence.as_bytes(),
+        genetic_code.resolve()?,
+    ))
+}
+
+/// Count the codons of every sequence in a FASTA/FASTQ file.
+///
+/// Parameters
+/// ----------
+/// input_file : str, pathlib.Path, bytes or binary file object
+///     Coding sequences, read in frame 1. Use '-' for stdin.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The code defining synonymous codons.
+/// per_sequence : bool, default: False
+///     Return the usage of each sequence instead of their sum.
+/// threads : int, optional
+///     The number of worker threads.
+///
+/// Returns
+/// -------
+/// CodonUsage or list of (str, CodonUsage)
+///     The summed usage, or `(id, usage)` pairs in input order.
+#[pyfunction]
+#[pyo3(signature = (input_file, genetic_code=CodeSelection::Id(1), per_sequence=false, threads=None))]
+pub fn codon_usage_file(
+    py: Python<'_>,
+    input_file: InputSource,
+    genetic_code: CodeSelection,
+    per_sequence: bool,
+    threads: Option<usize>,
+) -> PyResult<PyObject> {
+    let code = genetic_code.resolve()?;
+    let (usages, total) = pool::run(py, threads, |interrupt| {
+        let mut usages = Vec::new();
+        let mut total = [0u64; 64];
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut reader = RecordReader::open(input_file)?.peekable();
+        while let Some(record) = reader.next() {
+            interrupt.check()?;
+            batch.push(record?);
+            if batch.len() == BATCH_SIZE || reader.peek().is_none() {
+                let counts: Vec<[u64; 64]> = batch
+                    .par_iter()
+                    .map(|record| count_codons(&record.seq))
+                    .collect();
+                for (record, counts) in batch.iter().zip(counts) {
+                    for (sum, count) in total.iter_mut().zip(counts) {
+                        *sum += count;
+                    }
+                    if per_sequence {
+                        let id = String::from_utf8_lossy(&record.id).into_owned();
+                        usages.push((
+                            id,
+                            CodonUsage {
+                                counts,
+                                code: code.clone(),
+                            },
+                        ));
+                    }
+                }
+                batch.clear();
+            }
+        }
+        Ok((usages, total))
+    })?;
+
+    if per_sequence {
+        Ok(usages.into_pyobject(py)?.into_any().unbind())
+    } else {
+        let usage = CodonUsage {
+            counts: total,
+            code,
+        };
+        Ok(usage.into_pyobject(py)?.into_any().unbind())
+    }
+}
+
+/// Statistics of one sequence in `codon_usage_columns`
+struct UsageRow {
+    counts: Vec<u64>,
+    rscu: Vec<f64>,
+    enc: f64,
+    gc3: f64,
+    cai: Option<f64>,
+}
+
+/// One column of `codon_usage_columns`, null where the sequence was
+fn column<T>(rows: &[Option<UsageRow>], value: impl Fn(&UsageRow) -> T) -> Vec<Option<T>> {
+    rows.iter().map(|row| row.as_ref().map(&value)).collect()
+}
+
+/// Codon usage statistics of many sequences at once, as columns.
+///
+/// Meant for `polars.Series.map_batches`: the result converts directly to a
+/// `polars.DataFrame`.
+///
+/// Parameters
+/// ----------
+/// sequences : list of str or None
+///     Coding sequences, read in frame 1. None gives null statistics.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The code defining synonymous codons.
+/// reference : CodonUsage or dict of str to int, optional
+///     A CAI reference. Without one, `cai` is not computed.
+/// threads : int, optional
+///     The number of worker threads.
+///
+/// Returns
+/// -------
+/// dict of str to list
+///     `counts` (64 codon counts in NCBI order), `rscu` (64 values in the
+///     same order), `enc`, `gc3` and, with a reference, `cai`.
+#[pyfunction]
+#[pyo3(signature = (sequences, genetic_code=CodeSelection::Id(1), reference=None, threads=None))]
+pub fn codon_usage_columns<'py>(
+    py: Python<'py>,
+    sequences: Vec<Option<String>>,
+    genetic_code: CodeSelection,
+    reference: Option<UsageTable<'py>>,
+    threads: Option<usize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let code = genetic_code.resolve()?;
+    let weights = reference
+        .map(|r| r.into_usage(&code).map(|usage| usage.weights()))
+        .transpose()?;
+
+    let rows: Vec<Option<UsageRow>> = pool::run(py, threads, |_| {
+        Ok(sequences
+            .par_iter()
+            .map(|sequence| {
+                let usage = CodonUsage::from_sequence(sequence.as_ref()?.as_bytes(), code.clone());
+                Some(UsageRow {
+                    counts: usage.counts.to_vec(),
+                    rscu: usage.rscu_values().to_vec(),
+                    enc: usage.effective_number_of_codons(),
+                    gc3: usage.gc3_fraction(),
+                    cai: weights.as_ref().map(|w| usage.adaptation_index(w)),
+                })
+            })
+            .collect())
+    })?;
+
+    let columns = PyDict::new(py);
+    columns.set_item("counts", column(&rows, |row| row.counts.clone()))?;
+    columns.set_item("rscu", column(&rows, |row| row.rscu.clone()))?;
+    columns.set_item("enc", column(&rows, |row| row.enc))?;
+    columns.set_item("gc3", column(&rows, |row| row.gc3))?;
+    if weights.is_some() {
+        let cai: Vec<Option<f64>> = rows
+            .iter()
+            .map(|row| row.as_ref().and_then(|row| row.cai))
+            .collect();
+        columns.set_item("cai", cai)?;
+    }
+    Ok(columns)
+}
@@ -0,0 +1,342 @@
+//! Transparent compression for file inputs and outputs
+//!
+//! Inputs are sniffed from their magic bytes; outputs use the codec named by
+//! the caller or implied by the file extension.
+
+use bzip2::read::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Crc;
+use liblzma::read::XzDecoder;
+use liblzma::write::XzEncoder;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+const GZ_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZ_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZST_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Supported compression formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bgzf,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Parse a user supplied codec name
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "bgzf" | "bgzip" | "bgz" => Ok(Compression::Bgzf),
+            "bzip2" | "bz2" => Ok(Compression::Bzip2),
+            "xz" | "lzma" => Ok(Compression::Xz),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown compression '{}'. Use one of: none, gzip, bgzf, bzip2, xz, zstd.",
+                name
+            ))),
+        }
+    }
+
+    /// Guess the codec from a file extension
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".bgz") || lower.ends_with(".bgzf") {
+            Compression::Bgzf
+        } else if lower.ends_with(".gz") {
+            Compression::Gzip
+        } else if lower.ends_with(".bz2") {
+            Compression::Bzip2
+        } else if lower.ends_with(".xz") {
+            Compression::Xz
+        } else if lower.ends_with(".zst") || lower.ends_with(".zstd") {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Detect the codec from the first bytes of a stream
+    ///
+    /// BGZF files are reported as gzip since both decode the same way.
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(GZ_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(BZ_MAGIC) {
+            Compression::Bzip2
+        } else if magic.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else if magic.starts_with(ZST_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wrap a reader in the decoder matching its magic bytes
+pub fn wrap_input<R: Read + Send + 'static>(reader: R) -> io::Result<Box<dyn Read + Send>> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip | Compression::Bgzf => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+    })
+}
+
+/// A possibly compressed output stream
+///
+/// `finish` must be called to write the trailer of the compressed formats.
+pub enum OutputStream {
+    Plain(Box<dyn Write + Send>),
+    Gzip(GzEncoder<Box<dyn Write + Send>>),
+    Bgzf(BgzfWriter<Box<dyn Write + Send>>),
+    Bzip2(BzEncoder<Box<dyn Write + Send>>),
+    Xz(XzEncoder<Box<dyn Write + Send>>),
+    Zstd(zstd::Encoder<'static, Box<dyn Write + Send>>),
+}
+
+impl OutputStream {
+    pub fn new(inner: Box<dyn Write + Send>, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => OutputStream::Plain(inner),
+            Compression::Gzip => {
+                OutputStream::Gzip(GzEncoder::new(inner, flate2::Compression::default()))
+            }
+            Compression::Bgzf => OutputStream::Bgzf(BgzfWriter::new(inner)),
+            Compression::Bzip2 => {
+                OutputStream::Bzip2(BzEncoder::new(inner, bzip2::Compression::default()))
+            }
+            Compression::Xz => OutputStream::Xz(XzEncoder::new(inner, 6)),
+            Compression::Zstd => OutputStream::Zstd(zstd::Encoder::new(inner, 0)?),
+        })
+    }
+
+    /// Write any trailer and flush the underlying writer
+    pub fn finish(self) -> io::Result<()> {
+        let mut inner = match self {
+            OutputStream::Plain(w) => w,
+            OutputStream::Gzip(w) => w.finish()?,
+            OutputStream::Bgzf(w) => w.finish()?,
+            OutputStream::Bzip2(w) => w.finish()?,
+            OutputStream::Xz(w) => w.finish()?,
+            OutputStream::Zstd(w) => w.finish()?,
+        };
+        inner.flush()
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            OutputStream::Plain(w) => w,
+            OutputStream::Gzip(w) => w,
+            OutputStream::Bgzf(w) => w,
+            OutputStream::Bzip2(w) => w,
+            OutputStream::Xz(w) => w,
+            OutputStream::Zstd(w) => w,
+        }
+    }
+}
+
+impl Write for OutputStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+/// Maximum uncompressed payload of a BGZF block, as used by htslib
+const BGZF_BLOCK_SIZE: usize = 0xff00;
+
+/// The empty block marking the end of a BGZF file
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02,
+    0x00, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Blocked gzip writer compatible with `bgzip`, so outputs can be indexed
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::with_capacity(BGZF_BLOCK_SIZE),
+        }
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&self.buffer)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+
+        // Header with the BC extra subfield holding the total block size - 1
+        let block_size = compressed.len() + 26;
+        let mut header = [
+            0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43,
+            0x02, 0x00, 0x00, 0x00,
+        ];
+        header[16..18].copy_from_slice(&((block_size - 1) as u16).to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&compressed)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Write the remaining data and the EOF marker block
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            self.write_block()?;
+        }
+        self.inner.write_all(&BGZF_EOF)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(BGZF_BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == BGZF_BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.write_block()?;
+        }
+        self.inner.flush()
+    }
+}
+
+/// Whether `header` starts a BGZF block, i.e. a gzip member with a `BC` extra
+/// subfield
+pub fn is_bgzf(header: &[u8]) -> bool {
+    header.len() >= 16
+        && header.starts_with(GZ_MAGIC)
+        && header[3] & 0x04 != 0
+        && header[12] == b'B'
+        && header[13] == b'C'
+}
+
+/// Block-by-block BGZF reader that reports where each block starts
+///
+/// Used to build `.gzi` indices and to read from a block offset taken from one.
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    block: Vec<u8>,
+    position: usize,
+    /// Compressed offset of the next block
+    compressed_offset: u64,
+    /// Uncompressed offset of the next block
+    uncompressed_offset: u64,
+    /// `(compressed, uncompressed)` offsets of the block starts seen so far,
+    /// excluding the first one
+    pub block_starts: Vec<(u64, u64)>,
+}
+
+impl<R: Read> BgzfReader<R> {
+    /// Read blocks from `inner`, which is positioned at the start of a block
+    /// with the given offsets
+    pub fn new(inner: R, compressed_offset: u64, uncompressed_offset: u64) -> Self {
+        Self {
+            inner,
+            block: Vec::new(),
+            position: 0,
+            compressed_offset,
+            uncompressed_offset,
+            block_starts: Vec::new(),
+        }
+    }
+
+    /// Decompress the next block, returning false at the end of the input
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 18];
+        let mut filled = 0;
+        while filled < header.len() {
+            let n = self.inner.read(&mut header[filled..])?;
+            if n == 0 {
+                if filled == 0 {
+                    return Ok(false);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated BGZF block header",
+                ));
+            }
+            filled += n;
+        }
+        if !is_bgzf(&header) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a BGZF block; compress the file with bgzip or compression='bgzf'",
+            ));
+        }
+        let block_size = u16::from_le_bytes([header[16], header[17]]) as usize + 1;
+        if block_size < header.len() + 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid BGZF block size",
+            ));
+        }
+        let mut rest = vec![0u8; block_size - header.len()];
+        self.inner.read_exact(&mut rest)?;
+        let (data, trailer) = rest.split_at(rest.len() - 8);
+        let expected_size = u32::from_le_bytes(trailer[4..8].try_into().unwrap()) as usize;
+
+        self.block.clear();
+        self.block.reserve(expected_size);
+        DeflateDecoder::new(data).read_to_end(&mut self.block)?;
+        if self.block.len() != expected_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "BGZF block size does not match its trailer",
+            ));
+        }
+        self.position = 0;
+        self.compressed_offset += block_size as u64;
+        self.uncompressed_offset += expected_size as u64;
+        if expected_size > 0 {
+            self.block_starts
+                .push((self.compressed_offset, self.uncompressed_offset));
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.block.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.position);
+        buf[..n].copy_from_slice(&self.block[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
@@ -1,250 +1,1080 @@
-use pyo3::exceptions::PyValueError;
+use pyo3::exceptions::{PyIOError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyInt, PyString};
 use rayon::prelude::*;
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{self, BufRead, BufWriter, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::compression::OutputStream;
+use crate::fastx_io::{RecordReader, RecordWriter, SeqRecord, DEFAULT_LINE_LENGTH};
+use crate::pool::{self, Interrupt};
+use crate::pyfile::{InputSource, OutputTarget};
+use crate::seq_utils::complement_base;
+use crate::spill::{self, spill_error, Partition, SpillDir};
+
+/// Number of records hashed together
+const BATCH_SIZE: usize = 4096;
+
+/// Number of shards of the kept hashes, each filled by one thread at a time
+const SHARDS: usize = 64;
+
+/// Number of partition files records are spilled to
+const SPILL_PARTITIONS: usize = 64;
+
+/// How a sequence was turned into its key: reverse complemented or not,
+/// then rotated to start at `rotation`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Placement {
+    reverse: bool,
+    rotation: usize,
+}
+
+impl Placement {
+    /// Where a duplicate lies on the kept sequence of length `len`, as
+    /// `(strand, offset)`: the duplicate is the kept sequence rotated to
+    /// start at `offset`, reverse complemented on the '-' strand
+    fn relative_to(&self, kept: &Placement, len: usize) -> (char, usize) {
+        let shift = (kept.rotation + len - self.rotation) % len.max(1);
+        let offset = if kept.reverse { (len - shift) % len.max(1) } else { shift };
+        let strand = if self.reverse == kept.reverse { '+' } else { '-' };
+        (strand, offset)
+    }
+}
+
+/// Start of the lexicographically smallest rotation of `seq`, the first one
+/// for periodic sequences
+///
+/// Uses the linear-time minimum expression algorithm, which like Booth's
+/// compares two candidate starts and skips every start they rule out.
+fn least_rotation(seq: &[u8]) -> usize {
+    let n = seq.len();
+    let (mut i, mut j, mut k) = (0, 1, 0);
+    while i < n && j < n && k < n {
+        let (a, b) = (seq[(i + k) % n], seq[(j + k) % n]);
+        if a == b {
+            k += 1;
+            continue;
+        }
+        if a > b {
+            i += k + 1;
+        } else {
+            j += k + 1;
+        }
+        if i == j {
+            j += 1;
+        }
+        k = 0;
+    }
+    i.min(j)
+}
+
+/// `seq` rotated to its smallest rotation
+fn rotate_least(seq: &[u8]) -> (Vec<u8>, usize) {
+    let rotation = least_rotation(seq);
+    let mut rotated = Vec::with_capacity(seq.len());
+    rotated.extend_from_slice(&seq[rotation..]);
+    rotated.extend_from_slice(&seq[..rotation]);
+    (rotated, rotation)
+}
+
+/// Uppercase a sequence and, with `u_as_t`, turn U into T
+fn normalize(seq: &[u8], u_as_t: bool) -> Vec<u8> {
+    seq.iter().map(|&base| fold_base(base, u_as_t)).collect()
+}
+
+/// Whether a normalized sequence is RNA, that is has any U
+fn is_rna(key: &[u8]) -> bool {
+    key.contains(&b'U')
+}
+
+/// Complement a base of a normalized sequence, pairing A with U in RNA and
+/// with T in DNA
+///
+/// Bytes that are not nucleotide codes, and T in RNA or U in DNA, are kept,
+/// so distinct sequences of the same kind have distinct complements.
+fn complement_key_base(base: u8, rna: bool) -> u8 {
+    match base {
+        b'A' if rna => b'U',
+        b'U' if rna => b'A',
+        b'T' if rna => b'T',
+        b'A' | b'C' | b'G' | b'T' | b'R' | b'Y' | b'M' | b'K' | b'S' | b'W' | b'B' | b'V' | b'D' | b'H' | b'N' => {
+            complement_base(base)
+        }
+        base => base,
+    }
+}
+
+/// Reverse complement a normalized sequence, as RNA when `rna` is set
+fn reverse_complement_key(key: &[u8], rna: bool) -> Vec<u8> {
+    key.iter().rev().map(|&base| complement_key_base(base, rna)).collect()
+}
+
+/// The key of a sequence in `deduplicate_by_seq`, and how it was obtained
+///
+/// The canonical key is the smaller of the sequence and its reverse
+/// complement. Circular keys are rotated to their smallest rotation first.
+/// Keys end with a byte telling RNA (any U left after normalizing) from DNA,
+/// as an RNA and a DNA sequence can have the same reverse complement.
+fn sequence_key(seq: &[u8], canonical: bool, circular: bool, u_as_t: bool) -> (Vec<u8>, Placement) {
+    let key = normalize(seq, u_as_t);
+    let rna = is_rna(&key);
+    let orient = |key: Vec<u8>, reverse: bool| {
+        if circular {
+            let (rotated, rotation) = rotate_least(&key);
+            (rotated, Placement { reverse, rotation })
+        } else {
+            (key, Placement { reverse, rotation: 0 })
+        }
+    };
+    let (mut key, placement) = if canonical {
+        let reverse = orient(reverse_complement_key(&key, rna), true);
+        let forward = orient(key, false);
+        if reverse.0 < forward.0 {
+            reverse
+        } else {
+            forward
+        }
+    } else {
+        orient(key, false)
+    };
+    key.push(rna as u8);
+    (key, placement)
+}
 
 /// Deduplicate sequences by sequence
+///
+/// Sequences are compared case-insensitively by a 128-bit hash of their key
+/// and one record of every sequence is written. Files are read in order and
+/// the kept records are written in input order, so the output is the same on
+/// every run.
+///
+/// # Arguments
+/// * `canonical` - Also collapse reverse complements, comparing the smaller
+///   of each nucleotide sequence and its reverse complement
+/// * `u_as_t` - Read U as T, so DNA and RNA copies collapse
+/// * `circular` - Also collapse rotations, comparing circular sequences from
+///   their lexicographically smallest rotation
+/// * `duplicates_file` - Write a tab-separated table of the removed records:
+///   `duplicate`, `kept` (their names), `strand` and `offset`. The duplicate
+///   is the kept sequence rotated to start at `offset` (0-based) and reverse
+///   complemented on the `-` strand
+/// * `spill_dir` - Directory for temporary partition files, for inputs larger
+///   than memory. The output is the same as in memory
+/// * `keep` - Which record of every sequence to keep: `first`, `last`,
+///   `longest_header`, `best_quality` (highest mean quality, FASTQ only) or
+///   the index of the input file whose records are preferred. Ties go to the
+///   first record
 #[pyfunction]
+#[pyo3(signature = (input_files, output_file, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None, canonical=false, u_as_t=false, circular=false, duplicates_file=None, spill_dir=None, keep=KeepArg::Policy(String::from("first"))))]
+#[allow(clippy::too_many_arguments)]
 pub fn deduplicate_by_seq(
-    input_files: Vec<String>,
-    output_file: &str,
+    py: Python<'_>,
+    input_files: Vec<InputSource>,
+    output_file: OutputTarget,
     threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
+    canonical: bool,
+    u_as_t: bool,
+    circular: bool,
+    duplicates_file: Option<OutputTarget>,
+    spill_dir: Option<PathBuf>,
+    keep: KeepArg,
 ) -> PyResult<usize> {
-    // Set up parallelism
-    let threads = threads.unwrap_or_else(|| rayon::current_num_threads());
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build_global()
-        .map_err(|e| PyValueError::new_err(format!("Failed to set up thread pool: {}", e)))?;
-
-    // Output file
-    let output_path = Path::new(output_file);
-    let output_file = File::create(output_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to create output file: {}", e)))?;
-    let writer = Arc::new(Mutex::new(BufWriter::new(output_file)));
-    
-    // Use a HashSet to track unique sequences
-    let unique_seqs = Arc::new(Mutex::new(HashSet::new()));
-    let count = Arc::new(Mutex::new(0));
-    
-    // Process each input file
-    input_files.par_iter().for_each(|input_file| {
-        // Open the input file
-        let input_path = Path::new(input_file);
-        let file = match File::open(input_path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Failed to open input file {}: {}", input_file, e);
-                return;
-            }
+    let keep = keep.resolve(input_files.len())?;
+    let mut duplicates = duplicates_file.map(|target| target.create(None)).transpose()?;
+    if let Some(out) = duplicates.as_mut() {
+        writeln!(out, "duplicate\tkept\tstrand\toffset").map_err(duplicates_error)?;
+    }
+    deduplicate(py, input_files, output_file, threads, line_length, compression, duplicates, spill_dir, keep, |record| {
+        let (key, placement) = sequence_key(&record.seq, canonical, circular, u_as_t);
+        (xxh3_128(&key), placement)
+    })
+}
+
+fn duplicates_error(e: std::io::Error) -> PyErr {
+    PyIOError::new_err(format!("Error writing duplicates: {}", e))
+}
+
+/// Deduplicate sequences by ID
+///
+/// # Arguments
+/// * `spill_dir` - Directory for temporary partition files, for inputs larger
+///   than memory
+/// * `keep` - Which record of every ID to keep, as in `deduplicate_by_seq`
+#[pyfunction]
+#[pyo3(signature = (input_files, output_file, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None, spill_dir=None, keep=KeepArg::Policy(String::from("first"))))]
+#[allow(clippy::too_many_arguments)]
+pub fn deduplicate_by_id(
+    py: Python<'_>,
+    input_files: Vec<InputSource>,
+    output_file: OutputTarget,
+    threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
+    spill_dir: Option<PathBuf>,
+    keep: KeepArg,
+) -> PyResult<usize> {
+    let keep = keep.resolve(input_files.len())?;
+    // The ID is the first word of the header
+    deduplicate(py, input_files, output_file, threads, line_length, compression, None, spill_dir, keep, |record| {
+        (xxh3_128(record.name()), Placement::default())
+    })
+}
+
+/// Multiplier of the polynomial rolling hash of seeds
+const SEED_HASH_BASE: u64 = 0x0100_0000_01b3;
+
+/// Polynomial hash of a seed, updated in constant time along a sequence
+fn seed_hash(seed: &[u8]) -> u64 {
+    seed.iter()
+        .fold(0, |hash, &base| hash.wrapping_mul(SEED_HASH_BASE).wrapping_add(base as u64))
+}
+
+/// Hasher of seed and key hashes, which only need their bits mixed
+#[derive(Default)]
+struct MixHasher(u64);
+
+impl Hasher for MixHasher {
+    fn finish(&self) -> u64 {
+        let mixed = (self.0 ^ (self.0 >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        mixed ^ (mixed >> 33)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = seed_hash(bytes);
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+
+    fn write_u128(&mut self, hash: u128) {
+        self.0 = (hash ^ (hash >> 64)) as u64;
+    }
+}
+
+/// Sequences, with the offset of their seed in them, by seed hash
+type Seeds = HashMap<u64, Vec<(usize, usize)>, BuildHasherDefault<MixHasher>>;
+
+/// Number of seeds sampled along each sequence, the rarest of which indexes
+/// it
+const SEEDS_PER_QUERY: usize = 8;
+
+/// Uppercase a base and, with `u_as_t`, turn U into T
+fn fold_base(base: u8, u_as_t: bool) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'U' if u_as_t => b'T',
+        base => base,
+    }
+}
+
+/// Whether two sequences are equal after folding
+fn folded_eq(a: &[u8], b: &[u8], u_as_t: bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&x, &y)| fold_base(x, u_as_t) == fold_base(y, u_as_t))
+}
+
+/// Sequences indexed by one seed each, grouped by seed length
+///
+/// Sequences longer than `k` bases are indexed by the rarest of
+/// `SEEDS_PER_QUERY` evenly spaced seeds of `k` bases, so sequences sharing
+/// a low-complexity prefix do not all land in the same bucket. Shorter ones
+/// are their own seed.
+struct SeedIndex {
+    seeds: Vec<(usize, Seeds)>,
+    u_as_t: bool,
+}
+
+impl SeedIndex {
+    /// Index the sequences `seqs[query]` for every query in `queries`
+    fn new(seqs: &[&[u8]], queries: &[usize], k: usize, u_as_t: bool) -> Self {
+        let fold_hash = |seed: &[u8]| {
+            seed.iter()
+                .fold(0u64, |hash, &base| hash.wrapping_mul(SEED_HASH_BASE).wrapping_add(fold_base(base, u_as_t) as u64))
         };
-        
-        let reader = io::BufReader::new(file);
-        let mut lines = reader.lines();
-        
-        // Process FASTA file
-        let mut current_header = String::new();
-        let mut current_sequence = String::new();
-        
-        while let Some(Ok(line)) = lines.next() {
-            if line.starts_with('>') {
-                // Process previous sequence if there is one
-                if !current_header.is_empty() && !current_sequence.is_empty() {
-                    let normalized_seq = current_sequence.to_uppercase();
-                    let mut unique_seqs_lock = unique_seqs.lock().unwrap();
-                    
-                    if !unique_seqs_lock.contains(&normalized_seq) {
-                        unique_seqs_lock.insert(normalized_seq);
-                        drop(unique_seqs_lock); // Release lock before writing
-                        
-                        // Write to output file
-                        let mut writer_lock = writer.lock().unwrap();
-                        if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                            eprintln!("Error writing header: {}", e);
-                            return;
-                        }
-                        
-                        // Write sequence with line breaks every 60 characters
-                        for chunk in current_sequence.as_bytes().chunks(60) {
-                            if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                                eprintln!("Error writing sequence: {}", e);
-                                return;
-                            }
-                        }
-                        
-                        // Increment count
-                        let mut count_lock = count.lock().unwrap();
-                        *count_lock += 1;
-                    }
+        // The (hash, offset) of the seeds sampled along every query
+        let sampled: Vec<Vec<(u64, usize)>> = queries
+            .par_iter()
+            .map(|&query| {
+                let seq = seqs[query];
+                if seq.len() <= k {
+                    return vec![(fold_hash(seq), 0)];
+                }
+                let mut offsets: Vec<usize> =
+                    (0..SEEDS_PER_QUERY).map(|i| i * (seq.len() - k) / (SEEDS_PER_QUERY - 1)).collect();
+                offsets.dedup();
+                offsets.into_iter().map(|offset| (fold_hash(&seq[offset..offset + k]), offset)).collect()
+            })
+            .collect();
+        let mut counts: HashMap<u64, u32, BuildHasherDefault<MixHasher>> = HashMap::default();
+        for (&query, seeds) in queries.iter().zip(&sampled) {
+            if seqs[query].len() > k {
+                for (hash, _) in seeds {
+                    *counts.entry(*hash).or_default() += 1;
                 }
-                
-                // Extract header
-                current_header = line[1..].trim().to_string();
-                current_sequence = String::new();
-            } else {
-                // Append to current sequence
-                current_sequence.push_str(line.trim());
             }
         }
-        
-        // Process the last sequence
-        if !current_header.is_empty() && !current_sequence.is_empty() {
-            let normalized_seq = current_sequence.to_uppercase();
-            let mut unique_seqs_lock = unique_seqs.lock().unwrap();
-            
-            if !unique_seqs_lock.contains(&normalized_seq) {
-                unique_seqs_lock.insert(normalized_seq);
-                drop(unique_seqs_lock); // Release lock before writing
-                
-                // Write to output file
-                let mut writer_lock = writer.lock().unwrap();
-                if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                    eprintln!("Error writing header: {}", e);
-                    return;
+
+        let mut by_length: HashMap<usize, Seeds> = HashMap::new();
+        for (&query, seeds) in queries.iter().zip(&sampled) {
+            // The first of the rarest seeds
+            let &(hash, offset) = seeds
+                .iter()
+                .min_by_key(|(hash, offset)| (counts.get(hash).copied().unwrap_or(0), *offset))
+                .unwrap();
+            by_length
+                .entry(k.min(seqs[query].len()))
+                .or_default()
+                .entry(hash)
+                .or_default()
+                .push((query, offset));
+        }
+        let mut seeds: Vec<_> = by_length.into_iter().collect();
+        seeds.sort_by_key(|(length, _)| *length);
+        SeedIndex { seeds, u_as_t }
+    }
+
+    /// Call `hit(query, strand, offset)` for every sequence found in
+    /// `seqs[container]`, or its reverse complement with `canonical`, at
+    /// `offset` on the container's forward strand. A sequence is found in
+    /// longer ones and in equal ones that come before it; those for which
+    /// `skip` is true are not checked.
+    fn scan(
+        &self,
+        seqs: &[&[u8]],
+        container: usize,
+        canonical: bool,
+        skip: impl Fn(usize) -> bool,
+        mut hit: impl FnMut(usize, char, usize),
+    ) {
+        let seq = normalize(seqs[container], self.u_as_t);
+        let reverse = canonical.then(|| reverse_complement_key(&seq, is_rna(&seq)));
+        let strands = [('+', Some(seq.as_slice())), ('-', reverse.as_deref())];
+        for (strand, oriented) in strands {
+            let Some(oriented) = oriented else { continue };
+            for (length, seeds) in &self.seeds {
+                if *length > oriented.len() {
+                    break;
                 }
-                
-                // Write sequence with line breaks every 60 characters
-                for chunk in current_sequence.as_bytes().chunks(60) {
-                    if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                        eprintln!("Error writing sequence: {}", e);
-                        return;
+                // Empty sequences only need to be looked up once
+                let last = if *length == 0 { 0 } else { oriented.len() - length };
+                let leading = SEED_HASH_BASE.wrapping_pow(length.saturating_sub(1) as u32);
+                let mut hash = seed_hash(&oriented[..*length]);
+                for position in 0..=last {
+                    if position > 0 {
+                        let (out, next) = (oriented[position - 1], oriented[position + length - 1]);
+                        hash = hash
+                            .wrapping_sub((out as u64).wrapping_mul(leading))
+                            .wrapping_mul(SEED_HASH_BASE)
+                            .wrapping_add(next as u64);
+                    }
+                    // Candidates sharing the seed hash are verified in full
+                    let Some(queries) = seeds.get(&hash) else {
+                        continue;
+                    };
+                    for &(query, seed_offset) in queries {
+                        let query_seq = seqs[query];
+                        let Some(start) = position.checked_sub(seed_offset) else {
+                            continue;
+                        };
+                        let end = start + query_seq.len();
+                        if query == container
+                            || end > oriented.len()
+                            || (query_seq.len() == seq.len() && query < container)
+                            || skip(query)
+                            || !oriented[start..end]
+                                .iter()
+                                .zip(query_seq)
+                                .all(|(&base, &query_base)| base == fold_base(query_base, self.u_as_t))
+                        {
+                            continue;
+                        }
+                        let offset = match strand {
+                            '+' => start,
+                            _ => seq.len() - end,
+                        };
+                        hit(query, strand, offset);
                     }
                 }
-                
-                // Increment count
-                let mut count_lock = count.lock().unwrap();
-                *count_lock += 1;
             }
         }
-    });
-    
-    // Finalize and return count
-    let final_count = *count.lock().unwrap();
-    Ok(final_count)
+    }
 }
 
-/// Deduplicate sequences by ID
+/// Deduplicate sequences contained in other sequences
+///
+/// A sequence is removed when it is a substring of a longer sequence, or
+/// equal to an earlier one, after uppercasing. Copies of a sequence are set
+/// aside first, then every distinct sequence is indexed by one seed and
+/// looked up along all others. The records are held in memory and unique
+/// records are written in input order.
+///
+/// # Arguments
+/// * `canonical` - Also look for sequences on the reverse strand
+/// * `u_as_t` - Read U as T, so DNA and RNA copies collapse
+/// * `k` - Seed length. Longer seeds give fewer candidate matches to verify
+/// * `duplicates_file` - Write a tab-separated table of the removed records:
+///   `duplicate`, `kept` (their names), `strand` and `offset`. The duplicate
+///   is the kept sequence from `offset` (0-based), reverse complemented on
+///   the `-` strand
 #[pyfunction]
-pub fn deduplicate_by_id(
-    input_files: Vec<String>,
-    output_file: &str,
+#[pyo3(signature = (input_files, output_file, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None, canonical=true, u_as_t=false, k=31, duplicates_file=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn deduplicate_by_containment(
+    py: Python<'_>,
+    input_files: Vec<InputSource>,
+    output_file: OutputTarget,
     threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
+    canonical: bool,
+    u_as_t: bool,
+    k: usize,
+    duplicates_file: Option<OutputTarget>,
 ) -> PyResult<usize> {
-    // Set up parallelism
-    let threads = threads.unwrap_or_else(|| rayon::current_num_threads());
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build_global()
-        .map_err(|e| PyValueError::new_err(format!("Failed to set up thread pool: {}", e)))?;
-
-    // Output file
-    let output_path = Path::new(output_file);
-    let output_file = File::create(output_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to create output file: {}", e)))?;
-    let writer = Arc::new(Mutex::new(BufWriter::new(output_file)));
-    
-    // Use a HashSet to track unique sequence IDs
-    let unique_ids = Arc::new(Mutex::new(HashSet::new()));
-    let count = Arc::new(Mutex::new(0));
-    
-    // Process each input file
-    input_files.par_iter().for_each(|input_file| {
-        // Open the input file
-        let input_path = Path::new(input_file);
-        let file = match File::open(input_path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Failed to open input file {}: {}", input_file, e);
-                return;
+    if k == 0 {
+        return Err(PyValueError::new_err("k must be positive"));
+    }
+    let mut writer = RecordWriter::create(output_file, line_length, compression)?;
+    let mut duplicates = duplicates_file.map(|target| target.create(None)).transpose()?;
+
+    pool::run(py, threads, |interrupt| {
+        let mut records = Vec::new();
+        for input_file in input_files {
+            for record in RecordReader::open(input_file)? {
+                interrupt.check()?;
+                records.push(record?);
             }
-        };
-        
-        let reader = io::BufReader::new(file);
-        let mut lines = reader.lines();
-        
-        // Process FASTA file
-        let mut current_header = String::new();
-        let mut current_sequence = String::new();
-        
-        while let Some(Ok(line)) = lines.next() {
-            if line.starts_with('>') {
-                // Process previous sequence if there is one
-                if !current_header.is_empty() && !current_sequence.is_empty() {
-                    // Extract ID from header (first word after '>')
-                    let id = current_header.split_whitespace().next().unwrap_or("");
-                    
-                    let mut unique_ids_lock = unique_ids.lock().unwrap();
-                    if !unique_ids_lock.contains(id) {
-                        unique_ids_lock.insert(id.to_string());
-                        drop(unique_ids_lock); // Release lock before writing
-                        
-                        // Write to output file
-                        let mut writer_lock = writer.lock().unwrap();
-                        if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                            eprintln!("Error writing header: {}", e);
-                            return;
+        }
+        let seqs: Vec<&[u8]> = records.iter().map(|record| record.seq.as_slice()).collect();
+
+        // The first record with the same sequence as every record
+        let hashes: Vec<u128> = seqs.par_iter().map(|seq| xxh3_128(&normalize(seq, u_as_t))).collect();
+        let mut firsts: HashMap<u128, usize> = HashMap::new();
+        let mut first_copy: Vec<usize> = Vec::with_capacity(seqs.len());
+        for (index, hash) in hashes.into_iter().enumerate() {
+            let first = *firsts.entry(hash).or_insert(index);
+            first_copy.push(if folded_eq(seqs[first], seqs[index], u_as_t) { first } else { index });
+        }
+        let distinct: Vec<usize> = (0..seqs.len()).filter(|&index| first_copy[index] == index).collect();
+        let index = SeedIndex::new(&seqs, &distinct, k, u_as_t);
+
+        let removed: Vec<AtomicBool> = first_copy.iter().enumerate().map(|(i, &first)| AtomicBool::new(first != i)).collect();
+        distinct.par_iter().try_for_each(|&container| -> PyResult<()> {
+            interrupt.check()?;
+            let skip = |query: usize| removed[query].load(Ordering::Relaxed);
+            index.scan(&seqs, container, canonical, skip, |query, _, _| {
+                removed[query].store(true, Ordering::Relaxed)
+            });
+            Ok(())
+        })?;
+        let removed: Vec<bool> = removed.into_iter().map(AtomicBool::into_inner).collect();
+
+        let mut count = 0;
+        for (record, _) in records.iter().zip(&removed).filter(|(_, removed)| !**removed) {
+            writer.write_record(record)?;
+            count += 1;
+        }
+
+        if let Some(out) = duplicates.as_mut() {
+            // Report each removed record against the first unique record,
+            // strand and offset that contain it
+            let kept: Mutex<Vec<Option<(usize, char, usize)>>> = Mutex::new(vec![None; seqs.len()]);
+            distinct.par_iter().filter(|&&container| !removed[container]).try_for_each(
+                |&container| -> PyResult<()> {
+                    interrupt.check()?;
+                    index.scan(&seqs, container, canonical, |query| !removed[query], |query, strand, offset| {
+                        let mut kept = kept.lock().unwrap();
+                        let candidate = (container, strand, offset);
+                        if kept[query].is_none_or(|current| candidate < current) {
+                            kept[query] = Some(candidate);
                         }
-                        
-                        // Write sequence with line breaks every 60 characters
-                        for chunk in current_sequence.as_bytes().chunks(60) {
-                            if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                                eprintln!("Error writing sequence: {}", e);
-                                return;
+                    });
+                    Ok(())
+                },
+            )?;
+            let mut kept = kept.into_inner().unwrap();
+            // Copies are found where their first copy is, or in it when it is kept
+            for (copy, &first) in first_copy.iter().enumerate().filter(|(copy, first)| copy != *first) {
+                kept[copy] = if removed[first] { kept[first] } else { Some((first, '+', 0)) };
+            }
+
+            writeln!(out, "duplicate\tkept\tstrand\toffset").map_err(duplicates_error)?;
+            for (record, kept) in records.iter().zip(kept) {
+                if let Some((container, strand, offset)) = kept {
+                    out.write_all(record.name())
+                        .and_then(|_| out.write_all(b"\t"))
+                        .and_then(|_| out.write_all(records[container].name()))
+                        .and_then(|_| writeln!(out, "\t{}\t{}", strand, offset))
+                        .map_err(duplicates_error)?;
+                }
+            }
+        }
+        Ok(count)
+    })
+    .and_then(|count| {
+        writer.finish()?;
+        if let Some(duplicates) = duplicates {
+            duplicates.finish().map_err(duplicates_error)?;
+        }
+        Ok(count)
+    })
+}
+
+/// Which record of every key `deduplicate` keeps
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Keep {
+    First,
+    Last,
+    LongestHeader,
+    BestQuality,
+    /// The first record from the input file at this index, if any
+    File(usize),
+}
+
+impl Keep {
+    /// How good `record`, read at `index` from input file `file`, is to keep.
+    /// A record only replaces the kept one with a higher score, so ties go to
+    /// the first record
+    fn score(&self, index: u64, file: usize, record: &SeqRecord) -> PyResult<f64> {
+        Ok(match self {
+            Keep::First => 0.0,
+            Keep::Last => index as f64,
+            Keep::LongestHeader => record.id.len() as f64,
+            Keep::BestQuality => {
+                let qual = record.qual.as_ref().ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "keep='best_quality' needs FASTQ records, but '{}' has no qualities",
+                        String::from_utf8_lossy(record.name())
+                    ))
+                })?;
+                // The mean quality, the Phred offset making no difference
+                qual.iter().map(|&q| q as f64).sum::<f64>() / qual.len().max(1) as f64
+            }
+            Keep::File(preferred) => (file == *preferred) as u8 as f64,
+        })
+    }
+}
+
+/// The `keep` argument: a policy name or the index of the preferred input
+/// file
+pub enum KeepArg {
+    File(i64),
+    Policy(String),
+}
+
+impl<'py> FromPyObject<'py> for KeepArg {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        // bool is a subclass of int, but keep=True is no file index
+        if ob.is_instance_of::<PyBool>() {
+            return Err(PyValueError::new_err(
+                "keep must be a policy name or the index of an input file, not a bool",
+            ));
+        }
+        if let Ok(policy) = ob.downcast::<PyString>() {
+            return Ok(KeepArg::Policy(policy.to_str()?.to_string()));
+        }
+        if let Ok(file) = ob.downcast::<PyInt>() {
+            return file.extract().map(KeepArg::File).map_err(|_| {
+                PyValueError::new_err(format!("keep={} is not the index of an input file", file))
+            });
+        }
+        Err(PyValueError::new_err(format!(
+            "keep must be a policy name or the index of an input file, not {}",
+            ob.get_type().name()?
+        )))
+    }
+}
+
+impl KeepArg {
+    fn resolve(self, input_count: usize) -> PyResult<Keep> {
+        match self {
+            KeepArg::File(file) if (0..input_count as i64).contains(&file) => Ok(Keep::File(file as usize)),
+            KeepArg::File(file) => Err(PyValueError::new_err(format!(
+                "keep={} is not the index of an input file (there are {})",
+                file, input_count
+            ))),
+            KeepArg::Policy(policy) => match policy.to_ascii_lowercase().as_str() {
+                "first" => Ok(Keep::First),
+                "last" => Ok(Keep::Last),
+                "longest_header" => Ok(Keep::LongestHeader),
+                "best_quality" => Ok(Keep::BestQuality),
+                _ => Err(PyValueError::new_err(format!(
+                    "Invalid keep policy '{}'. Expected 'first', 'last', 'longest_header', 'best_quality' or the index of an input file",
+                    policy
+                ))),
+            },
+        }
+    }
+}
+
+/// The kept record of a key: its name, only needed for the duplicates
+/// table, and placement
+#[derive(Clone)]
+struct Kept {
+    name: Vec<u8>,
+    placement: Placement,
+}
+
+/// The record kept so far for a key
+struct Candidate {
+    index: u64,
+    score: f64,
+    kept: Kept,
+    /// The record itself, when it is only written once all records are read
+    record: Option<SeqRecord>,
+}
+
+impl Candidate {
+    fn new(index: u64, score: f64, record: &SeqRecord, placement: Placement, report: bool, hold: bool) -> Self {
+        let name = if report { record.name().to_vec() } else { Vec::new() };
+        Candidate {
+            index,
+            score,
+            kept: Kept { name, placement },
+            record: hold.then(|| record.clone()),
+        }
+    }
+}
+
+/// Kept records by the 128-bit hash of their key
+type Candidates = HashMap<u128, Candidate, BuildHasherDefault<MixHasher>>;
+
+/// Shard of the kept keys holding `hash`
+fn shard_of(hash: u128) -> usize {
+    (hash >> 64) as usize % SHARDS
+}
+
+/// Line of the duplicates table for the record `name` of length `len`, a
+/// duplicate of `kept`
+fn duplicate_row(name: &[u8], len: usize, placement: &Placement, kept: &Kept) -> Vec<u8> {
+    let (strand, offset) = placement.relative_to(&kept.placement, len);
+    let mut row = Vec::with_capacity(name.len() + kept.name.len() + 24);
+    row.extend_from_slice(name);
+    row.push(b'\t');
+    row.extend_from_slice(&kept.name);
+    row.extend_from_slice(format!("\t{}\t{}\n", strand, offset).as_bytes());
+    row
+}
+
+/// Pass the records of `input_files`, with the index of their file, to
+/// `process` in input order, in batches of `BATCH_SIZE`
+fn for_each_batch<F>(input_files: Vec<InputSource>, interrupt: &Interrupt, mut process: F) -> PyResult<()>
+where
+    F: FnMut(&[(usize, SeqRecord)]) -> PyResult<()>,
+{
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for (file, input_file) in input_files.into_iter().enumerate() {
+        for record in RecordReader::open(input_file)? {
+            interrupt.check()?;
+            batch.push((file, record?));
+            if batch.len() == BATCH_SIZE {
+                process(&batch)?;
+                batch.clear();
+            }
+        }
+    }
+    if !batch.is_empty() {
+        process(&batch)?;
+    }
+    Ok(())
+}
+
+/// Hash and score a batch of records in parallel, the first one being at
+/// `first_index` in the input
+fn score_batch<F>(batch: &[(usize, SeqRecord)], first_index: u64, keep: Keep, key: &F) -> PyResult<Vec<(u128, Placement, f64)>>
+where
+    F: Fn(&SeqRecord) -> (u128, Placement) + Sync,
+{
+    batch
+        .par_iter()
+        .enumerate()
+        .map(|(i, (file, record))| {
+            let (hash, placement) = key(record);
+            Ok((hash, placement, keep.score(first_index + i as u64, *file, record)?))
+        })
+        .collect()
+}
+
+/// Internal function to write the record `keep` chooses for every key, and
+/// optionally report the others to `duplicates`
+///
+/// Records are compared by the 128-bit hash `key` gives them. Files are read
+/// in order and the kept records are written in input order, so the output
+/// does not depend on the number of threads. With `spill_dir`, the records
+/// are partitioned by hash to temporary files there and each partition is
+/// deduplicated in memory on its own, bounding memory by the largest
+/// partition rather than the whole input.
+#[allow(clippy::too_many_arguments)]
+fn deduplicate<F>(
+    py: Python<'_>,
+    input_files: Vec<InputSource>,
+    output_file: OutputTarget,
+    threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
+    duplicates: Option<OutputStream>,
+    spill_dir: Option<PathBuf>,
+    keep: Keep,
+    key: F,
+) -> PyResult<usize>
+where
+    F: Fn(&SeqRecord) -> (u128, Placement) + Sync,
+{
+    let mut writer = RecordWriter::create(output_file, line_length, compression)?;
+    let mut duplicates = duplicates;
+
+    let count = pool::run(py, threads, |interrupt| match spill_dir {
+        Some(parent) => deduplicate_spilled(input_files, &parent, keep, &mut writer, &mut duplicates, interrupt, &key),
+        None => deduplicate_in_memory(input_files, keep, &mut writer, &mut duplicates, interrupt, &key),
+    })?;
+
+    writer.finish()?;
+    if let Some(duplicates) = duplicates {
+        duplicates.finish().map_err(duplicates_error)?;
+    }
+    Ok(count)
+}
+
+/// `deduplicate` keeping the first record of every key without a duplicates
+/// table, where each shard only needs to hold the 128-bit hashes
+fn deduplicate_hashes<F>(
+    input_files: Vec<InputSource>,
+    writer: &mut RecordWriter,
+    interrupt: &Interrupt,
+    key: &F,
+) -> PyResult<usize>
+where
+    F: Fn(&SeqRecord) -> (u128, Placement) + Sync,
+{
+    let mut shards: Vec<HashSet<u128, BuildHasherDefault<MixHasher>>> =
+        (0..SHARDS).map(|_| HashSet::default()).collect();
+    let mut count = 0;
+
+    for_each_batch(input_files, interrupt, |batch| {
+        let hashes: Vec<u128> = batch.par_iter().map(|(_, record)| key(record).0).collect();
+        let mut buckets = vec![Vec::new(); SHARDS];
+        for (i, hash) in hashes.iter().enumerate() {
+            buckets[shard_of(*hash)].push(i);
+        }
+        let firsts: Vec<Vec<usize>> = shards
+            .par_iter_mut()
+            .zip(buckets)
+            .map(|(shard, indices)| indices.into_iter().filter(|&i| shard.insert(hashes[i])).collect())
+            .collect();
+        let mut first = vec![false; batch.len()];
+        for i in firsts.into_iter().flatten() {
+            first[i] = true;
+        }
+        for ((_, record), _) in batch.iter().zip(first).filter(|(_, first)| *first) {
+            writer.write_record(record)?;
+            count += 1;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// `deduplicate` with every kept hash in memory
+///
+/// Each batch is hashed in parallel, then every shard of the kept hashes
+/// takes in its records in input order on its own thread. The first record
+/// of a key is final as soon as it is read, so with `Keep::First` records
+/// are written as they come; other policies hold the kept records until the
+/// end.
+fn deduplicate_in_memory<F>(
+    input_files: Vec<InputSource>,
+    keep: Keep,
+    writer: &mut RecordWriter,
+    duplicates: &mut Option<OutputStream>,
+    interrupt: &Interrupt,
+    key: &F,
+) -> PyResult<usize>
+where
+    F: Fn(&SeqRecord) -> (u128, Placement) + Sync,
+{
+    let report = duplicates.is_some();
+    let streaming = keep == Keep::First;
+    if streaming && !report {
+        return deduplicate_hashes(input_files, writer, interrupt, key);
+    }
+    let mut shards: Vec<Candidates> = (0..SHARDS).map(|_| Candidates::default()).collect();
+    // Name, length, hash and placement of every record, for the duplicates
+    // table once the kept records are known
+    let mut reads: Vec<(Vec<u8>, usize, u128, Placement)> = Vec::new();
+    let mut index = 0;
+    let mut count = 0;
+
+    for_each_batch(input_files, interrupt, |batch| {
+        let keys = score_batch(batch, index, keep, key)?;
+        let mut buckets = vec![Vec::new(); SHARDS];
+        for (i, (hash, _, _)) in keys.iter().enumerate() {
+            buckets[shard_of(*hash)].push(i);
+        }
+
+        // When streaming, the kept record of every duplicate in the batch
+        let verdicts: Vec<Vec<(usize, Kept)>> = shards
+            .par_iter_mut()
+            .zip(buckets)
+            .map(|(shard, indices)| {
+                let mut found = Vec::new();
+                for i in indices {
+                    let (hash, placement, score) = keys[i];
+                    let record = &batch[i].1;
+                    let candidate = || Candidate::new(index + i as u64, score, record, placement, report, !streaming);
+                    match shard.entry(hash) {
+                        Entry::Vacant(slot) => {
+                            slot.insert(candidate());
+                        }
+                        Entry::Occupied(slot) if streaming => found.push((i, slot.get().kept.clone())),
+                        Entry::Occupied(mut slot) => {
+                            if score > slot.get().score {
+                                slot.insert(candidate());
                             }
                         }
-                        
-                        // Increment count
-                        let mut count_lock = count.lock().unwrap();
-                        *count_lock += 1;
                     }
                 }
-                
-                // Extract header
-                current_header = line[1..].trim().to_string();
-                current_sequence = String::new();
-            } else {
-                // Append to current sequence
-                current_sequence.push_str(line.trim());
+                found
+            })
+            .collect();
+        index += batch.len() as u64;
+
+        if !streaming {
+            if report {
+                for ((_, record), (hash, placement, _)) in batch.iter().zip(keys) {
+                    reads.push((record.name().to_vec(), record.seq.len(), hash, placement));
+                }
             }
+            return Ok(());
+        }
+        let mut kept_of: Vec<Option<Kept>> = vec![None; batch.len()];
+        for (i, kept) in verdicts.into_iter().flatten() {
+            kept_of[i] = Some(kept);
         }
-        
-        // Process the last sequence
-        if !current_header.is_empty() && !current_sequence.is_empty() {
-            // Extract ID from header (first word after '>')
-            let id = current_header.split_whitespace().next().unwrap_or("");
-            
-            let mut unique_ids_lock = unique_ids.lock().unwrap();
-            if !unique_ids_lock.contains(id) {
-                unique_ids_lock.insert(id.to_string());
-                drop(unique_ids_lock); // Release lock before writing
-                
-                // Write to output file
-                let mut writer_lock = writer.lock().unwrap();
-                if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                    eprintln!("Error writing header: {}", e);
-                    return;
+        for (((_, record), (_, placement, _)), kept) in batch.iter().zip(&keys).zip(kept_of) {
+            match (kept, duplicates.as_mut()) {
+                (None, _) => {
+                    writer.write_record(record)?;
+                    count += 1;
                 }
-                
-                // Write sequence with line breaks every 60 characters
-                for chunk in current_sequence.as_bytes().chunks(60) {
-                    if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                        eprintln!("Error writing sequence: {}", e);
-                        return;
+                (Some(kept), Some(out)) => {
+                    let row = duplicate_row(record.name(), record.seq.len(), placement, &kept);
+                    out.write_all(&row).map_err(duplicates_error)?;
+                }
+                (Some(_), None) => {}
+            }
+        }
+        Ok(())
+    })?;
+    if streaming {
+        return Ok(count);
+    }
+
+    if let Some(out) = duplicates.as_mut() {
+        for (i, (name, len, hash, placement)) in reads.iter().enumerate() {
+            let candidate = &shards[shard_of(*hash)][hash];
+            if candidate.index != i as u64 {
+                out.write_all(&duplicate_row(name, *len, placement, &candidate.kept)).map_err(duplicates_error)?;
+            }
+        }
+    }
+    let mut kept: Vec<Candidate> = shards.into_iter().flat_map(|shard| shard.into_values()).collect();
+    kept.par_sort_unstable_by_key(|candidate| candidate.index);
+    for record in kept.iter().filter_map(|candidate| candidate.record.as_ref()) {
+        interrupt.check()?;
+        writer.write_record(record)?;
+    }
+    Ok(kept.len())
+}
+
+/// `deduplicate` through partition files in a temporary directory inside
+/// `parent`
+///
+/// Records are written with their input index to the partition of their
+/// hash. Each partition is read once to choose the kept record of every hash
+/// and again to split it into kept records and duplicates rows, which are
+/// then merged back from all partitions in input order.
+#[allow(clippy::too_many_arguments)]
+fn deduplicate_spilled<F>(
+    input_files: Vec<InputSource>,
+    parent: &Path,
+    keep: Keep,
+    writer: &mut RecordWriter,
+    duplicates: &mut Option<OutputStream>,
+    interrupt: &Interrupt,
+    key: &F,
+) -> PyResult<usize>
+where
+    F: Fn(&SeqRecord) -> (u128, Placement) + Sync,
+{
+    let report = duplicates.is_some();
+    let dir = SpillDir::create(parent)?;
+
+    let mut partitions = dir.partitions("records", SPILL_PARTITIONS)?;
+    let mut index = 0u64;
+    for_each_batch(input_files, interrupt, |batch| {
+        let keys = score_batch(batch, index, keep, key)?;
+        for ((_, record), (hash, placement, score)) in batch.iter().zip(keys) {
+            let out = &mut partitions[(hash >> 96) as usize % SPILL_PARTITIONS].out;
+            spill::write_u64(out, index)
+                .and_then(|_| out.write_all(&hash.to_le_bytes()))
+                .and_then(|_| out.write_all(&[placement.reverse as u8]))
+                .and_then(|_| spill::write_u64(out, placement.rotation as u64))
+                .and_then(|_| out.write_all(&score.to_le_bytes()))
+                .and_then(|_| spill::write_record(out, record))
+                .map_err(spill_error)?;
+            index += 1;
+        }
+        Ok(())
+    })?;
+    let finish = |partitions: Vec<Partition>| {
+        partitions
+            .into_iter()
+            .map(Partition::finish)
+            .collect::<PyResult<Vec<_>>>()
+    };
+    let record_paths = finish(partitions)?;
+
+    let mut kept_partitions = dir.partitions("kept", SPILL_PARTITIONS)?;
+    let mut duplicate_partitions = dir.partitions("duplicates", SPILL_PARTITIONS)?;
+    record_paths
+        .par_iter()
+        .zip(kept_partitions.par_iter_mut())
+        .zip(duplicate_partitions.par_iter_mut())
+        .try_for_each(|((path, kept_out), duplicates_out)| -> PyResult<()> {
+            let mut candidates = Candidates::default();
+            let mut input = spill::open_partition(path)?;
+            while let Some(index) = spill::read_index(&mut input).map_err(spill_error)? {
+                interrupt.check()?;
+                let (hash, placement, score, record) = read_spilled(&mut input).map_err(spill_error)?;
+                let candidate = || Candidate::new(index, score, &record, placement, report, false);
+                match candidates.entry(hash) {
+                    Entry::Vacant(slot) => {
+                        slot.insert(candidate());
+                    }
+                    Entry::Occupied(mut slot) => {
+                        if score > slot.get().score {
+                            slot.insert(candidate());
+                        }
                     }
                 }
-                
-                // Increment count
-                let mut count_lock = count.lock().unwrap();
-                *count_lock += 1;
+            }
+
+            let mut input = spill::open_partition(path)?;
+            while let Some(index) = spill::read_index(&mut input).map_err(spill_error)? {
+                interrupt.check()?;
+                let (hash, placement, _, record) = read_spilled(&mut input).map_err(spill_error)?;
+                let candidate = &candidates[&hash];
+                if candidate.index == index {
+                    spill::write_u64(&mut kept_out.out, index)
+                        .and_then(|_| spill::write_record(&mut kept_out.out, &record))
+                        .map_err(spill_error)?;
+                } else if report {
+                    let row = duplicate_row(record.name(), record.seq.len(), &placement, &candidate.kept);
+                    spill::write_u64(&mut duplicates_out.out, index)
+                        .and_then(|_| spill::write_bytes(&mut duplicates_out.out, &row))
+                        .map_err(spill_error)?;
+                }
+            }
+            // The records are no longer needed, free their disk space early
+            drop(input);
+            std::fs::remove_file(path).map_err(spill_error)
+        })?;
+    let kept_paths = finish(kept_partitions)?;
+    let duplicate_paths = finish(duplicate_partitions)?;
+
+    let mut count = 0;
+    spill::merge_by_index(&kept_paths, spill::read_record, |record| {
+        interrupt.check()?;
+        count += 1;
+        writer.write_record(&record)
+    })?;
+    if let Some(out) = duplicates.as_mut() {
+        spill::merge_by_index(&duplicate_paths, spill::read_bytes, |row| {
+            out.write_all(&row).map_err(duplicates_error)
+        })?;
+    }
+    Ok(count)
+}
+
+/// Read the hash, placement, score and record of a spilled record after its
+/// index
+fn read_spilled<R: Read>(input: &mut R) -> std::io::Result<(u128, Placement, f64, SeqRecord)> {
+    let mut hash = [0; 16];
+    input.read_exact(&mut hash)?;
+    let mut reverse = [0];
+    input.read_exact(&mut reverse)?;
+    let rotation = spill::read_u64(input)? as usize;
+    let mut score = [0; 8];
+    input.read_exact(&mut score)?;
+    let record = spill::read_record(input)?;
+    let placement = Placement { reverse: reverse[0] != 0, rotation };
+    Ok((u128::from_le_bytes(hash), placement, f64::from_le_bytes(score), record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// `seq` rotated to start at `offset`, then reverse complemented when
+    /// `reverse`, as described by a duplicates table row
+    fn place(seq: &[u8], offset: usize, reverse: bool) -> Vec<u8> {
+        let rotated = [&seq[offset..], &seq[..offset]].concat();
+        if reverse {
+            reverse_complement_key(&rotated, false)
+        } else {
+            rotated
+        }
+    }
+
+    #[test]
+    fn least_rotation_of_periodic_and_empty_sequences() {
+        assert_eq!(least_rotation(b""), 0);
+        assert_eq!(least_rotation(b"A"), 0);
+        assert_eq!(least_rotation(b"AAAA"), 0);
+        assert_eq!(least_rotation(b"ABAB"), 0);
+        assert_eq!(least_rotation(b"BABA"), 1);
+        assert_eq!(least_rotation(b"CAGCAGCAG"), 1);
+        assert_eq!(least_rotation(b"GCAGCAGCA"), 2);
+        assert_eq!(rotate_least(b"TACGTACG"), (b"ACGTACGT".to_vec(), 1));
+    }
+
+    #[test]
+    fn relative_to_places_duplicates_on_both_strands() {
+        let mut strands = HashSet::new();
+        for kept in [&b"AACGTT"[..], b"ACGGT", b"CCGTTA", b"GATTACA", b"ACACAC", b"TTTGGG"] {
+            for offset in 0..kept.len() {
+                for reverse in [false, true] {
+                    let duplicate = place(kept, offset, reverse);
+                    let (kept_key, kept_placement) = sequence_key(kept, true, true, false);
+                    let (key, placement) = sequence_key(&duplicate, true, true, false);
+                    assert_eq!(key, kept_key);
+                    strands.insert((kept_placement.reverse, placement.reverse));
+
+                    let (strand, found) = placement.relative_to(&kept_placement, kept.len());
+                    assert_eq!(place(kept, found, strand == '-'), duplicate);
+                }
             }
         }
-    });
-    
-    // Finalize and return count
-    let final_count = *count.lock().unwrap();
-    Ok(final_count)
+        // Kept and duplicate keys taken from each strand
+        assert_eq!(strands.len(), 4);
+    }
+
+    #[test]
+    fn relative_to_empty_and_linear_sequences() {
+        let (_, empty) = sequence_key(b"", true, true, false);
+        assert_eq!(empty.relative_to(&empty, 0), ('+', 0));
+
+        let (_, kept) = sequence_key(b"AACG", true, false, false);
+        let (_, duplicate) = sequence_key(b"CGTT", true, false, false);
+        assert_eq!(duplicate.relative_to(&kept, 4), ('-', 0));
+        assert_eq!(kept.relative_to(&kept, 4), ('+', 0));
+    }
 }
@@ -0,0 +1,502 @@
+//! Indexed FASTA random access
+//!
+//! The `.fai` index uses the `samtools faidx` layout (name, length, offset,
+//! bases per line, bytes per line), with offsets into the uncompressed data.
+//! BGZF-compressed files additionally get a `.gzi` index mapping compressed
+//! block offsets to uncompressed ones, in the format written by `bgzip -i`.
+
+use pyo3::exceptions::{PyFileNotFoundError, PyIOError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyIterator, PyList};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::compression::{is_bgzf, BgzfReader, Compression};
+use crate::pool::{self, Interrupt};
+use crate::seq_utils::reverse_complement;
+
+/// `(compressed, uncompressed)` offsets of the blocks of a BGZF file
+type BlockOffsets = Vec<(u64, u64)>;
+
+/// One line of a `.fai` index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaiEntry {
+    pub name: String,
+    pub length: u64,
+    pub offset: u64,
+    pub line_bases: u64,
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    /// Offset of the 0-based sequence position `pos` in the uncompressed file
+    fn byte_offset(&self, pos: u64) -> u64 {
+        if self.line_bases == 0 {
+            return self.offset;
+        }
+        self.offset + pos / self.line_bases * self.line_width + pos % self.line_bases
+    }
+}
+
+/// The sequences of an indexed FASTA file, in file order
+#[derive(Debug, Default)]
+pub struct FastaIndex {
+    pub entries: Vec<FaiEntry>,
+    by_name: HashMap<String, usize>,
+}
+
+impl FastaIndex {
+    fn push(&mut self, entry: FaiEntry) {
+        // Like samtools, later sequences with a duplicate name are ignored
+        if !self.by_name.contains_key(&entry.name) {
+            self.by_name.insert(entry.name.clone(), self.entries.len());
+            self.entries.push(entry);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FaiEntry> {
+        self.by_name.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Scan uncompressed FASTA data and record the layout of every sequence
+    pub fn build<R: BufRead>(mut reader: R, interrupt: &Interrupt) -> PyResult<Self> {
+        let mut index = FastaIndex::default();
+        let mut current: Option<(FaiEntry, bool)> = None;
+        let mut line = Vec::new();
+        let mut offset = 0u64;
+
+        loop {
+            line.clear();
+            let n = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| PyIOError::new_err(format!("Error reading FASTA: {}", e)))?;
+            if n == 0 {
+                break;
+            }
+            offset += n as u64;
+            let content = trim_line_end(&line);
+
+            if content.first() == Some(&b'>') {
+                interrupt.check()?;
+                if let Some((entry, _)) = current.take() {
+                    index.push(entry);
+                }
+                let name = content[1..]
+                    .split(|b| b.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                let entry = FaiEntry {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    length: 0,
+                    offset,
+                    line_bases: 0,
+                    line_width: 0,
+                };
+                current = Some((entry, false));
+                continue;
+            }
+
+            let Some((entry, ended)) = current.as_mut() else {
+                if content.iter().all(|b| b.is_ascii_whitespace()) {
+                    continue;
+                }
+                return Err(PyValueError::new_err(
+                    "Only FASTA files can be indexed; expected '>' at the start of the file",
+                ));
+            };
+            let bases = content.len() as u64;
+            if bases == 0 {
+                // A blank line may only follow the last line of a sequence
+                *ended = true;
+                continue;
+            }
+            if *ended || bases > entry.line_bases && entry.line_bases > 0 {
+                return Err(PyValueError::new_err(format!(
+                    "Different line length in sequence '{}'",
+                    entry.name
+                )));
+            }
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = n as u64;
+            } else if bases < entry.line_bases || n as u64 != entry.line_width {
+                *ended = true;
+            }
+            entry.length += bases;
+        }
+        if let Some((entry, _)) = current {
+            index.push(entry);
+        }
+        Ok(index)
+    }
+
+    pub fn read(path: &str) -> PyResult<Self> {
+        let file = File::open(path)
+            .map_err(|e| PyIOError::new_err(format!("Failed to open index {}: {}", path, e)))?;
+        let mut index = FastaIndex::default();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|e| PyIOError::new_err(format!("Error reading {}: {}", path, e)))?;
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let number = |j: usize| fields.get(j).and_then(|f| f.parse::<u64>().ok());
+            match (number(1), number(2), number(3), number(4)) {
+                (Some(length), Some(offset), Some(line_bases), Some(line_width)) => {
+                    index.push(FaiEntry {
+                        name: fields[0].to_string(),
+                        length,
+                        offset,
+                        line_bases,
+                        line_width,
+                    })
+                }
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Malformed line {} in index {}",
+                        i + 1,
+                        path
+                    )))
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn write(&self, path: &str) -> PyResult<()> {
+        let result = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            for e in &self.entries {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}",
+                    e.name, e.length, e.offset, e.line_bases, e.line_width
+                )?;
+            }
+            out.flush()
+        });
+        result.map_err(|e| PyIOError::new_err(format!("Failed to write index {}: {}", path, e)))
+    }
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    while end > 0 && matches!(line[end - 1], b'\n' | b'\r') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Read a `.gzi` index, prepending the implicit first block at `(0, 0)`
+fn read_gzi(path: &str) -> PyResult<BlockOffsets> {
+    let data = std::fs::read(path)
+        .map_err(|e| PyIOError::new_err(format!("Failed to open index {}: {}", path, e)))?;
+    let malformed = || PyValueError::new_err(format!("Malformed index {}", path));
+    let word = |i: usize| -> Option<u64> {
+        data.get(i * 8..i * 8 + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    };
+    let count = word(0).ok_or_else(malformed)? as usize;
+    let mut blocks = vec![(0, 0)];
+    for i in 0..count {
+        match (word(1 + 2 * i), word(2 + 2 * i)) {
+            (Some(compressed), Some(uncompressed)) => blocks.push((compressed, uncompressed)),
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(blocks)
+}
+
+fn write_gzi(path: &str, blocks: &[(u64, u64)]) -> PyResult<()> {
+    let mut data = Vec::with_capacity(8 + blocks.len() * 16);
+    data.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+    for (compressed, uncompressed) in blocks {
+        data.extend_from_slice(&compressed.to_le_bytes());
+        data.extend_from_slice(&uncompressed.to_le_bytes());
+    }
+    std::fs::write(path, data)
+        .map_err(|e| PyIOError::new_err(format!("Failed to write index {}: {}", path, e)))
+}
+
+/// Whether the file at `path` is BGZF-compressed; other codecs cannot be
+/// indexed
+fn check_compression(path: &str) -> PyResult<bool> {
+    let mut header = [0u8; 18];
+    let mut file = File::open(path)
+        .map_err(|e| PyIOError::new_err(format!("Failed to open input file {}: {}", path, e)))?;
+    let mut filled = 0;
+    while filled < header.len() {
+        match file.read(&mut header[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) => return Err(PyIOError::new_err(format!("Error reading {}: {}", path, e))),
+        }
+    }
+    let header = &header[..filled];
+    if is_bgzf(header) {
+        return Ok(true);
+    }
+    match Compression::detect(header) {
+        Compression::None => Ok(false),
+        _ => Err(PyValueError::new_err(format!(
+            "{} is compressed but not with BGZF; only plain and BGZF FASTA can be indexed",
+            path
+        ))),
+    }
+}
+
+/// Build the `.fai` (and for BGZF input the `.gzi`) index of `path` and write
+/// it next to the file
+fn build_index(
+    path: &str,
+    bgzf: bool,
+    interrupt: &Interrupt,
+) -> PyResult<(FastaIndex, Option<BlockOffsets>)> {
+    let file = File::open(path)
+        .map_err(|e| PyIOError::new_err(format!("Failed to open input file {}: {}", path, e)))?;
+    let (index, gzi) = if bgzf {
+        let mut reader = BufReader::new(BgzfReader::new(file, 0, 0));
+        let index = FastaIndex::build(&mut reader, interrupt)?;
+        let mut blocks = reader.into_inner().block_starts;
+        write_gzi(&format!("{}.gzi", path), &blocks)?;
+        blocks.insert(0, (0, 0));
+        (index, Some(blocks))
+    } else {
+        (FastaIndex::build(BufReader::new(file), interrupt)?, None)
+    };
+    index.write(&format!("{}.fai", path))?;
+    Ok((index, gzi))
+}
+
+/// Build a `samtools faidx` compatible `.fai` index for a FASTA file.
+///
+/// The index is written to `<path>.fai`. For BGZF-compressed files a `.gzi`
+/// block index is written to `<path>.gzi` as well. Returns the path of the
+/// `.fai` file.
+#[pyfunction]
+pub fn index_fasta(py: Python<'_>, path: PathBuf) -> PyResult<String> {
+    let path = path.to_string_lossy().into_owned();
+    let bgzf = check_compression(&path)?;
+    pool::run(py, Some(1), |interrupt| build_index(&path, bgzf, interrupt))?;
+    Ok(format!("{}.fai", path))
+}
+
+/// Random access to the sequences of an indexed FASTA file.
+///
+/// The `.fai` index (and `.gzi` for BGZF-compressed files) is read from next
+/// to the file, or built there if missing and `build_index` is true.
+/// Coordinates are 0-based and half-open.
+#[pyclass]
+pub struct IndexedFasta {
+    path: String,
+    index: FastaIndex,
+    /// Block offsets of BGZF files, starting with `(0, 0)`
+    gzi: Option<BlockOffsets>,
+    file: Mutex<File>,
+}
+
+impl IndexedFasta {
+    /// Read the raw bytes of `entry` between sequence positions `start` and `end`
+    fn read_range(&self, entry: &FaiEntry, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        if start == end {
+            return Ok(Vec::new());
+        }
+        let first = entry.byte_offset(start);
+        let last = entry.byte_offset(end - 1) + 1;
+        let mut raw = Vec::with_capacity((last - first) as usize);
+        let mut file = self.file.lock().unwrap();
+        match &self.gzi {
+            None => {
+                file.seek(SeekFrom::Start(first))?;
+                (&mut *file).take(last - first).read_to_end(&mut raw)?;
+            }
+            Some(blocks) => {
+                let i = blocks.partition_point(|&(_, u)| u <= first) - 1;
+                let (compressed, uncompressed) = blocks[i];
+                file.seek(SeekFrom::Start(compressed))?;
+                let mut reader = BgzfReader::new(&mut *file, compressed, uncompressed);
+                io::copy(
+                    &mut (&mut reader).take(first - uncompressed),
+                    &mut io::sink(),
+                )?;
+                reader.take(last - first).read_to_end(&mut raw)?;
+            }
+        }
+        if raw.len() as u64 != last - first {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file is shorter than its index; rebuild the index",
+            ));
+        }
+        raw.retain(|&b| b != b'\n' && b != b'\r');
+        Ok(raw)
+    }
+
+    fn from_parts(path: String, index: FastaIndex, gzi: Option<BlockOffsets>) -> PyResult<Self> {
+        let file = File::open(&path).map_err(|e| {
+            PyIOError::new_err(format!("Failed to open input file {}: {}", path, e))
+        })?;
+        Ok(Self {
+            path,
+            index,
+            gzi,
+            file: file.into(),
+        })
+    }
+
+    /// Open `path` with the indices next to it, or `None` if they are missing
+    pub fn open_indexed(path: &str) -> PyResult<Option<Self>> {
+        let bgzf = check_compression(path)?;
+        let fai_path = format!("{}.fai", path);
+        let gzi_path = format!("{}.gzi", path);
+        if !Path::new(&fai_path).exists() || bgzf && !Path::new(&gzi_path).exists() {
+            return Ok(None);
+        }
+        let gzi = if bgzf {
+            Some(read_gzi(&gzi_path)?)
+        } else {
+            None
+        };
+        let index = FastaIndex::read(&fai_path)?;
+        Self::from_parts(path.to_string(), index, gzi).map(Some)
+    }
+
+    pub fn length(&self, name: &str) -> Option<u64> {
+        self.index.get(name).map(|e| e.length)
+    }
+
+    /// The bases of `name` between `start` and `end`, which must lie within
+    /// the sequence
+    pub fn fetch_bytes(&self, name: &str, start: u64, end: u64) -> PyResult<Vec<u8>> {
+        let entry = self.entry(name)?;
+        self.read_range(entry, start, end)
+            .map_err(|e| PyIOError::new_err(format!("Error reading {}: {}", self.path, e)))
+    }
+
+    fn entry(&self, name: &str) -> PyResult<&FaiEntry> {
+        self.index
+            .get(name)
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))
+    }
+}
+
+#[pymethods]
+impl IndexedFasta {
+    #[new]
+    #[pyo3(signature = (path, build_index=true))]
+    fn new(py: Python<'_>, path: PathBuf, build_index: bool) -> PyResult<Self> {
+        let path = path.to_string_lossy().into_owned();
+        if let Some(fasta) = Self::open_indexed(&path)? {
+            return Ok(fasta);
+        }
+        if !build_index {
+            return Err(PyFileNotFoundError::new_err(format!(
+                "No index found for {}; use build_index=True or index_fasta()",
+                path
+            )));
+        }
+        let bgzf = check_compression(&path)?;
+        let (index, gzi) = pool::run(py, Some(1), |interrupt| {
+            self::build_index(&path, bgzf, interrupt)
+        })?;
+        Self::from_parts(path, index, gzi)
+    }
+
+    /// Fetch a sequence or a subrange of it.
+    ///
+    /// Parameters
+    /// ----------
+    /// name : str
+    ///     The sequence name, as in the first column of the `.fai` index.
+    /// start : int, optional
+    ///     The 0-based start position. Defaults to the sequence start.
+    /// end : int, optional
+    ///     The exclusive end position, clipped to the sequence length.
+    ///     Defaults to the sequence end.
+    /// strand : {'+', '-'}, default: '+'
+    ///     Return the reverse complement for '-'.
+    ///
+    /// Returns
+    /// -------
+    /// str
+    ///     The requested sequence.
+    #[pyo3(signature = (name, start=None, end=None, strand="+"))]
+    fn fetch(
+        &self,
+        py: Python<'_>,
+        name: &str,
+        start: Option<u64>,
+        end: Option<u64>,
+        strand: &str,
+    ) -> PyResult<String> {
+        let entry = self.entry(name)?;
+        let start = start.unwrap_or(0);
+        let end = end.unwrap_or(entry.length).min(entry.length);
+        if start > end {
+            return Err(PyValueError::new_err(format!(
+                "Invalid range {}-{} for sequence '{}' of length {}",
+                start, end, name, entry.length
+            )));
+        }
+        let reverse = match strand {
+            "+" => false,
+            "-" => true,
+            _ => return Err(PyValueError::new_err("strand must be '+' or '-'")),
+        };
+        let raw = py.allow_threads(|| self.fetch_bytes(name, start, end))?;
+        let seq = String::from_utf8_lossy(&raw).into_owned();
+        Ok(if reverse {
+            reverse_complement(&seq)
+        } else {
+            seq
+        })
+    }
+
+    /// The names of the sequences, in file order.
+    fn keys(&self) -> Vec<String> {
+        self.index.entries.iter().map(|e| e.name.clone()).collect()
+    }
+
+    /// The length of every sequence, keyed by name in file order.
+    #[getter]
+    fn lengths<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let lengths = PyDict::new(py);
+        for entry in &self.index.entries {
+            lengths.set_item(&entry.name, entry.length)?;
+        }
+        Ok(lengths)
+    }
+
+    /// The length of the sequence `name`.
+    fn get_length(&self, name: &str) -> PyResult<u64> {
+        Ok(self.entry(name)?.length)
+    }
+
+    fn __getitem__(&self, py: Python<'_>, name: &str) -> PyResult<String> {
+        self.fetch(py, name, None, None, "+")
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.index.get(name).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.index.entries.len()
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.keys())?.try_iter()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<IndexedFasta {} ({} sequences)>",
+            self.path,
+            self.index.entries.len()
+        )
+    }
+}
@@ -1,7 +1,6 @@
-//! Python bindings for needletail
+//! Python bindings for FASTA/FASTQ parsing, built on needletail
 
 // TODO:
-// - Enable reading FASTA and FASTQ files from stdin
 // - Add a `reverse_complement` method to the `Record` class that returns a new
 //   `Record` object with the reverse complement of the sequence.
 // - Turn `is_fasta` and `is_fastq` into properties.
@@ -9,29 +8,20 @@
 // - Make `normalize_seq`, `reverse_complement`, and `decode_phred` functions
 //   able to handle `Record` objects as input.
 
-use crate::parser::SequenceRecord;
-use crate::quality::{decode_phred, PhredEncoding};
-use crate::sequence::{complement, normalize};
-use crate::{parse_fastx_file, parse_fastx_reader, FastxReader};
+use crate::fastx_io::{
+    decode_phred, LetterCase, OutputFormat, PhredEncoding, RecordReader, RecordWriter, SeqRecord,
+    WriteOptions, DEFAULT_LINE_LENGTH,
+};
+use crate::pyfile::{InputSource, OutputTarget};
+use needletail::sequence::{complement, normalize};
 
-use pyo3::exceptions::PyValueError;
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::PyTuple;
-use pyo3::{create_exception, wrap_pyfunction};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Cursor;
-use std::path::PathBuf;
 use std::sync::Mutex;
 
-create_exception!(capibex, FastxParseError, pyo3::exceptions::PyException);
-
-// Avoid some boilerplate with the error handling
-macro_rules! py_try {
-    ($call:expr) => {
-        $call.map_err(|e| PyErr::new::<FastxParseError, _>(format!("{}", e)))?
-    };
-}
-
 fn get_seq_snippet(seq: &str, max_len: usize) -> String {
     if seq.len() > max_len {
         let start = &seq[..max_len - 4];
@@ -60,7 +50,7 @@ fn get_seq_snippet(seq: &str, max_len: usize) -> String {
 #[pyclass]
 #[pyo3(name = "FastxReader")]
 pub struct PyFastxReader {
-    reader: Mutex<Box<dyn FastxReader>>,
+    reader: Mutex<RecordReader>,
 }
 
 #[pymethods]
@@ -74,11 +64,9 @@ impl PyFastxReader {
     }
 
     fn __next__(slf: PyRefMut<Self>) -> PyResult<Option<Record>> {
-        if let Some(rec) = slf.reader.lock().unwrap().next() {
-            let record = py_try!(rec);
-            Ok(Some(Record::from_sequence_record(&record)))
-        } else {
-            Ok(None)
+        match slf.reader.lock().unwrap().next() {
+            Some(record) => Ok(Some(Record::from(record?))),
+            None => Ok(None),
         }
     }
 }
@@ -123,19 +111,19 @@ impl PyFastxReader {
 #[pyclass]
 pub struct Record {
     #[pyo3(get)]
-    id: String,
+    pub(crate) id: String,
     #[pyo3(get)]
-    seq: String,
+    pub(crate) seq: String,
     #[pyo3(get)]
-    qual: Option<String>,
+    pub(crate) qual: Option<String>,
 }
 
-impl Record {
-    fn from_sequence_record(rec: &SequenceRecord) -> Self {
+impl From<SeqRecord> for Record {
+    fn from(rec: SeqRecord) -> Self {
         Self {
-            id: String::from_utf8_lossy(rec.id()).to_string(),
-            seq: String::from_utf8_lossy(&rec.seq()).to_string(),
-            qual: rec.qual().map(|q| String::from_utf8_lossy(q).to_string()),
+            id: String::from_utf8_lossy(&rec.id).into_owned(),
+            seq: String::from_utf8_lossy(&rec.seq).into_owned(),
+            qual: rec.qual.map(|q| String::from_utf8_lossy(&q).into_owned()),
         }
     }
 }
@@ -154,7 +142,7 @@ impl Record {
     #[getter]
     pub fn description(&self) -> PyResult<Option<&str>> {
         if let Some(pos) = self.id.find(char::is_whitespace) {
-            Ok(Some(&self.id[pos..].trim_start()))
+            Ok(Some(self.id[pos..].trim_start()))
         } else {
             Ok(None)
         }
@@ -227,9 +215,8 @@ impl Record {
         let mut hasher = DefaultHasher::new();
         self.id.hash(&mut hasher);
         self.seq.hash(&mut hasher);
-        match &self.qual {
-            Some(qual) => qual.hash(&mut hasher),
-            None => {}
+        if let Some(qual) = &self.qual {
+            qual.hash(&mut hasher);
         }
         Ok(hasher.finish())
     }
@@ -273,13 +260,193 @@ impl Record {
     }
 }
 
+/// A writer for FASTA/FASTQ records.
+///
+/// Parameters
+/// ----------
+/// output : str, pathlib.Path or binary file object
+///     The path of the output file, '-' for stdout, or a writable binary
+///     file-like object.
+/// line_length : int, default: 60
+///     The number of sequence characters per FASTA line. Use 0 to write
+///     each sequence on a single line.
+/// case : {'keep', 'upper', 'lower'}, default: 'keep'
+///     The letter case of the written sequences.
+/// format : {'auto', 'fasta', 'fastq'}, default: 'auto'
+///     The output format. 'auto' writes records with qualities as FASTQ and
+///     the others as FASTA. 'fasta' drops qualities, and 'fastq' raises a
+///     `ValueError` for records without qualities.
+/// input_phred : {33, 64}, default: 33
+///     The Phred offset of the qualities passed to the writer.
+/// output_phred : {33, 64}, default: 33
+///     The Phred offset of the written qualities.
+/// compression : str, optional
+///     The compression codec ('gzip', 'bgzf', 'bzip2', 'xz', 'zstd' or
+///     'none'). By default it is inferred from the file extension.
+///
+/// Methods
+/// -------
+/// write(record)
+///     Write a `Record` or an `(id, seq)` or `(id, seq, qual)` tuple.
+/// write_records(records)
+///     Write all records of an iterable and return their number.
+/// close()
+///     Flush the output and finish the compressed stream.
+///
+/// Notes
+/// -----
+/// The writer is a context manager that closes itself on exit.
+///
+/// Examples
+/// --------
+/// >>> with FastxWriter("out.fa.gz", line_length=0, case="upper") as w:
+/// ...     w.write(("seq1", "acgt"))
+#[pyclass]
+#[pyo3(name = "FastxWriter")]
+pub struct PyFastxWriter {
+    writer: Mutex<Option<RecordWriter>>,
+    name: String,
+}
+
+impl PyFastxWriter {
+    fn with_writer<R>(&self, f: impl FnOnce(&mut RecordWriter) -> PyResult<R>) -> PyResult<R> {
+        match self.writer.lock().unwrap().as_mut() {
+            Some(writer) => f(writer),
+            None => Err(PyValueError::new_err("I/O operation on closed FastxWriter")),
+        }
+    }
+}
+
+/// Extract a record from a `Record` or an `(id, seq[, qual])` tuple
+fn extract_record(ob: &Bound<'_, PyAny>) -> PyResult<SeqRecord> {
+    if let Ok(record) = ob.downcast::<Record>() {
+        let record = record.borrow();
+        return Ok(SeqRecord {
+            id: record.id.as_bytes().to_vec(),
+            seq: record.seq.as_bytes().to_vec(),
+            qual: record.qual.as_ref().map(|q| q.as_bytes().to_vec()),
+        });
+    }
+    let (id, seq, qual) = if let Ok((id, seq)) = ob.extract::<(String, String)>() {
+        (id, seq, None)
+    } else if let Ok(parts) = ob.extract::<(String, String, Option<String>)>() {
+        parts
+    } else {
+        return Err(PyTypeError::new_err(
+            "Expected a Record or an (id, seq) or (id, seq, qual) tuple",
+        ));
+    };
+    Ok(SeqRecord {
+        id: id.into_bytes(),
+        seq: seq.into_bytes(),
+        qual: qual.map(String::into_bytes),
+    })
+}
+
+#[pymethods]
+impl PyFastxWriter {
+    #[new]
+    #[pyo3(signature = (output, line_length=DEFAULT_LINE_LENGTH, case="keep", format="auto", input_phred=33, output_phred=33, compression=None))]
+    fn new(
+        output: OutputTarget,
+        line_length: usize,
+        case: &str,
+        format: &str,
+        input_phred: u8,
+        output_phred: u8,
+        compression: Option<&str>,
+    ) -> PyResult<Self> {
+        let options = WriteOptions {
+            line_length,
+            case: LetterCase::from_name(case)?,
+            format: OutputFormat::from_name(format)?,
+            input_phred: PhredEncoding::from_offset(input_phred)?,
+            output_phred: PhredEncoding::from_offset(output_phred)?,
+        };
+        let name = output.name();
+        let writer = RecordWriter::with_options(output.create(compression)?, options);
+        Ok(Self {
+            writer: Some(writer).into(),
+            name,
+        })
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("<FastxWriter {}>", self.name))
+    }
+
+    /// Whether the writer has been closed.
+    #[getter]
+    fn closed(&self) -> bool {
+        self.writer.lock().unwrap().is_none()
+    }
+
+    /// Write a single record.
+    ///
+    /// Parameters
+    /// ----------
+    /// record : Record or tuple
+    ///     A `Record`, or an `(id, seq)` or `(id, seq, qual)` tuple.
+    fn write(&self, record: &Bound<'_, PyAny>) -> PyResult<()> {
+        let record = extract_record(record)?;
+        self.with_writer(|writer| writer.write_record(&record))
+    }
+
+    /// Write all records of an iterable.
+    ///
+    /// Returns
+    /// -------
+    /// int
+    ///     The number of records written.
+    fn write_records(&self, records: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let mut count = 0;
+        for record in records.try_iter()? {
+            let record = extract_record(&record?)?;
+            self.with_writer(|writer| writer.write_record(&record))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Flush buffered records to the output.
+    fn flush(&self) -> PyResult<()> {
+        self.with_writer(|writer| writer.flush())
+    }
+
+    /// Flush the output and finish the compressed stream. Closing twice is a
+    /// no-op.
+    fn close(&self) -> PyResult<()> {
+        match self.writer.lock().unwrap().take() {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &self,
+        _exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        self.close()?;
+        Ok(false)
+    }
+}
+
 /// Returns an iterator that parses a FASTA/FASTQ file and yields sequence
 /// records.
 ///
 /// Parameters
 /// ----------
-/// path : str or pathlib.Path
-///     The path to a FASTA/FASTQ file.
+/// path : str, pathlib.Path, bytes or binary file object
+///     The path to a FASTA/FASTQ file, optionally compressed. Use '-' to
+///     read from stdin. `bytes` are parsed as the file content, and binary
+///     file-like objects (e.g. `io.BytesIO`) are read until exhausted.
 ///
 /// Returns
 /// -------
@@ -290,7 +457,7 @@ impl Record {
 /// Raises
 /// ------
 /// FastxParseError
-///     If an error occurs while reading and parsing the input file.
+///     If an error occurs while parsing the input.
 ///
 /// See also
 /// --------
@@ -300,8 +467,8 @@ impl Record {
 ///     A class with instances that are iterators that yield `Record` objects.
 #[pyfunction]
 #[pyo3(name = "parse_fastx_file")]
-fn py_parse_fastx_file(path: PathBuf) -> PyResult<PyFastxReader> {
-    let reader = py_try!(parse_fastx_file(path));
+pub fn py_parse_fastx_file(path: InputSource) -> PyResult<PyFastxReader> {
+    let reader = RecordReader::open(path)?;
     Ok(PyFastxReader {
         reader: reader.into(),
     })
@@ -333,8 +500,8 @@ fn py_parse_fastx_file(path: PathBuf) -> PyResult<PyFastxReader> {
 /// FastxReader:
 ///     A class with instances that are iterators that yield `Record` objects.
 #[pyfunction]
-fn parse_fastx_string(fastx_string: &str) -> PyResult<PyFastxReader> {
-    let reader = py_try!(parse_fastx_reader(Cursor::new(fastx_string.to_owned())));
+pub fn parse_fastx_string(fastx_string: &str) -> PyResult<PyFastxReader> {
+    let reader = RecordReader::from_reader(Cursor::new(fastx_string.to_owned()), "<string>")?;
     Ok(PyFastxReader {
         reader: reader.into(),
     })
@@ -435,9 +602,3 @@ pub fn py_decode_phred(qual: &str, base_64: bool, py: Python<'_>) -> PyResult<Py
         .map_err(|e| PyValueError::new_err(format!("Invalid Phred quality: {}", e)))?;
     Ok(PyTuple::new(py, &scores)?.into())
 }
-
-#[pymodule]
-fn needletail(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
-
-    Ok(())
-}
@@ -0,0 +1,349 @@
+//! Streaming FASTA/FASTQ reading and writing shared by the file-level functions
+
+use needletail::errors::ParseErrorKind;
+use needletail::parser::SequenceRecord;
+use needletail::{parse_fastx_reader, FastxReader};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+use crate::compression::OutputStream;
+use crate::pyfile::{InputSource, OutputTarget};
+
+create_exception!(capibex, FastxParseError, pyo3::exceptions::PyException);
+
+/// Default number of sequence characters per FASTA line
+pub const DEFAULT_LINE_LENGTH: usize = 60;
+
+/// An owned FASTA/FASTQ record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeqRecord {
+    pub id: Vec<u8>,
+    pub seq: Vec<u8>,
+    pub qual: Option<Vec<u8>>,
+}
+
+impl SeqRecord {
+    pub fn from_sequence_record(rec: &SequenceRecord) -> Self {
+        Self {
+            id: rec.id().to_vec(),
+            seq: rec.seq().into_owned(),
+            qual: rec.qual().map(|q| q.to_vec()),
+        }
+    }
+
+    /// The part of the header before the first whitespace
+    pub fn name(&self) -> &[u8] {
+        let end = self
+            .id
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(self.id.len());
+        &self.id[..end]
+    }
+}
+
+/// Iterator over the records of a possibly compressed FASTA/FASTQ stream
+///
+/// Empty inputs yield no records instead of an error.
+pub struct RecordReader {
+    inner: Option<Box<dyn FastxReader>>,
+    source: String,
+}
+
+impl RecordReader {
+    pub fn open(source: InputSource) -> PyResult<Self> {
+        let name = source.name();
+        Self::from_reader(source.open()?, &name)
+    }
+
+    /// Parse records from `reader`; `source` names it in error messages
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, source: &str) -> PyResult<Self> {
+        let inner = match parse_fastx_reader(reader) {
+            Ok(reader) => Some(reader),
+            Err(e) if e.kind == ParseErrorKind::EmptyFile => None,
+            Err(e) => {
+                return Err(FastxParseError::new_err(format!(
+                    "Failed to parse {}: {}",
+                    source, e
+                )))
+            }
+        };
+        Ok(Self {
+            inner,
+            source: source.to_string(),
+        })
+    }
+}
+
+impl Iterator for RecordReader {
+    type Item = PyResult<SeqRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.inner.as_mut()?;
+        let record = reader.next()?;
+        Some(
+            record
+                .map(|rec| SeqRecord::from_sequence_record(&rec))
+                .map_err(|e| {
+                    FastxParseError::new_err(format!(
+                        "Error parsing record in {}: {}",
+                        self.source, e
+                    ))
+                }),
+        )
+    }
+}
+
+/// Letter case applied to sequences on output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LetterCase {
+    #[default]
+    Keep,
+    Upper,
+    Lower,
+}
+
+impl LetterCase {
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "keep" => Ok(LetterCase::Keep),
+            "upper" => Ok(LetterCase::Upper),
+            "lower" => Ok(LetterCase::Lower),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown case '{}'. Use one of: keep, upper, lower.",
+                name
+            ))),
+        }
+    }
+}
+
+/// Output record format; `Auto` writes FASTQ only for records with qualities
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Auto,
+    Fasta,
+    Fastq,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(OutputFormat::Auto),
+            "fasta" | "fa" => Ok(OutputFormat::Fasta),
+            "fastq" | "fq" => Ok(OutputFormat::Fastq),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown format '{}'. Use one of: auto, fasta, fastq.",
+                name
+            ))),
+        }
+    }
+}
+
+/// Formatting options of a `RecordWriter`
+#[derive(Clone, Copy, Debug)]
+pub struct WriteOptions {
+    /// Sequence characters per FASTA line, 0 for no wrapping
+    pub line_length: usize,
+    pub case: LetterCase,
+    pub format: OutputFormat,
+    /// Encoding of the qualities passed in
+    pub input_phred: PhredEncoding,
+    /// Encoding of the qualities written out
+    pub output_phred: PhredEncoding,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            line_length: DEFAULT_LINE_LENGTH,
+            case: LetterCase::Keep,
+            format: OutputFormat::Auto,
+            input_phred: PhredEncoding::Phred33,
+            output_phred: PhredEncoding::Phred33,
+        }
+    }
+}
+
+/// Writes records as FASTA (wrapped at `line_length`, 0 for no wrapping) or,
+/// when they carry qualities, as FASTQ
+pub struct RecordWriter {
+    inner: OutputStream,
+    options: WriteOptions,
+}
+
+impl RecordWriter {
+    /// Create `target`, compressed with `compression` or as implied by its extension
+    pub fn create(
+        target: OutputTarget,
+        line_length: usize,
+        compression: Option<&str>,
+    ) -> PyResult<Self> {
+        Ok(Self::new(target.create(compression)?, line_length))
+    }
+
+    pub fn new(inner: OutputStream, line_length: usize) -> Self {
+        Self::with_options(
+            inner,
+            WriteOptions {
+                line_length,
+                ..WriteOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(inner: OutputStream, options: WriteOptions) -> Self {
+        Self { inner, options }
+    }
+
+    pub fn write_record(&mut self, record: &SeqRecord) -> PyResult<()> {
+        self.write_parts(&record.id, &record.seq, record.qual.as_deref())
+    }
+
+    pub fn write_parts(&mut self, id: &[u8], seq: &[u8], qual: Option<&[u8]>) -> PyResult<()> {
+        let options = self.options;
+        let seq = match options.case {
+            LetterCase::Keep => Cow::Borrowed(seq),
+            LetterCase::Upper => Cow::Owned(seq.to_ascii_uppercase()),
+            LetterCase::Lower => Cow::Owned(seq.to_ascii_lowercase()),
+        };
+        let qual = match (options.format, qual) {
+            (OutputFormat::Fasta, _) | (OutputFormat::Auto, None) => None,
+            (_, Some(qual)) => Some(reencode_phred(qual, options.input_phred, options.output_phred)),
+            (OutputFormat::Fastq, None) => {
+                return Err(PyValueError::new_err(format!(
+                    "Record '{}' has no qualities to write as FASTQ",
+                    String::from_utf8_lossy(id)
+                )))
+            }
+        };
+        let result = match qual {
+            Some(qual) => {
+                let qual = qual.map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Invalid quality in record '{}': {}",
+                        String::from_utf8_lossy(id),
+                        e
+                    ))
+                })?;
+                if qual.len() != seq.len() {
+                    return Err(PyValueError::new_err(format!(
+                        "Record '{}' has {} bases but {} quality values",
+                        String::from_utf8_lossy(id),
+                        seq.len(),
+                        qual.len()
+                    )));
+                }
+                write_fastq(&mut self.inner, id, &seq, &qual)
+            }
+            None => write_fasta(&mut self.inner, id, &seq, options.line_length),
+        };
+        result.map_err(|e| PyIOError::new_err(format!("Error writing record: {}", e)))
+    }
+
+    /// Flush buffered output without finishing the stream
+    pub fn flush(&mut self) -> PyResult<()> {
+        self.inner
+            .flush()
+            .map_err(|e| PyIOError::new_err(format!("Error writing output: {}", e)))
+    }
+
+    /// Flush the output and write any compression trailer
+    pub fn finish(self) -> PyResult<()> {
+        self.inner
+            .finish()
+            .map_err(|e| PyIOError::new_err(format!("Error writing output: {}", e)))
+    }
+}
+
+fn write_fasta<W: Write>(out: &mut W, id: &[u8], seq: &[u8], line_length: usize) -> std::io::Result<()> {
+    out.write_all(b">")?;
+    out.write_all(id)?;
+    out.write_all(b"\n")?;
+    // Empty sequences still get their (empty) sequence line
+    if seq.is_empty() {
+        return out.write_all(b"\n");
+    }
+    let width = if line_length == 0 { seq.len() } else { line_length };
+    for chunk in seq.chunks(width) {
+        out.write_all(chunk)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn write_fastq<W: Write>(out: &mut W, id: &[u8], seq: &[u8], qual: &[u8]) -> std::io::Result<()> {
+    out.write_all(b"@")?;
+    out.write_all(id)?;
+    out.write_all(b"\n")?;
+    out.write_all(seq)?;
+    out.write_all(b"\n+\n")?;
+    out.write_all(qual)?;
+    out.write_all(b"\n")
+}
+
+/// Phred quality score encodings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhredEncoding {
+    Phred33,
+    Phred64,
+}
+
+impl PhredEncoding {
+    /// The encoding with ASCII offset 33 or 64
+    pub fn from_offset(offset: u8) -> PyResult<Self> {
+        match offset {
+            33 => Ok(PhredEncoding::Phred33),
+            64 => Ok(PhredEncoding::Phred64),
+            _ => Err(PyValueError::new_err(format!(
+                "Unsupported Phred offset {}. Use 33 or 64.",
+                offset
+            ))),
+        }
+    }
+
+    pub fn offset(self) -> u8 {
+        match self {
+            PhredEncoding::Phred33 => 33,
+            PhredEncoding::Phred64 => 64,
+        }
+    }
+}
+
+/// Decode a quality string into Phred scores
+pub fn decode_phred(qual: &[u8], encoding: PhredEncoding) -> Result<Vec<u8>, String> {
+    let offset = encoding.offset();
+    qual.iter()
+        .map(|&q| match q {
+            b'!'..=b'~' if q >= offset => Ok(q - offset),
+            _ => Err(format!(
+                "character '{}' is out of range for Phred+{}",
+                q.escape_ascii(),
+                offset
+            )),
+        })
+        .collect()
+}
+
+/// Convert a quality string between Phred encodings
+pub fn reencode_phred(
+    qual: &[u8],
+    from: PhredEncoding,
+    to: PhredEncoding,
+) -> Result<Cow<'_, [u8]>, String> {
+    if from == to {
+        return Ok(Cow::Borrowed(qual));
+    }
+    let offset = to.offset();
+    decode_phred(qual, from)?
+        .into_iter()
+        .map(|score| match score.checked_add(offset) {
+            Some(q) if q <= b'~' => Ok(q),
+            _ => Err(format!("score {} cannot be encoded as Phred+{}", score, offset)),
+        })
+        .collect::<Result<Vec<u8>, String>>()
+        .map(Cow::Owned)
+}
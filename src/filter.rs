@@ -1,126 +1,82 @@
-use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::pyfunction;
-use std::fs::File;
-use std::io::{self, BufRead, BufWriter, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use rayon::prelude::*;
+
+use crate::fastx_io::{RecordReader, RecordWriter, DEFAULT_LINE_LENGTH};
+use crate::pool;
+use crate::pyfile::{InputSource, OutputTarget};
+
+/// Number of records matched per parallel batch
+const BATCH_SIZE: usize = 4096;
 
 /// Filter sequences by header pattern
 #[pyfunction]
+#[pyo3(signature = (input_file, patterns, output_file, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None))]
 pub fn filter_by_header(
-    input_file: &str,
+    py: Python<'_>,
+    input_file: InputSource,
     patterns: Vec<String>,
-    output_file: &str,
+    output_file: OutputTarget,
     threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
 ) -> PyResult<usize> {
-    filter_sequences(input_file, patterns, output_file, threads, false)
+    let writer = RecordWriter::create(output_file, line_length, compression)?;
+    filter_sequences(py, input_file, patterns, writer, threads, false)
 }
 
 /// Filter sequences by header pattern (inverted)
 #[pyfunction]
+#[pyo3(signature = (input_file, patterns, output_file, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None))]
 pub fn filter_by_header_invert(
-    input_file: &str,
+    py: Python<'_>,
+    input_file: InputSource,
     patterns: Vec<String>,
-    output_file: &str,
+    output_file: OutputTarget,
     threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
 ) -> PyResult<usize> {
-    filter_sequences(input_file, patterns, output_file, threads, true)
+    let writer = RecordWriter::create(output_file, line_length, compression)?;
+    filter_sequences(py, input_file, patterns, writer, threads, true)
 }
 
 /// Internal function to filter sequences
 fn filter_sequences(
-    input_file: &str,
+    py: Python<'_>,
+    input_file: InputSource,
     patterns: Vec<String>,
-    output_file: &str,
+    mut writer: RecordWriter,
     threads: Option<usize>,
     invert: bool,
 ) -> PyResult<usize> {
-    // Set up parallelism
-    let threads = threads.unwrap_or_else(|| rayon::current_num_threads());
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build_global()
-        .map_err(|e| PyValueError::new_err(format!("Failed to set up thread pool: {}", e)))?;
-
-    // Output file
-    let output_path = Path::new(output_file);
-    let output_file = File::create(output_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to create output file: {}", e)))?;
-    let writer = Arc::new(Mutex::new(BufWriter::new(output_file)));
-    let count = Arc::new(Mutex::new(0));
-
-    // Open the input file
-    let input_path = Path::new(input_file);
-    let file = File::open(input_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to open input file: {}", e)))?;
-    
-    let reader = io::BufReader::new(file);
-    let mut lines = reader.lines();
-    
-    // Process FASTA file
-    let mut current_header = String::new();
-    let mut current_sequence = String::new();
-    
-    while let Some(Ok(line)) = lines.next() {
-        if line.starts_with('>') {
-            // Process previous sequence if there is one
-            if !current_header.is_empty() && !current_sequence.is_empty() {
-                let matches = patterns.iter().any(|pattern| current_header.contains(pattern));
-                if matches ^ invert {
-                    // Write to output file
-                    let mut writer_lock = writer.lock().unwrap();
-                    if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                        return Err(PyValueError::new_err(format!("Error writing header: {}", e)));
-                    }
-                    
-                    // Write sequence with line breaks every 60 characters
-                    for chunk in current_sequence.as_bytes().chunks(60) {
-                        if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                            return Err(PyValueError::new_err(format!("Error writing sequence: {}", e)));
-                        }
-                    }
-                    
-                    // Increment count
-                    let mut count_lock = count.lock().unwrap();
-                    *count_lock += 1;
-                }
-            }
-            
-            // Extract header
-            current_header = line[1..].trim().to_string();
-            current_sequence = String::new();
-        } else {
-            // Append to current sequence
-            current_sequence.push_str(line.trim());
-        }
-    }
-    
-    // Process the last sequence
-    if !current_header.is_empty() && !current_sequence.is_empty() {
-        let matches = patterns.iter().any(|pattern| current_header.contains(pattern));
-        if matches ^ invert {
-            // Write to output file
-            let mut writer_lock = writer.lock().unwrap();
-            if let Err(e) = writeln!(writer_lock, ">{}", current_header) {
-                return Err(PyValueError::new_err(format!("Error writing header: {}", e)));
-            }
-            
-            // Write sequence with line breaks every 60 characters
-            for chunk in current_sequence.as_bytes().chunks(60) {
-                if let Err(e) = writeln!(writer_lock, "{}", std::str::from_utf8(chunk).unwrap()) {
-                    return Err(PyValueError::new_err(format!("Error writing sequence: {}", e)));
+    // Records are matched in parallel batches and written back in input order
+    let count = pool::run(py, threads, |interrupt| {
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut reader = RecordReader::open(input_file)?.peekable();
+        while let Some(record) = reader.next() {
+            interrupt.check()?;
+            batch.push(record?);
+            if batch.len() == BATCH_SIZE || reader.peek().is_none() {
+                let keep: Vec<bool> = batch
+                    .par_iter()
+                    .map(|record| {
+                        let header = String::from_utf8_lossy(&record.id);
+                        let matches = patterns.iter().any(|p| header.contains(p.as_str()));
+                        matches ^ invert
+                    })
+                    .collect();
+                for (record, _) in batch.iter().zip(keep).filter(|(_, keep)| *keep) {
+                    writer.write_record(record)?;
+                    count += 1;
                 }
+                batch.clear();
             }
-            
-            // Increment count
-            let mut count_lock = count.lock().unwrap();
-            *count_lock += 1;
         }
-    }
-    
-    // Fix the lifetime issue by getting the value before returning
-    let result = *count.lock().unwrap();
-    Ok(result)
-}
+        Ok(count)
+    })?;
 
+    writer.finish()?;
+    Ok(count)
+}
@@ -0,0 +1,714 @@
+//! NCBI and custom genetic codes
+//!
+//! Codons are indexed in NCBI order (TTT, TTC, TTA, TTG, TCT, ..., GGG), so a
+//! code is a 64-letter amino acid string plus the codons that may start
+//! translation.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Genetic codes / variables sourced from Seals2 by Yuri Wolf (https://github.com/YuriWolf-ncbi/seals-2/blob/master/bin/misc/orf)
+// Original Perl implementation for comprehensive genetic code support
+
+/// An NCBI translation table, fetched on 03/07/2025 from
+/// https://www.ncbi.nlm.nih.gov/Taxonomy/Utils/wprintgc.cgi
+struct NcbiCode {
+    id: u32,
+    short_name: &'static str,
+    name: &'static str,
+    /// Amino acids of the 64 codons
+    amino_acids: &'static str,
+    /// M for start codons, - for others
+    starts: &'static str,
+}
+
+const NCBI_CODES: [NcbiCode; 26] = [
+    NcbiCode {
+        id: 1,
+        short_name: "Standard",
+        name: "Standard",
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M----------------------------",
+    },
+    NcbiCode {
+        id: 2,
+        short_name: "Vertebrate",
+        name: "Vertebrate Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        starts: "--------------------------------MMMM---------------M------------",
+    },
+    NcbiCode {
+        id: 3,
+        short_name: "Yeast",
+        name: "Yeast Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------------------------------MM----------------------------",
+    },
+    NcbiCode {
+        id: 4,
+        short_name: "MoldProt",
+        name: "Mold Mitochondrial; Protozoan Mitochondrial; Coelenterate Mitochondrial; Mycoplasma; Spiroplasma",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--MM---------------M------------MMMM---------------M------------",
+    },
+    NcbiCode {
+        id: 5,
+        short_name: "Invertebrate",
+        name: "Invertebrate Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        starts: "---M----------------------------MMMM---------------M------------",
+    },
+    NcbiCode {
+        id: 6,
+        short_name: "Ciliate",
+        name: "Ciliate Nuclear; Dasycladacean Nuclear; Hexamita Nuclear",
+        amino_acids: "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 9,
+        short_name: "Echinoderm",
+        name: "Echinoderm Mitochondrial; Flatworm Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M---------------M------------",
+    },
+    NcbiCode {
+        id: 10,
+        short_name: "Euplotid",
+        name: "Euplotid Nuclear",
+        amino_acids: "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 11,
+        short_name: "Bacterial",
+        name: "Bacterial and Plant Plastid",
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M---------------M------------MMMM---------------M------------",
+    },
+    NcbiCode {
+        id: 12,
+        short_name: "AltYeast",
+        name: "Alternative Yeast Nuclear",
+        amino_acids: "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-------------------M---------------M----------------------------",
+    },
+    NcbiCode {
+        id: 13,
+        short_name: "Ascidian",
+        name: "Ascidian Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 14,
+        short_name: "AltFlatWorm",
+        name: "Alternative Flatworm Mitochondrial",
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 15,
+        short_name: "Blepharisma",
+        name: "Blepharisma Macronuclear",
+        amino_acids: "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 16,
+        short_name: "Chlorophycean",
+        name: "Chlorophycean Mitochondrial",
+        amino_acids: "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 21,
+        short_name: "Trematode",
+        name: "Trematode Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M---------------M------------",
+    },
+    NcbiCode {
+        id: 22,
+        short_name: "Scenedesmus",
+        name: "Scenedesmus obliquus mitochondrial",
+        amino_acids: "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 23,
+        short_name: "Thraustochytrium",
+        name: "Thraustochytrium mitochondrial",
+        amino_acids: "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------------------------M--M---------------M------------",
+    },
+    NcbiCode {
+        id: 24,
+        short_name: "Rhabdopleuridae",
+        name: "Pterobranchia mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M---------------M------------",
+    },
+    NcbiCode {
+        id: 25,
+        short_name: "CandidateDivision",
+        name: "Candidate Division SR1 and Gracilibacteria",
+        amino_acids: "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M-------------------------------M---------------M------------",
+    },
+    NcbiCode {
+        id: 26,
+        short_name: "Pachysolen",
+        name: "Pachysolen tannophilus Nuclear Code",
+        amino_acids: "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-------------------M---------------M----------------------------",
+    },
+    NcbiCode {
+        id: 27,
+        short_name: "Karyorelict",
+        name: "Karyorelict Nuclear Code",
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 28,
+        short_name: "Condylostoma",
+        name: "Condylostoma Nuclear Code",
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 29,
+        short_name: "Mesodinium",
+        name: "Mesodinium Nuclear Code",
+        amino_acids: "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 30,
+        short_name: "Peritrich",
+        name: "Peritrich Nuclear Code",
+        amino_acids: "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 31,
+        short_name: "Blastocrithidia",
+        name: "Blastocrithidia Nuclear Code",
+        amino_acids: "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    NcbiCode {
+        id: 33,
+        short_name: "Cephalodiscidae",
+        name: "Cephalodiscidae Mitochondrial UAA-Tyr Code",
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M---------------M------------",
+    },
+];
+
+/// The bases each byte stands for as a bit mask (T=1, C=2, A=4, G=8)
+///
+/// IUPAC codes are accepted in either case and U reads as T. Gaps (`-`, `.`)
+/// have no bits and any other byte reads as N.
+const BASE_MASKS: [u8; 256] = {
+    let codes: [(u8, u8); 15] = [
+        (b'T', 0b0001),
+        (b'U', 0b0001),
+        (b'C', 0b0010),
+        (b'A', 0b0100),
+        (b'G', 0b1000),
+        (b'Y', 0b0011),
+        (b'W', 0b0101),
+        (b'K', 0b1001),
+        (b'M', 0b0110),
+        (b'S', 0b1010),
+        (b'R', 0b1100),
+        (b'H', 0b0111),
+        (b'B', 0b1011),
+        (b'D', 0b1101),
+        (b'V', 0b1110),
+    ];
+    let mut masks = [0b1111; 256];
+    let mut i = 0;
+    while i < codes.len() {
+        let (base, mask) = codes[i];
+        masks[base as usize] = mask;
+        masks[base.to_ascii_lowercase() as usize] = mask;
+        i += 1;
+    }
+    masks[b'-' as usize] = 0;
+    masks[b'.' as usize] = 0;
+    masks
+};
+
+/// Position of a codon in the lookup table: the masks of its three bases
+#[inline]
+fn lookup_key(codon: &[u8]) -> usize {
+    (BASE_MASKS[codon[0] as usize] as usize) << 8
+        | (BASE_MASKS[codon[1] as usize] as usize) << 4
+        | BASE_MASKS[codon[2] as usize] as usize
+}
+
+/// Translate the codon with lookup `key`, given the amino acids of the 64
+/// codons
+///
+/// Ambiguous codons resolve to an amino acid when every codon they stand for
+/// agrees (`GCN` is A, `YTR` is L) and to X otherwise. A gap codon `---`
+/// translates to `-` and codons with one or two gaps to X.
+fn resolve_codon(key: usize, amino_acids: &[u8; 64]) -> u8 {
+    let masks = [key >> 8, key >> 4 & 0xF, key & 0xF];
+    if masks == [0, 0, 0] {
+        return b'-';
+    }
+    let bases = |mask: usize| (0..4).filter(move |bit| mask & 1 << bit != 0);
+
+    let mut amino_acid = None;
+    for pos1 in bases(masks[0]) {
+        for pos2 in bases(masks[1]) {
+            for pos3 in bases(masks[2]) {
+                let candidate = amino_acids[pos1 * 16 + pos2 * 4 + pos3];
+                match amino_acid {
+                    Some(previous) if previous != candidate => return b'X',
+                    _ => amino_acid = Some(candidate),
+                }
+            }
+        }
+    }
+    // Codons with a gap among bases expand to nothing
+    amino_acid.unwrap_or(b'X')
+}
+
+/// The DNA codon at `index` in NCBI order
+pub fn codon_at(index: usize) -> String {
+    const BASES: [char; 4] = ['T', 'C', 'A', 'G'];
+    [index / 16, index / 4 % 4, index % 4]
+        .iter()
+        .map(|&i| BASES[i])
+        .collect()
+}
+
+/// The table index of a DNA or RNA codon in either case, `None` if it has
+/// other characters
+pub fn codon_index(codon: &[u8]) -> Option<usize> {
+    if codon.len() != 3 {
+        return None;
+    }
+    codon.iter().try_fold(0, |index, base| {
+        let value = match base.to_ascii_uppercase() {
+            b'T' | b'U' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => return None,
+        };
+        Some(index * 4 + value)
+    })
+}
+
+/// The table index of a codon given by the user
+pub fn parse_codon(codon: &str) -> PyResult<usize> {
+    codon_index(codon.as_bytes()).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Invalid codon '{}'; expected three of A, C, G, T or U",
+            codon
+        ))
+    })
+}
+
+/// Check a one-letter amino acid code, `*` being a stop
+fn amino_acid_code(amino_acid: &str) -> PyResult<u8> {
+    match amino_acid.as_bytes() {
+        [code] if code.is_ascii_alphabetic() || *code == b'*' => Ok(code.to_ascii_uppercase()),
+        _ => Err(PyValueError::new_err(format!(
+            "Invalid amino acid '{}'; expected a one-letter code or '*'",
+            amino_acid
+        ))),
+    }
+}
+
+/// A genetic code: the amino acid of every codon and the start codons.
+///
+/// Parameters
+/// ----------
+/// code : int, str or GeneticCode, default: 1
+///     An NCBI translation table id, or a name such as 'Standard',
+///     'Bacterial' or 'Vertebrate Mitochondrial' (case-insensitive).
+///
+/// Attributes
+/// ----------
+/// id : int or None
+///     The NCBI table id, None for custom codes.
+/// name : str
+///     The code name.
+/// amino_acids : str
+///     The amino acids of the 64 codons in NCBI order (TTT, TTC, ..., GGG).
+/// codons : dict of str to str
+///     The amino acid of every codon; stops are '*'.
+/// starts : list of str
+///     The codons that may start translation.
+/// stops : list of str
+///     The stop codons.
+///
+/// Raises
+/// ------
+/// ValueError
+///     If there is no NCBI code with that id or name.
+#[pyclass(frozen, eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneticCode {
+    id: Option<u32>,
+    name: String,
+    amino_acids: [u8; 64],
+    starts: [bool; 64],
+    /// The translation of every codon by `lookup_key`, ambiguous ones included
+    lookup: Box<[u8; 4096]>,
+}
+
+impl GeneticCode {
+    fn new_code(id: Option<u32>, name: String, amino_acids: [u8; 64], starts: [bool; 64]) -> Self {
+        let mut lookup = Box::new([b'X'; 4096]);
+        for (key, amino_acid) in lookup.iter_mut().enumerate() {
+            *amino_acid = resolve_codon(key, &amino_acids);
+        }
+        GeneticCode {
+            id,
+            name,
+            amino_acids,
+            starts,
+            lookup,
+        }
+    }
+
+    fn from_ncbi(code: &NcbiCode) -> Self {
+        let mut amino_acids = [b'X'; 64];
+        amino_acids.copy_from_slice(code.amino_acids.as_bytes());
+        let mut starts = [false; 64];
+        for (start, marker) in starts.iter_mut().zip(code.starts.bytes()) {
+            *start = marker == b'M';
+        }
+        Self::new_code(Some(code.id), code.name.to_string(), amino_acids, starts)
+    }
+
+    /// The NCBI codes, with their lookup tables built once
+    fn ncbi_codes() -> &'static [GeneticCode] {
+        static CODES: OnceLock<Vec<GeneticCode>> = OnceLock::new();
+        CODES.get_or_init(|| NCBI_CODES.iter().map(Self::from_ncbi).collect())
+    }
+
+    /// Look up an NCBI code by id
+    pub fn from_id(id: u32) -> PyResult<Self> {
+        Self::ncbi_codes()
+            .iter()
+            .find(|code| code.id == Some(id))
+            .cloned()
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "Unknown genetic code {}. See list_genetic_codes().",
+                    id
+                ))
+            })
+    }
+
+    /// Look up an NCBI code by its short or full name, or any part of a full
+    /// name such as "Mycoplasma"
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        if let Ok(id) = name.trim().parse() {
+            return Self::from_id(id);
+        }
+        let wanted = name.trim().to_lowercase();
+        let matches = |candidate: &str| candidate.trim().to_lowercase() == wanted;
+        NCBI_CODES
+            .iter()
+            .find(|code| {
+                matches(code.short_name) || matches(code.name) || code.name.split(';').any(matches)
+            })
+            .map(|code| Self::from_id(code.id))
+            .unwrap_or_else(|| {
+                Err(PyValueError::new_err(format!(
+                    "Unknown genetic code '{}'. See list_genetic_codes().",
+                    name
+                )))
+            })
+    }
+
+    pub fn is_start(&self, index: usize) -> bool {
+        self.starts[index]
+    }
+
+    /// The amino acid of the codon at table `index`
+    pub fn amino_acid_at(&self, index: usize) -> u8 {
+        self.amino_acids[index]
+    }
+
+    /// Translate one codon, which must have three bytes
+    #[inline]
+    pub fn amino_acid(&self, codon: &[u8]) -> u8 {
+        self.lookup[lookup_key(codon)]
+    }
+
+    /// Translate the complete codons of a sequence
+    pub fn translate_codons(&self, seq: &[u8]) -> Vec<u8> {
+        seq.chunks_exact(3)
+            .map(|codon| self.amino_acid(codon))
+            .collect()
+    }
+
+    fn codons_where(&self, keep: impl Fn(usize) -> bool) -> Vec<String> {
+        (0..64).filter(|&i| keep(i)).map(codon_at).collect()
+    }
+}
+
+#[pymethods]
+impl GeneticCode {
+    #[new]
+    #[pyo3(signature = (code=CodeSelection::Id(1)))]
+    fn new(code: CodeSelection) -> PyResult<Self> {
+        code.resolve()
+    }
+
+    /// Build a custom genetic code.
+    ///
+    /// Parameters
+    /// ----------
+    /// amino_acids : str
+    ///     The amino acids of the 64 codons in NCBI order (TTT, TTC, ...,
+    ///     GGG), with '*' for stops.
+    /// starts : list of str, optional
+    ///     The start codons. Defaults to ATG.
+    /// name : str, default: 'custom'
+    ///     The code name.
+    ///
+    /// Returns
+    /// -------
+    /// GeneticCode
+    #[staticmethod]
+    #[pyo3(signature = (amino_acids, starts=None, name="custom"))]
+    fn custom(amino_acids: &str, starts: Option<Vec<String>>, name: &str) -> PyResult<Self> {
+        let letters: Vec<u8> = amino_acids
+            .chars()
+            .map(|c| amino_acid_code(&c.to_string()))
+            .collect::<PyResult<_>>()?;
+        let amino_acids: [u8; 64] = letters.try_into().map_err(|letters: Vec<u8>| {
+            PyValueError::new_err(format!(
+                "A genetic code needs 64 amino acids, got {}",
+                letters.len()
+            ))
+        })?;
+        let mut start_codons = [false; 64];
+        for codon in starts.unwrap_or_else(|| vec!["ATG".to_string()]) {
+            start_codons[parse_codon(&codon)?] = true;
+        }
+        Ok(Self::new_code(
+            None,
+            name.to_string(),
+            amino_acids,
+            start_codons,
+        ))
+    }
+
+    /// Reassign codons to other amino acids.
+    ///
+    /// Parameters
+    /// ----------
+    /// changes : dict of str to str
+    ///     New one-letter amino acids by codon, for example
+    ///     ``{"UGA": "W"}``, ``{"UAG": "O"}`` (pyrrolysine) or
+    ///     ``{"UGA": "U"}`` (selenocysteine). '*' makes a codon a stop.
+    ///
+    /// Returns
+    /// -------
+    /// GeneticCode
+    ///     A custom copy of this code.
+    fn reassign(&self, changes: HashMap<String, String>) -> PyResult<Self> {
+        let mut changes: Vec<(usize, u8)> = changes
+            .iter()
+            .map(|(codon, amino_acid)| Ok((parse_codon(codon)?, amino_acid_code(amino_acid)?)))
+            .collect::<PyResult<_>>()?;
+        changes.sort_unstable();
+        let description: Vec<String> = changes
+            .iter()
+            .map(|&(index, amino_acid)| format!("{}={}", codon_at(index), amino_acid as char))
+            .collect();
+        let mut amino_acids = self.amino_acids;
+        for (index, amino_acid) in changes {
+            amino_acids[index] = amino_acid;
+        }
+        Ok(Self::new_code(
+            None,
+            format!("{}; {}", self.name, description.join(",")),
+            amino_acids,
+            self.starts,
+        ))
+    }
+
+    /// Read through stop codons.
+    ///
+    /// Parameters
+    /// ----------
+    /// codons : list of str, optional
+    ///     The stop codons translated instead of terminating. Defaults to
+    ///     every stop codon.
+    /// amino_acid : str, default: 'X'
+    ///     What those codons translate to.
+    ///
+    /// Returns
+    /// -------
+    /// GeneticCode
+    ///     A custom copy of this code.
+    #[pyo3(signature = (codons=None, amino_acid="X"))]
+    fn readthrough(&self, codons: Option<Vec<String>>, amino_acid: &str) -> PyResult<Self> {
+        let codons = codons.unwrap_or_else(|| self.stops());
+        let changes = codons
+            .into_iter()
+            .map(|codon| (codon, amino_acid.to_string()))
+            .collect();
+        self.reassign(changes)
+    }
+
+    /// Translate one codon, resolving IUPAC ambiguity as `translate` does.
+    fn translate_codon(&self, codon: &str) -> String {
+        match codon.as_bytes() {
+            codon @ [_, _, _] => (self.amino_acid(codon) as char).to_string(),
+            _ => "X".to_string(),
+        }
+    }
+
+    #[getter]
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[getter(amino_acids)]
+    fn amino_acids_str(&self) -> String {
+        String::from_utf8_lossy(&self.amino_acids).into_owned()
+    }
+
+    #[getter]
+    fn codons<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        // Built in table order, which dicts keep
+        let codons = PyDict::new(py);
+        for (i, &amino_acid) in self.amino_acids.iter().enumerate() {
+            codons.set_item(codon_at(i), (amino_acid as char).to_string())?;
+        }
+        Ok(codons)
+    }
+
+    #[getter]
+    fn starts(&self) -> Vec<String> {
+        self.codons_where(|i| self.starts[i])
+    }
+
+    #[getter]
+    fn stops(&self) -> Vec<String> {
+        self.codons_where(|i| self.amino_acids[i] == b'*')
+    }
+
+    fn __repr__(&self) -> String {
+        match self.id {
+            Some(id) => format!("GeneticCode({}, '{}')", id, self.name),
+            None => format!("GeneticCode('{}')", self.name),
+        }
+    }
+}
+
+/// A genetic code argument: an NCBI id, a name or a `GeneticCode`
+#[derive(FromPyObject)]
+pub enum CodeSelection {
+    Code(GeneticCode),
+    Id(u32),
+    Name(String),
+}
+
+impl CodeSelection {
+    pub fn resolve(self) -> PyResult<GeneticCode> {
+        match self {
+            CodeSelection::Code(code) => Ok(code),
+            CodeSelection::Id(id) => GeneticCode::from_id(id),
+            CodeSelection::Name(name) => GeneticCode::from_name(&name),
+        }
+    }
+}
+
+/// Get available genetic code tables
+#[pyfunction]
+pub fn list_genetic_codes() -> PyResult<Vec<(u32, String)>> {
+    Ok(NCBI_CODES
+        .iter()
+        .map(|code| (code.id, code.name.to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IUPAC code of each base mask in `BASE_MASKS`
+    const IUPAC_CODES: &[u8; 16] = b"-TCYAWMHGKSBRDVN";
+
+    /// The translation before the lookup table, expanding every IUPAC code
+    /// of the codon to the bases it stands for
+    fn expand_codon(codon: &[u8], amino_acids: &[u8; 64]) -> u8 {
+        if codon == b"---" {
+            return b'-';
+        }
+        let expand = |base: u8| {
+            let mask = IUPAC_CODES.iter().position(|&code| code == base).unwrap();
+            (0..4).filter(move |bit| mask & 1 << bit != 0)
+        };
+        let mut translations = expand(codon[0]).flat_map(|pos1| {
+            expand(codon[1])
+                .flat_map(move |pos2| expand(codon[2]).map(move |pos3| pos1 * 16 + pos2 * 4 + pos3))
+        });
+        match translations.next() {
+            Some(first) if translations.all(|index| amino_acids[index] == amino_acids[first]) => {
+                amino_acids[first]
+            }
+            _ => b'X',
+        }
+    }
+
+    #[test]
+    fn lookup_matches_resolve_codon_for_every_mask() {
+        for code in GeneticCode::ncbi_codes() {
+            for key in 0..4096 {
+                let codon = [key >> 8, key >> 4 & 0xF, key & 0xF].map(|mask| IUPAC_CODES[mask]);
+                assert_eq!(lookup_key(&codon), key);
+                assert_eq!(lookup_key(&codon.to_ascii_lowercase()), key);
+
+                let expected = resolve_codon(key, &code.amino_acids);
+                assert_eq!(code.amino_acid(&codon), expected);
+                assert_eq!(code.amino_acid(&codon.to_ascii_lowercase()), expected);
+                assert_eq!(
+                    expand_codon(&codon, &code.amino_acids),
+                    expected,
+                    "codon {} in table {:?}",
+                    String::from_utf8_lossy(&codon),
+                    code.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rna_codons_translate_as_dna() {
+        let code = GeneticCode::from_id(1).unwrap();
+        for index in 0..64 {
+            let dna = codon_at(index);
+            let rna = dna.replace('T', "U");
+            assert_eq!(code.amino_acid(rna.as_bytes()), code.amino_acid_at(index));
+            assert_eq!(code.amino_acid(dna.as_bytes()), code.amino_acid_at(index));
+        }
+        assert_eq!(code.amino_acid(b"GCN"), b'A');
+        assert_eq!(code.amino_acid(b"YTR"), b'L');
+        assert_eq!(code.amino_acid(b"-A-"), b'X');
+    }
+}
@@ -0,0 +1,759 @@
+//! GFF3 and GTF annotation reading and writing
+//!
+//! Features are returned to Python as a dict of columns so they can be passed
+//! straight to `polars.DataFrame`. GFF3 attributes are percent-decoded and
+//! split on commas; repeated GTF attributes become multiple values.
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Cursor, Write};
+
+use crate::fastx_io::{RecordReader, RecordWriter, SeqRecord, DEFAULT_LINE_LENGTH};
+use crate::pool::{self, Interrupt};
+use crate::pyfile::{InputSource, OutputTarget};
+
+/// Annotation file flavours
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    Gff3,
+    Gtf,
+}
+
+impl AnnotationFormat {
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gff" | "gff3" => Ok(AnnotationFormat::Gff3),
+            "gtf" | "gff2" => Ok(AnnotationFormat::Gtf),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown annotation format '{}'. Use 'gff3' or 'gtf'.",
+                name
+            ))),
+        }
+    }
+
+    /// Guess the format from the file name, ignoring compression suffixes
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        let stem = [".gz", ".bgz", ".bz2", ".xz", ".zst"]
+            .iter()
+            .find_map(|ext| lower.strip_suffix(ext))
+            .unwrap_or(&lower);
+        if stem.ends_with(".gtf") {
+            Some(AnnotationFormat::Gtf)
+        } else if stem.ends_with(".gff") || stem.ends_with(".gff3") {
+            Some(AnnotationFormat::Gff3)
+        } else {
+            None
+        }
+    }
+
+    /// Guess the format from the attribute column of a feature line
+    pub fn from_attributes(attributes: &str) -> Self {
+        if attributes.contains('"') || !attributes.contains('=') && attributes.contains(' ') {
+            AnnotationFormat::Gtf
+        } else {
+            AnnotationFormat::Gff3
+        }
+    }
+}
+
+/// Namespace of feature IDs: GTF genes and transcripts may reuse one name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IdSpace {
+    /// GFF3 `ID`s, shared by all features
+    #[default]
+    Feature,
+    Gene,
+    Transcript,
+}
+
+/// One feature line, with 1-based inclusive coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Feature {
+    pub seqid: String,
+    pub source: Option<String>,
+    pub feature_type: String,
+    pub start: u64,
+    pub end: u64,
+    pub score: Option<f64>,
+    pub strand: Option<char>,
+    pub phase: Option<u8>,
+    pub attributes: Vec<(String, Vec<String>)>,
+    /// `ID` in GFF3; `gene_id`/`transcript_id` of genes and transcripts in GTF
+    pub id: Option<String>,
+    /// `Parent` in GFF3; the transcript (or gene) of other GTF features
+    pub parents: Vec<String>,
+    /// Namespaces of `id` and `parents` when building the hierarchy
+    pub id_space: IdSpace,
+    pub parent_space: IdSpace,
+}
+
+impl Feature {
+    pub fn attribute(&self, key: &str) -> Option<&[String]> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    pub fn first_attribute(&self, key: &str) -> Option<&str> {
+        self.attribute(key)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+
+    /// Parse a tab-separated feature line
+    fn parse(line: &str, format: AnnotationFormat, line_number: usize) -> PyResult<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 8 {
+            return Err(PyValueError::new_err(format!(
+                "Expected 9 columns on line {}, found {}",
+                line_number,
+                fields.len()
+            )));
+        }
+        let invalid = |column: &str, value: &str| {
+            PyValueError::new_err(format!(
+                "Invalid {} '{}' on line {}",
+                column, value, line_number
+            ))
+        };
+        let optional = |value: &str| (value != ".").then(|| unescape(value));
+        let coordinate =
+            |column: &str, value: &str| value.parse::<u64>().map_err(|_| invalid(column, value));
+
+        let start = coordinate("start", fields[3])?;
+        let end = coordinate("end", fields[4])?;
+        if start == 0 || end < start.saturating_sub(1) {
+            return Err(PyValueError::new_err(format!(
+                "Invalid range {}-{} on line {}; coordinates are 1-based and inclusive",
+                start, end, line_number
+            )));
+        }
+        let score = match fields[5] {
+            "." => None,
+            value => Some(value.parse::<f64>().map_err(|_| invalid("score", value))?),
+        };
+        let strand = match fields[6] {
+            "." => None,
+            "+" => Some('+'),
+            "-" => Some('-'),
+            "?" => Some('?'),
+            value => return Err(invalid("strand", value)),
+        };
+        let phase = match fields[7] {
+            "." => None,
+            "0" => Some(0),
+            "1" => Some(1),
+            "2" => Some(2),
+            value => return Err(invalid("phase", value)),
+        };
+        let raw_attributes = fields.get(8).copied().unwrap_or(".");
+        let attributes = match (raw_attributes, format) {
+            (".", _) | ("", _) => Vec::new(),
+            (raw, AnnotationFormat::Gff3) => parse_gff3_attributes(raw),
+            (raw, AnnotationFormat::Gtf) => parse_gtf_attributes(raw, line_number)?,
+        };
+
+        let mut feature = Feature {
+            seqid: unescape(fields[0]),
+            source: optional(fields[1]),
+            feature_type: unescape(fields[2]),
+            start,
+            end,
+            score,
+            strand,
+            phase,
+            attributes,
+            id: None,
+            parents: Vec::new(),
+            id_space: IdSpace::Feature,
+            parent_space: IdSpace::Feature,
+        };
+        match format {
+            AnnotationFormat::Gff3 => {
+                feature.id = feature.first_attribute("ID").map(str::to_string);
+                feature.parents = feature.attribute("Parent").unwrap_or_default().to_vec();
+            }
+            AnnotationFormat::Gtf => {
+                let gene_id = feature.first_attribute("gene_id").map(str::to_string);
+                let transcript_id = feature.first_attribute("transcript_id").map(str::to_string);
+                match feature.feature_type.as_str() {
+                    "gene" => {
+                        feature.id = gene_id;
+                        feature.id_space = IdSpace::Gene;
+                    }
+                    "transcript" | "mRNA" => {
+                        feature.id = transcript_id;
+                        feature.id_space = IdSpace::Transcript;
+                        feature.parents = gene_id.into_iter().collect();
+                        feature.parent_space = IdSpace::Gene;
+                    }
+                    _ => {
+                        (feature.parents, feature.parent_space) = match transcript_id {
+                            Some(id) => (vec![id], IdSpace::Transcript),
+                            None => (gene_id.into_iter().collect(), IdSpace::Gene),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(feature)
+    }
+
+    /// Format as a feature line, without the trailing newline
+    pub fn to_line(&self, format: AnnotationFormat) -> String {
+        let or_dot = |value: Option<String>| value.unwrap_or_else(|| ".".to_string());
+        let attributes = match format {
+            AnnotationFormat::Gff3 => self
+                .attributes
+                .iter()
+                .map(|(key, values)| {
+                    let values: Vec<String> = values.iter().map(|v| escape(v, true)).collect();
+                    format!("{}={}", escape(key, true), values.join(","))
+                })
+                .collect::<Vec<_>>()
+                .join(";"),
+            AnnotationFormat::Gtf => self
+                .attributes
+                .iter()
+                .flat_map(|(key, values)| {
+                    values.iter().map(move |value| {
+                        format!(
+                            "{} \"{}\";",
+                            key,
+                            value.replace('\\', "\\\\").replace('"', "\\\"")
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        [
+            escape(&self.seqid, false),
+            or_dot(self.source.as_deref().map(|s| escape(s, false))),
+            escape(&self.feature_type, false),
+            self.start.to_string(),
+            self.end.to_string(),
+            or_dot(self.score.map(|score| score.to_string())),
+            or_dot(self.strand.map(String::from)),
+            or_dot(self.phase.map(|phase| phase.to_string())),
+            if attributes.is_empty() {
+                ".".to_string()
+            } else {
+                attributes
+            },
+        ]
+        .join("\t")
+    }
+}
+
+/// Decode `%XX` escapes; malformed escapes are kept as they are
+fn unescape(value: &str) -> String {
+    if !value.contains('%') {
+        return value.to_string();
+    }
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Percent-encode the characters GFF3 reserves in columns (and, with
+/// `attribute`, in attribute keys and values)
+fn escape(value: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        let reserved =
+            c == '%' || c.is_control() || attribute && matches!(c, ';' | '=' | '&' | ',');
+        if reserved {
+            let mut buffer = [0u8; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Parse `key=value1,value2;key2=value` attributes
+fn parse_gff3_attributes(raw: &str) -> Vec<(String, Vec<String>)> {
+    raw.split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let values = value.split(',').map(unescape).collect();
+            (unescape(key.trim()), values)
+        })
+        .collect()
+}
+
+/// Parse `key "value"; key2 value2;` attributes, merging repeated keys
+fn parse_gtf_attributes(raw: &str, line_number: usize) -> PyResult<Vec<(String, Vec<String>)>> {
+    let mut attributes: Vec<(String, Vec<String>)> = Vec::new();
+    let mut chars = raw.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ';') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+        let key: String = std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect();
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let value = if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => value.extend(chars.next()),
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(PyValueError::new_err(format!(
+                            "Unterminated quote in attributes on line {}",
+                            line_number
+                        )))
+                    }
+                }
+            }
+            value
+        } else {
+            std::iter::from_fn(|| chars.next_if(|c| *c != ';'))
+                .collect::<String>()
+                .trim()
+                .to_string()
+        };
+        match attributes.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => attributes.push((key, vec![value])),
+        }
+    }
+    Ok(attributes)
+}
+
+/// The features of an annotation file and the sequences of its `##FASTA`
+/// section
+#[derive(Debug, Default)]
+pub struct Annotation {
+    pub features: Vec<Feature>,
+    pub sequences: Vec<SeqRecord>,
+}
+
+impl Annotation {
+    /// Read features from `source`, guessing the format from its name or its
+    /// first feature line when `format` is `None`. The `##FASTA` section is
+    /// only parsed when `with_sequences` is set.
+    pub fn read(
+        source: InputSource,
+        format: Option<AnnotationFormat>,
+        with_sequences: bool,
+        interrupt: &Interrupt,
+    ) -> PyResult<Self> {
+        let name = source.name();
+        let format = format.or_else(|| AnnotationFormat::from_path(&name));
+        let reader = BufReader::new(source.open()?);
+        Self::from_reader(reader, &name, format, with_sequences, interrupt)
+    }
+
+    /// Read features from `reader`, named `name` in errors, guessing the
+    /// format from its first feature line when `format` is `None`
+    pub fn from_reader(
+        mut reader: impl BufRead,
+        name: &str,
+        mut format: Option<AnnotationFormat>,
+        with_sequences: bool,
+        interrupt: &Interrupt,
+    ) -> PyResult<Self> {
+        let mut annotation = Annotation::default();
+        let mut line = String::new();
+        let mut line_number = 0;
+        let read_error =
+            |e: std::io::Error| PyIOError::new_err(format!("Error reading {}: {}", name, e));
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(read_error)? == 0 {
+                break;
+            }
+            line_number += 1;
+            let content = line.trim_end_matches(['\n', '\r']);
+            if content.starts_with("##FASTA") || content.starts_with('>') {
+                if with_sequences {
+                    let mut rest = if content.starts_with('>') {
+                        line.clone().into_bytes()
+                    } else {
+                        Vec::new()
+                    };
+                    reader.read_to_end(&mut rest).map_err(read_error)?;
+                    for record in RecordReader::from_reader(Cursor::new(rest), name)? {
+                        annotation.sequences.push(record?);
+                    }
+                }
+                break;
+            }
+            if content.trim().is_empty() || content.starts_with('#') {
+                continue;
+            }
+            if line_number % 4096 == 0 {
+                interrupt.check()?;
+            }
+            let format = *format.get_or_insert_with(|| {
+                AnnotationFormat::from_attributes(content.split('\t').nth(8).unwrap_or(""))
+            });
+            annotation
+                .features
+                .push(Feature::parse(content, format, line_number)?);
+        }
+        Ok(annotation)
+    }
+
+    /// Row indices of the parents and children of every feature
+    pub fn hierarchy(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        // Discontinuous GFF3 features share one ID across several lines
+        let mut by_id: HashMap<(IdSpace, &str), Vec<usize>> = HashMap::new();
+        for (i, feature) in self.features.iter().enumerate() {
+            if let Some(id) = &feature.id {
+                by_id
+                    .entry((feature.id_space, id.as_str()))
+                    .or_default()
+                    .push(i);
+            }
+        }
+        let mut parents = vec![Vec::new(); self.features.len()];
+        let mut children = vec![Vec::new(); self.features.len()];
+        for (i, feature) in self.features.iter().enumerate() {
+            for parent in &feature.parents {
+                let key = (feature.parent_space, parent.as_str());
+                for &j in by_id.get(&key).into_iter().flatten() {
+                    if j != i {
+                        parents[i].push(j);
+                        children[j].push(i);
+                    }
+                }
+            }
+        }
+        (parents, children)
+    }
+}
+
+fn features_to_columns<'py>(
+    py: Python<'py>,
+    annotation: &Annotation,
+) -> PyResult<Bound<'py, PyDict>> {
+    let features = &annotation.features;
+    let (parents, children) = annotation.hierarchy();
+    let columns = PyDict::new(py);
+    let attributes = PyList::empty(py);
+    for feature in features {
+        let map = PyDict::new(py);
+        for (key, values) in &feature.attributes {
+            map.set_item(key, values)?;
+        }
+        attributes.append(map)?;
+    }
+    columns.set_item(
+        "seqid",
+        features.iter().map(|f| &f.seqid).collect::<Vec<_>>(),
+    )?;
+    columns.set_item(
+        "source",
+        features.iter().map(|f| &f.source).collect::<Vec<_>>(),
+    )?;
+    columns.set_item(
+        "type",
+        features.iter().map(|f| &f.feature_type).collect::<Vec<_>>(),
+    )?;
+    columns.set_item(
+        "start",
+        features.iter().map(|f| f.start).collect::<Vec<_>>(),
+    )?;
+    columns.set_item("end", features.iter().map(|f| f.end).collect::<Vec<_>>())?;
+    columns.set_item(
+        "score",
+        features.iter().map(|f| f.score).collect::<Vec<_>>(),
+    )?;
+    columns.set_item(
+        "strand",
+        features.iter().map(|f| f.strand).collect::<Vec<_>>(),
+    )?;
+    columns.set_item(
+        "phase",
+        features.iter().map(|f| f.phase).collect::<Vec<_>>(),
+    )?;
+    columns.set_item("attributes", attributes)?;
+    columns.set_item("id", features.iter().map(|f| &f.id).collect::<Vec<_>>())?;
+    columns.set_item("parents", parents)?;
+    columns.set_item("children", children)?;
+    Ok(columns)
+}
+
+/// Read a GFF3 or GTF file into a dict of columns.
+///
+/// Parameters
+/// ----------
+/// path : str, pathlib.Path, bytes or binary file object
+///     The annotation file, optionally compressed. Use '-' for stdin.
+/// format : {'gff3', 'gtf'}, optional
+///     The file format. By default it is guessed from the file extension or
+///     the first feature line.
+///
+/// Returns
+/// -------
+/// dict
+///     Columns of equal length, ready for `polars.DataFrame`:
+///     `seqid`, `source`, `type` (str), `start`, `end` (int, 1-based and
+///     inclusive), `score` (float), `strand` (str), `phase` (int),
+///     `attributes` (dict of str to list of str), `id` (str) and the
+///     reconstructed hierarchy as row indices in `parents` and `children`.
+///     Missing values ('.') are `None`.
+///
+/// Notes
+/// -----
+/// GFF3 values are percent-decoded and multi-value attributes are split on
+/// commas. Repeated GTF attributes (e.g. `tag`) are collected as multiple
+/// values. In GTF files genes are identified by `gene_id`, transcripts by
+/// `transcript_id` (separate namespaces, so a transcript may reuse its gene's
+/// ID), and other features are children of their transcript.
+/// Reading stops at a `##FASTA` section; see `read_gff_sequences`.
+#[pyfunction]
+#[pyo3(signature = (path, format=None))]
+pub fn read_gff<'py>(
+    py: Python<'py>,
+    path: InputSource,
+    format: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let format = format.map(AnnotationFormat::from_name).transpose()?;
+    let annotation = pool::run(py, Some(1), |interrupt| {
+        Annotation::read(path, format, false, interrupt)
+    })?;
+    features_to_columns(py, &annotation)
+}
+
+/// Read the sequences embedded after the `##FASTA` directive of a GFF3 file.
+///
+/// Returns a dict mapping sequence names to sequences, in file order.
+#[pyfunction]
+pub fn read_gff_sequences<'py>(py: Python<'py>, path: InputSource) -> PyResult<Bound<'py, PyDict>> {
+    let annotation = pool::run(py, Some(1), |interrupt| {
+        Annotation::read(path, Some(AnnotationFormat::Gff3), true, interrupt)
+    })?;
+    let sequences = PyDict::new(py);
+    for record in &annotation.sequences {
+        sequences.set_item(
+            String::from_utf8_lossy(record.name()),
+            String::from_utf8_lossy(&record.seq),
+        )?;
+    }
+    Ok(sequences)
+}
+
+/// Extract the column `name` of a feature table as a list
+fn column<'py, T: FromPyObject<'py>>(
+    columns: &Bound<'py, PyDict>,
+    name: &str,
+    rows: usize,
+    required: bool,
+) -> PyResult<Vec<Option<T>>> {
+    match columns.get_item(name)? {
+        Some(values) => {
+            let values: Vec<Option<T>> = values.extract()?;
+            if values.len() != rows {
+                return Err(PyValueError::new_err(format!(
+                    "Column '{}' has {} values, expected {}",
+                    name,
+                    values.len(),
+                    rows
+                )));
+            }
+            Ok(values)
+        }
+        None if required => Err(PyValueError::new_err(format!("Missing column '{}'", name))),
+        None => Ok((0..rows).map(|_| None).collect()),
+    }
+}
+
+/// Extract attribute values given as a string or a list of strings
+fn attribute_values(value: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    if let Ok(value) = value.downcast::<PyString>() {
+        return Ok(vec![value.to_string()]);
+    }
+    if let Ok(values) = value.extract::<Vec<String>>() {
+        return Ok(values);
+    }
+    Ok(vec![value.str()?.to_string()])
+}
+
+/// Extract an attribute map given as a dict, or as a list of `(key, value)`
+/// pairs or `{"key": ..., "value": ...}` structs as produced by polars
+fn attribute_map(attributes: &Bound<'_, PyAny>) -> PyResult<Vec<(String, Vec<String>)>> {
+    let mut map = Vec::new();
+    if let Ok(dict) = attributes.downcast::<PyDict>() {
+        for (key, value) in dict.iter() {
+            if !value.is_none() {
+                map.push((key.str()?.to_string(), attribute_values(&value)?));
+            }
+        }
+        return Ok(map);
+    }
+    for item in attributes.try_iter()? {
+        let item = item?;
+        let (key, value) = match item.downcast::<PyDict>() {
+            Ok(entry) => match (entry.get_item("key")?, entry.get_item("value")?) {
+                (Some(key), Some(value)) => (key, value),
+                _ => {
+                    return Err(PyValueError::new_err(
+                        "Attribute entries must have 'key' and 'value' fields",
+                    ))
+                }
+            },
+            Err(_) => item.extract()?,
+        };
+        if !value.is_none() {
+            map.push((key.str()?.to_string(), attribute_values(&value)?));
+        }
+    }
+    Ok(map)
+}
+
+fn features_from_columns(columns: &Bound<'_, PyDict>) -> PyResult<Vec<Feature>> {
+    let rows = match columns.get_item("seqid")? {
+        Some(seqids) => seqids.len()?,
+        None => return Err(PyValueError::new_err("Missing column 'seqid'")),
+    };
+    let seqids = column::<String>(columns, "seqid", rows, true)?;
+    let sources = column::<String>(columns, "source", rows, false)?;
+    let types = column::<String>(columns, "type", rows, true)?;
+    let starts = column::<u64>(columns, "start", rows, true)?;
+    let ends = column::<u64>(columns, "end", rows, true)?;
+    let scores = column::<f64>(columns, "score", rows, false)?;
+    let strands = column::<String>(columns, "strand", rows, false)?;
+    let phases = column::<u8>(columns, "phase", rows, false)?;
+    let attributes = column::<Bound<PyAny>>(columns, "attributes", rows, false)?;
+
+    let mut features = Vec::with_capacity(rows);
+    for i in 0..rows {
+        let missing = |name: &str| {
+            PyValueError::new_err(format!("Column '{}' is missing a value in row {}", name, i))
+        };
+        let strand = match strands[i].as_deref() {
+            None | Some(".") => None,
+            Some(strand @ ("+" | "-" | "?")) => strand.chars().next(),
+            Some(strand) => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid strand '{}' in row {}",
+                    strand, i
+                )))
+            }
+        };
+        let feature_attributes = match &attributes[i] {
+            Some(map) => attribute_map(map)?,
+            None => Vec::new(),
+        };
+        features.push(Feature {
+            seqid: seqids[i].clone().ok_or_else(|| missing("seqid"))?,
+            source: sources[i].clone(),
+            feature_type: types[i].clone().ok_or_else(|| missing("type"))?,
+            start: starts[i].ok_or_else(|| missing("start"))?,
+            end: ends[i].ok_or_else(|| missing("end"))?,
+            score: scores[i],
+            strand,
+            phase: phases[i],
+            attributes: feature_attributes,
+            id: None,
+            parents: Vec::new(),
+            id_space: IdSpace::Feature,
+            parent_space: IdSpace::Feature,
+        });
+    }
+    Ok(features)
+}
+
+/// Write features to a GFF3 or GTF file.
+///
+/// Parameters
+/// ----------
+/// features : dict
+///     Columns as returned by `read_gff` (or a `polars.DataFrame.to_dict`
+///     with the same names). `seqid`, `type`, `start` and `end` are
+///     required; `id`, `parents` and `children` are ignored.
+/// output_file : str, pathlib.Path or binary file object
+///     Where the features are written. Use '-' for stdout.
+/// format : {'gff3', 'gtf'}, default: 'gff3'
+///     The output format.
+/// sequences : dict of str to str, optional
+///     Sequences written after a `##FASTA` directive (GFF3 only).
+/// compression : str, optional
+///     The output compression codec. Inferred from the extension by default.
+///
+/// Returns
+/// -------
+/// int
+///     The number of features written.
+#[pyfunction]
+#[pyo3(signature = (features, output_file, format="gff3", sequences=None, compression=None))]
+pub fn write_gff(
+    py: Python<'_>,
+    features: &Bound<'_, PyDict>,
+    output_file: OutputTarget,
+    format: &str,
+    sequences: Option<&Bound<'_, PyDict>>,
+    compression: Option<&str>,
+) -> PyResult<usize> {
+    let format = AnnotationFormat::from_name(format)?;
+    if sequences.is_some() && format == AnnotationFormat::Gtf {
+        return Err(PyValueError::new_err(
+            "Sequences can only be embedded in GFF3 files",
+        ));
+    }
+    let features = features_from_columns(features)?;
+    let sequences: Option<Vec<(String, String)>> = sequences
+        .map(|sequences| {
+            sequences
+                .iter()
+                .map(|(name, seq)| Ok((name.extract()?, seq.extract()?)))
+                .collect::<PyResult<Vec<_>>>()
+        })
+        .transpose()?;
+    let mut out = output_file.create(compression)?;
+
+    pool::run(py, Some(1), |interrupt| {
+        let write_error =
+            |e: std::io::Error| PyIOError::new_err(format!("Error writing annotation: {}", e));
+        if format == AnnotationFormat::Gff3 {
+            writeln!(out, "##gff-version 3").map_err(write_error)?;
+        }
+        for feature in &features {
+            interrupt.check()?;
+            writeln!(out, "{}", feature.to_line(format)).map_err(write_error)?;
+        }
+        match sequences {
+            Some(sequences) => {
+                writeln!(out, "##FASTA").map_err(write_error)?;
+                let mut writer = RecordWriter::new(out, DEFAULT_LINE_LENGTH);
+                for (name, seq) in sequences {
+                    writer.write_parts(name.as_bytes(), seq.as_bytes(), None)?;
+                }
+                writer.finish()?;
+            }
+            None => out.finish().map_err(write_error)?,
+        }
+        Ok(features.len())
+    })
+}
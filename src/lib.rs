@@ -3,22 +3,51 @@ use pyo3::Python;
 
 // Import our modules
 mod translate;
+mod genetic_code;
 mod dedup;
 mod filter;
 mod sample;
 mod seq_utils;
 mod fastx;
+mod fastx_io;
+mod compression;
+mod pool;
+mod pyfile;
+mod faidx;
+mod regions;
+mod gff;
+mod orf;
+mod codon_usage;
+mod back_translate;
+mod spill;
 
 // Expose the PyO3 modules
 #[pymodule]
 #[pyo3(name = "capibex")]
-fn capibex(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+fn capibex(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(translate::translate, m)?)?;
     m.add_function(wrap_pyfunction!(translate::translate_file, m)?)?;
+    m.add_function(wrap_pyfunction!(translate::translate_six_frames, m)?)?;
+    m.add_function(wrap_pyfunction!(translate::translate_codons, m)?)?;
+    m.add_function(wrap_pyfunction!(translate::translate_frameshifts, m)?)?;
+    m.add_class::<translate::TranslationReport>()?;
+    m.add_class::<translate::Frameshift>()?;
+    m.add_class::<genetic_code::GeneticCode>()?;
+    m.add_class::<orf::Orf>()?;
+    m.add_class::<codon_usage::CodonUsage>()?;
+    m.add_function(wrap_pyfunction!(codon_usage::codon_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(codon_usage::codon_usage_file, m)?)?;
+    m.add_function(wrap_pyfunction!(codon_usage::codon_usage_columns, m)?)?;
+    m.add_class::<back_translate::Primer>()?;
+    m.add_function(wrap_pyfunction!(back_translate::back_translate, m)?)?;
+    m.add_function(wrap_pyfunction!(back_translate::degenerate_primers, m)?)?;
+    m.add_function(wrap_pyfunction!(orf::find_orfs, m)?)?;
+    m.add_function(wrap_pyfunction!(orf::find_orfs_file, m)?)?;
 
 
     m.add_function(wrap_pyfunction!(dedup::deduplicate_by_seq, m)?)?;
     m.add_function(wrap_pyfunction!(dedup::deduplicate_by_id, m)?)?;
+    m.add_function(wrap_pyfunction!(dedup::deduplicate_by_containment, m)?)?;
     
     m.add_function(wrap_pyfunction!(filter::filter_by_header, m)?)?;
     m.add_function(wrap_pyfunction!(filter::filter_by_header_invert, m)?)?;
@@ -31,21 +60,29 @@ fn capibex(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(seq_utils::reverse_complement_seq, m)?)?;
     m.add_function(wrap_pyfunction!(seq_utils::count_nucleotides, m)?)?;
     m.add_function(wrap_pyfunction!(seq_utils::gc_content, m)?)?;
-    m.add_function(wrap_pyfunction!(translate::list_genetic_codes, m)?)?;
+    m.add_function(wrap_pyfunction!(genetic_code::list_genetic_codes, m)?)?;
 
     m.add_function(wrap_pyfunction!(seq_utils::is_amino_acid_string, m)?)?;
     m.add_function(wrap_pyfunction!(seq_utils::is_xna_string, m)?)?;
+    m.add_function(wrap_pyfunction!(seq_utils::is_dna_string, m)?)?;
     m.add_function(wrap_pyfunction!(seq_utils::is_ambiguous_string, m)?)?;
     m.add_function(wrap_pyfunction!(seq_utils::complement_base, m)?)?;
 
     m.add_class::<fastx::PyFastxReader>()?;
+    m.add_class::<fastx::PyFastxWriter>()?;
     m.add_class::<fastx::Record>()?;
-    m.add_wrapped(wrap_pyfunction!(fastx::py_parse_fastx_file))?;
-    m.add_wrapped(wrap_pyfunction!(fastx::parse_fastx_string))?;
-    m.add_wrapped(wrap_pyfunction!(fastx::normalize_seq))?;
-    m.add_wrapped(wrap_pyfunction!(fastx::reverse_complement))?;
-    m.add_wrapped(wrap_pyfunction!(fastx::py_decode_phred))?;
-    m.add("FastxParseError", py.get_type::<FastxParseError>())?;
+    m.add_function(wrap_pyfunction!(fastx::py_parse_fastx_file, m)?)?;
+    m.add_function(wrap_pyfunction!(fastx::parse_fastx_string, m)?)?;
+    m.add_function(wrap_pyfunction!(fastx::normalize_seq, m)?)?;
+    m.add_function(wrap_pyfunction!(fastx::reverse_complement, m)?)?;
+    m.add_function(wrap_pyfunction!(fastx::py_decode_phred, m)?)?;
+    m.add_class::<faidx::IndexedFasta>()?;
+    m.add_function(wrap_pyfunction!(faidx::index_fasta, m)?)?;
+    m.add_function(wrap_pyfunction!(regions::extract_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(gff::read_gff, m)?)?;
+    m.add_function(wrap_pyfunction!(gff::read_gff_sequences, m)?)?;
+    m.add_function(wrap_pyfunction!(gff::write_gff, m)?)?;
+    m.add("FastxParseError", py.get_type::<fastx_io::FastxParseError>())?;
 
     Ok(())
 }
\ No newline at end of file
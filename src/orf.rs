@@ -0,0 +1,657 @@
+//! Open reading frame calling
+//!
+//! ORFs are searched in all six frames using the stop and start codons of an
+//! NCBI genetic code. Coordinates are 0-based and half-open on the forward
+//! strand and include the stop codon.
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::io::Write;
+
+use crate::compression::OutputStream;
+use crate::fastx_io::{RecordReader, RecordWriter, DEFAULT_LINE_LENGTH};
+use crate::genetic_code::{codon_index, CodeSelection, GeneticCode};
+use crate::gff::{AnnotationFormat, Feature, IdSpace};
+use crate::pool;
+use crate::pyfile::{InputSource, OutputTarget};
+use crate::seq_utils::{is_ambiguous_string, reverse_complement};
+
+/// Number of records searched per parallel batch
+const BATCH_SIZE: usize = 256;
+
+/// Index of ATG in the genetic code tables
+const ATG: usize = 35;
+
+/// Codons allowed to initiate an ORF
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartCodons {
+    /// ATG only
+    Atg,
+    /// ATG and the alternative initiation codons of the genetic code
+    Alternative,
+}
+
+impl StartCodons {
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "atg" => Ok(StartCodons::Atg),
+            "alternative" => Ok(StartCodons::Alternative),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown start codons '{}'. Use 'atg' or 'alternative'.",
+                name
+            ))),
+        }
+    }
+}
+
+/// A genetic code with the start codons allowed to initiate ORFs
+pub struct CodonTable {
+    code: GeneticCode,
+    starts: [bool; 64],
+}
+
+impl CodonTable {
+    pub fn new(code: GeneticCode, start_codons: StartCodons) -> Self {
+        let mut table = CodonTable {
+            code,
+            starts: [false; 64],
+        };
+        match start_codons {
+            StartCodons::Atg => table.starts[ATG] = true,
+            StartCodons::Alternative => {
+                for (i, start) in table.starts.iter_mut().enumerate() {
+                    *start = table.code.is_start(i);
+                }
+            }
+        }
+        table
+    }
+
+    fn is_stop(&self, codon: &[u8]) -> bool {
+        self.amino_acid(codon) == b'*'
+    }
+
+    fn is_start(&self, codon: &[u8]) -> bool {
+        codon_index(codon).is_some_and(|i| self.starts[i])
+    }
+
+    /// Ambiguous codons resolve as in `translate`
+    fn amino_acid(&self, codon: &[u8]) -> u8 {
+        self.code.amino_acid(codon)
+    }
+}
+
+/// What counts as an ORF
+pub struct OrfOptions {
+    pub table: CodonTable,
+    /// Minimum length in nucleotides, stop codon included
+    pub min_length: usize,
+    /// Report the stretch between two stop codons instead of start to stop
+    pub stop_to_stop: bool,
+    /// Also report ORFs starting at downstream in-frame start codons
+    pub nested: bool,
+    /// Report ORFs running off either end of a linear sequence
+    pub partial: bool,
+    /// Let ORFs wrap around the end of the sequence
+    pub circular: bool,
+}
+
+/// An ORF within an oriented sequence
+struct Hit {
+    start: usize,
+    end: usize,
+    partial_start: bool,
+    partial_end: bool,
+    /// Whether the first codon is a start codon, translated as M
+    initiated: bool,
+}
+
+/// An open reading frame.
+///
+/// Attributes
+/// ----------
+/// start, end : int
+///     0-based, half-open coordinates on the forward strand, including the
+///     stop codon. ORFs crossing the origin of a circular sequence end past
+///     its length; `find_orfs_file` splits them into two BED rows.
+/// strand : str
+///     '+' or '-'.
+/// frame : int
+///     The reading frame, as in `translate` (1, 2, 3, -1, -2 or -3).
+/// partial_start, partial_end : bool
+///     Whether the ORF runs off the 5' or 3' end of the sequence.
+/// sequence : str
+///     The nucleotide sequence, reverse complemented on the minus strand.
+/// protein : str
+///     The translation, without the stop codon. Start codons translate to M.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Orf {
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    #[pyo3(get)]
+    pub strand: char,
+    #[pyo3(get)]
+    pub frame: i8,
+    #[pyo3(get)]
+    pub partial_start: bool,
+    #[pyo3(get)]
+    pub partial_end: bool,
+    #[pyo3(get)]
+    pub sequence: String,
+    #[pyo3(get)]
+    pub protein: String,
+}
+
+#[pymethods]
+impl Orf {
+    fn __len__(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Orf(start={}, end={}, strand='{}', frame={}, length={})",
+            self.start,
+            self.end,
+            self.strand,
+            self.frame,
+            self.protein.len()
+        )
+    }
+}
+
+impl Orf {
+    /// `5'`, `3'` or both, for ORFs running off the sequence
+    fn partial_ends(&self) -> Vec<String> {
+        let mut ends = Vec::new();
+        if self.partial_start {
+            ends.push("5'".to_string());
+        }
+        if self.partial_end {
+            ends.push("3'".to_string());
+        }
+        ends
+    }
+
+    /// FASTA description: `seqid:start-end(strand) frame=N length=N`
+    fn description(&self, seqid: &str) -> String {
+        let mut description = format!(
+            "{}:{}-{}({}) frame={} length={}",
+            seqid,
+            self.start + 1,
+            self.end,
+            self.strand,
+            self.frame,
+            self.protein.len()
+        );
+        let ends = self.partial_ends();
+        if !ends.is_empty() {
+            description.push_str(&format!(" partial={}", ends.join(",")));
+        }
+        description
+    }
+
+    fn to_feature(&self, seqid: &str, id: &str) -> Feature {
+        let mut attributes = vec![("ID".to_string(), vec![id.to_string()])];
+        let ends = self.partial_ends();
+        if !ends.is_empty() {
+            attributes.push(("partial".to_string(), ends));
+        }
+        Feature {
+            seqid: seqid.to_string(),
+            source: Some("capibex".to_string()),
+            feature_type: "ORF".to_string(),
+            start: self.start as u64 + 1,
+            end: self.end as u64,
+            score: None,
+            strand: Some(self.strand),
+            phase: None,
+            attributes,
+            id: Some(id.to_string()),
+            parents: Vec::new(),
+            id_space: IdSpace::Feature,
+            parent_space: IdSpace::Feature,
+        }
+    }
+}
+
+/// Record the ORFs of one stop-delimited stretch `region_start..end`
+///
+/// `bounded` is false for the stretch at the 5' end of the sequence, which
+/// has no upstream stop codon; `has_stop` is false for the one at the 3' end.
+#[allow(clippy::too_many_arguments)]
+fn region_hits(
+    hits: &mut Vec<Hit>,
+    options: &OrfOptions,
+    partial: bool,
+    region_start: usize,
+    end: usize,
+    bounded: bool,
+    has_stop: bool,
+    starts: &[usize],
+) {
+    if !has_stop && !partial {
+        return;
+    }
+    let stop_length = if has_stop { 3 } else { 0 };
+    let mut push = |start: usize, partial_start: bool, initiated: bool| {
+        let length = end - start;
+        if length > stop_length && length >= options.min_length {
+            hits.push(Hit {
+                start,
+                end,
+                partial_start,
+                partial_end: !has_stop,
+                initiated,
+            });
+        }
+    };
+
+    if options.stop_to_stop {
+        if bounded || partial {
+            push(region_start, !bounded, false);
+        }
+        return;
+    }
+    // Without a start codon at the sequence end the ORF is 5' partial
+    let truncated = !bounded && partial && starts.first() != Some(&region_start);
+    if truncated {
+        push(region_start, true, false);
+    }
+    for &start in starts {
+        if truncated && !options.nested {
+            break;
+        }
+        push(start, false, true);
+    }
+}
+
+/// Find ORFs in the three forward frames of an uppercase sequence
+fn scan(seq: &[u8], options: &OrfOptions, partial: bool) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for offset in 0..3 {
+        let mut region_start = offset;
+        let mut bounded = false;
+        let mut starts = Vec::new();
+        let mut pos = offset;
+        while pos + 3 <= seq.len() {
+            let codon = &seq[pos..pos + 3];
+            if options.table.is_stop(codon) {
+                region_hits(
+                    &mut hits,
+                    options,
+                    partial,
+                    region_start,
+                    pos + 3,
+                    bounded,
+                    true,
+                    &starts,
+                );
+                region_start = pos + 3;
+                bounded = true;
+                starts.clear();
+            } else if options.table.is_start(codon) && (options.nested || starts.is_empty()) {
+                starts.push(pos);
+            }
+            pos += 3;
+        }
+        if region_start < pos {
+            region_hits(
+                &mut hits,
+                options,
+                partial,
+                region_start,
+                pos,
+                bounded,
+                false,
+                &starts,
+            );
+        }
+    }
+    hits
+}
+
+/// Translate an ORF, leaving out the stop codon
+fn translate_hit(seq: &[u8], hit: &Hit, table: &CodonTable) -> String {
+    let coding_end = if hit.partial_end {
+        hit.end
+    } else {
+        hit.end - 3
+    };
+    let mut protein = table.code.translate_codons(&seq[hit.start..coding_end]);
+    if hit.initiated {
+        protein[0] = b'M';
+    }
+    String::from_utf8(protein).unwrap_or_default()
+}
+
+/// Find the ORFs on one strand of `seq`, oriented 5' to 3'
+fn strand_orfs(seq: &[u8], strand: char, options: &OrfOptions) -> Vec<Orf> {
+    let length = seq.len();
+    // Circular sequences are searched in three copies so that ORFs starting
+    // in the middle one see their full upstream and downstream context
+    let (search, shift) = if options.circular {
+        (seq.repeat(3), length)
+    } else {
+        (seq.to_vec(), 0)
+    };
+    scan(&search, options, options.partial && !options.circular)
+        .into_iter()
+        .filter(|hit| {
+            !options.circular
+                || (shift..2 * shift).contains(&hit.start) && hit.end - hit.start <= length
+        })
+        .map(|hit| {
+            let start = hit.start - shift;
+            let end = hit.end - shift;
+            let offset = (start % 3) as i8 + 1;
+            // Map minus strand coordinates back onto the forward strand,
+            // keeping ORFs across the origin past the end of the sequence
+            let (start, end, frame) = match strand {
+                '+' => (start, end, offset),
+                _ if end > length => (2 * length - end, 2 * length - start, -offset),
+                _ => (length - end, length - start, -offset),
+            };
+            Orf {
+                start,
+                end,
+                strand,
+                frame,
+                partial_start: hit.partial_start,
+                partial_end: hit.partial_end,
+                sequence: String::from_utf8_lossy(&search[hit.start..hit.end]).into_owned(),
+                protein: translate_hit(&search, &hit, &options.table),
+            }
+        })
+        .collect()
+}
+
+/// Find the ORFs on both strands of a sequence, sorted by position
+fn find_sequence_orfs(sequence: &str, options: &OrfOptions) -> PyResult<Vec<Orf>> {
+    let seq = sequence.to_uppercase().replace('U', "T");
+    if !is_ambiguous_string(&seq) {
+        return Err(PyValueError::new_err(
+            "Sequence contains invalid nucleotides. Only A, T, C, G, U, R, Y, S, W, K, M, B, D, H, V, N are allowed."
+        ));
+    }
+    let mut orfs = strand_orfs(seq.as_bytes(), '+', options);
+    orfs.extend(strand_orfs(
+        reverse_complement(&seq).as_bytes(),
+        '-',
+        options,
+    ));
+    orfs.sort_by_key(|orf| (orf.start, orf.end, orf.strand));
+    Ok(orfs)
+}
+
+/// Find open reading frames in both strands of a sequence.
+///
+/// Parameters
+/// ----------
+/// sequence : str
+///     A DNA or RNA sequence. Ambiguous codons are never start codons and
+///     translate as in `translate`; those resolving to a stop end ORFs.
+/// genetic_code : int, str or GeneticCode, default: 1
+///     The genetic code providing the start and stop codons.
+/// min_length : int, default: 75
+///     The minimum ORF length in nucleotides, stop codon included.
+/// starts : {'atg', 'alternative'}, default: 'atg'
+///     Start at ATG only, or also at the alternative initiation codons of
+///     the genetic code.
+/// stop_to_stop : bool, default: False
+///     Report every stretch between two in-frame stop codons instead of
+///     starting at a start codon.
+/// nested : bool, default: False
+///     Also report ORFs starting at downstream start codons of a longer ORF.
+/// partial : bool, default: False
+///     Report ORFs without a start or stop codon at the sequence ends.
+/// circular : bool, default: False
+///     Treat the sequence as circular, so ORFs may span the origin. There
+///     are no partial ORFs in circular sequences.
+///
+/// Returns
+/// -------
+/// list of Orf
+///     The ORFs ordered by start, end and strand.
+#[pyfunction]
+#[pyo3(signature = (sequence, genetic_code=CodeSelection::Id(1), min_length=75, starts="atg", stop_to_stop=false, nested=false, partial=false, circular=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn find_orfs(
+    sequence: &str,
+    genetic_code: CodeSelection,
+    min_length: usize,
+    starts: &str,
+    stop_to_stop: bool,
+    nested: bool,
+    partial: bool,
+    circular: bool,
+) -> PyResult<Vec<Orf>> {
+    let options = OrfOptions {
+        table: CodonTable::new(genetic_code.resolve()?, StartCodons::from_name(starts)?),
+        min_length,
+        stop_to_stop,
+        nested,
+        partial,
+        circular,
+    };
+    find_sequence_orfs(sequence, &options)
+}
+
+/// Output formats of `find_orfs_file`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OrfFormat {
+    Protein,
+    Nucleotide,
+    Bed,
+    Annotation(AnnotationFormat),
+}
+
+impl OrfFormat {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "protein" => Ok(OrfFormat::Protein),
+            "nucleotide" => Ok(OrfFormat::Nucleotide),
+            "bed" => Ok(OrfFormat::Bed),
+            "gff" | "gff3" => Ok(OrfFormat::Annotation(AnnotationFormat::Gff3)),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown ORF output format '{}'. Use 'protein', 'nucleotide', 'bed' or 'gff3'.",
+                name
+            ))),
+        }
+    }
+}
+
+/// Where `find_orfs_file` writes to
+enum OrfWriter {
+    Fasta(RecordWriter),
+    Table(OutputStream),
+}
+
+impl OrfWriter {
+    /// Write the ORFs of sequence `seqid`, named `seqid_ORF1`, `seqid_ORF2`...
+    ///
+    /// ORFs crossing the origin of a circular sequence of `length` bases are
+    /// split into two BED rows; in GFF3 they end past `length`, on a
+    /// sequence marked circular by a `region` feature.
+    fn write(
+        &mut self,
+        seqid: &str,
+        length: usize,
+        orfs: &[Orf],
+        format: OrfFormat,
+        circular: bool,
+    ) -> PyResult<()> {
+        let write_error =
+            |e: std::io::Error| PyIOError::new_err(format!("Error writing ORFs: {}", e));
+        if let (OrfWriter::Table(out), OrfFormat::Annotation(annotation), true) =
+            (&mut *self, format, circular)
+        {
+            let region = Feature {
+                seqid: seqid.to_string(),
+                source: Some("capibex".to_string()),
+                feature_type: "region".to_string(),
+                start: 1,
+                end: length as u64,
+                score: None,
+                strand: None,
+                phase: None,
+                attributes: vec![
+                    ("ID".to_string(), vec![seqid.to_string()]),
+                    ("Is_circular".to_string(), vec!["true".to_string()]),
+                ],
+                id: Some(seqid.to_string()),
+                parents: Vec::new(),
+                id_space: IdSpace::Feature,
+                parent_space: IdSpace::Feature,
+            };
+            writeln!(out, "##sequence-region {} 1 {}", seqid, length).map_err(write_error)?;
+            writeln!(out, "{}", region.to_line(annotation)).map_err(write_error)?;
+        }
+        for (i, orf) in orfs.iter().enumerate() {
+            let id = format!("{}_ORF{}", seqid, i + 1);
+            match (&mut *self, format) {
+                (OrfWriter::Fasta(writer), _) => {
+                    let header = format!("{} {}", id, orf.description(seqid));
+                    let seq = match format {
+                        OrfFormat::Protein => &orf.protein,
+                        _ => &orf.sequence,
+                    };
+                    writer.write_parts(header.as_bytes(), seq.as_bytes(), None)?;
+                }
+                (OrfWriter::Table(out), OrfFormat::Annotation(annotation)) => {
+                    let line = orf.to_feature(seqid, &id).to_line(annotation);
+                    writeln!(out, "{}", line).map_err(write_error)?;
+                }
+                (OrfWriter::Table(out), _) => {
+                    let blocks = if orf.end > length {
+                        vec![(orf.start, length), (0, orf.end - length)]
+                    } else {
+                        vec![(orf.start, orf.end)]
+                    };
+                    for (start, end) in blocks {
+                        writeln!(
+                            out,
+                            "{}\t{}\t{}\t{}\t0\t{}",
+                            seqid, start, end, id, orf.strand
+                        )
+                        .map_err(write_error)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> PyResult<()> {
+        match self {
+            OrfWriter::Fasta(writer) => writer.finish(),
+            OrfWriter::Table(out) => out
+                .finish()
+                .map_err(|e| PyIOError::new_err(format!("Error writing ORFs: {}", e))),
+        }
+    }
+}
+
+/// Find open reading frames in every sequence of a FASTA/FASTQ file.
+///
+/// ORFs are named `<sequence name>_ORF<n>`, numbered by position within each
+/// sequence.
+///
+/// Parameters
+/// ----------
+/// input_file : str, pathlib.Path or binary file object
+///     The sequences to search. Use '-' for stdin.
+/// output_file : str, pathlib.Path or binary file object
+///     Where the ORFs are written. Use '-' for stdout.
+/// output_format : {'protein', 'nucleotide', 'bed', 'gff3'}, default: 'protein'
+///     Protein or nucleotide FASTA, with coordinates in the description,
+///     BED6 or GFF3 `ORF` features. With `circular`, BED rows of ORFs
+///     crossing the origin are split in two and GFF3 sequences get a
+///     `region` feature with `Is_circular=true`.
+/// genetic_code, min_length, starts, stop_to_stop, nested, partial, circular
+///     As in `find_orfs`.
+/// threads : int, optional
+///     The number of worker threads. Output order follows the input.
+/// line_length : int, default: 60
+///     FASTA line width; 0 writes each sequence on one line.
+/// compression : str, optional
+///     The output compression codec. Inferred from the extension by default.
+///
+/// Returns
+/// -------
+/// int
+///     The number of ORFs written.
+#[pyfunction]
+#[pyo3(signature = (input_file, output_file, output_format="protein", genetic_code=CodeSelection::Id(1), min_length=75, starts="atg", stop_to_stop=false, nested=false, partial=false, circular=false, threads=None, line_length=DEFAULT_LINE_LENGTH, compression=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn find_orfs_file(
+    py: Python<'_>,
+    input_file: InputSource,
+    output_file: OutputTarget,
+    output_format: &str,
+    genetic_code: CodeSelection,
+    min_length: usize,
+    starts: &str,
+    stop_to_stop: bool,
+    nested: bool,
+    partial: bool,
+    circular: bool,
+    threads: Option<usize>,
+    line_length: usize,
+    compression: Option<&str>,
+) -> PyResult<usize> {
+    let format = OrfFormat::from_name(output_format)?;
+    let options = OrfOptions {
+        table: CodonTable::new(genetic_code.resolve()?, StartCodons::from_name(starts)?),
+        min_length,
+        stop_to_stop,
+        nested,
+        partial,
+        circular,
+    };
+    let mut writer = match format {
+        OrfFormat::Protein | OrfFormat::Nucleotide => {
+            OrfWriter::Fasta(RecordWriter::create(output_file, line_length, compression)?)
+        }
+        _ => OrfWriter::Table(output_file.create(compression)?),
+    };
+    if let OrfWriter::Table(out) = &mut writer {
+        if format == OrfFormat::Annotation(AnnotationFormat::Gff3) {
+            writeln!(out, "##gff-version 3")
+                .map_err(|e| PyIOError::new_err(format!("Error writing ORFs: {}", e)))?;
+        }
+    }
+
+    // Sequences are searched in parallel batches and written in input order
+    let count = pool::run(py, threads, |interrupt| {
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut reader = RecordReader::open(input_file)?.peekable();
+        while let Some(record) = reader.next() {
+            interrupt.check()?;
+            batch.push(record?);
+            if batch.len() == BATCH_SIZE || reader.peek().is_none() {
+                let found: Vec<Vec<Orf>> = batch
+                    .par_iter()
+                    .map(|record| {
+                        find_sequence_orfs(&String::from_utf8_lossy(&record.seq), &options)
+                    })
+                    .collect::<PyResult<_>>()?;
+                for (record, orfs) in batch.iter().zip(found) {
+                    let seqid = String::from_utf8_lossy(record.name()).into_owned();
+                    writer.write(&seqid, record.seq.len(), &orfs, format, circular)?;
+                    count += orfs.len();
+                }
+                batch.clear();
+            }
+        }
+        Ok(count)
+    })?;
+
+    writer.finish()?;
+    Ok(count)
+}
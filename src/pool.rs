@@ -0,0 +1,82 @@
+//! Per-call rayon thread pools with the GIL released
+//!
+//! Every parallel function builds its own pool instead of configuring the
+//! global one, so `threads=` is honoured on each call and repeated calls from
+//! the same Python process keep working. The work runs without the GIL while
+//! the calling thread watches for Ctrl-C.
+
+use pyo3::exceptions::{PyKeyboardInterrupt, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// How often the calling thread checks for pending signals
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Cancellation flag that long-running loops check between records
+#[derive(Default)]
+pub struct Interrupt(AtomicBool);
+
+impl Interrupt {
+    /// Fail with `KeyboardInterrupt` once the call has been interrupted
+    pub fn check(&self) -> PyResult<()> {
+        if self.0.load(Ordering::Relaxed) {
+            Err(PyKeyboardInterrupt::new_err("Interrupted"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Run `op` inside a dedicated pool with `threads` workers (all cores if
+/// `None`), releasing the GIL until it returns
+///
+/// Signal handlers only run on the main thread, so the calling thread polls
+/// them while waiting and flags the workers to stop when one raises.
+pub fn run<OP, R>(py: Python<'_>, threads: Option<usize>, op: OP) -> PyResult<R>
+where
+    OP: FnOnce(&Interrupt) -> PyResult<R> + Send,
+    R: Send,
+{
+    if threads == Some(0) {
+        return Err(PyValueError::new_err("threads must be at least 1"));
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .map_err(|e| PyValueError::new_err(format!("Failed to set up thread pool: {}", e)))?;
+    let interrupt = &Interrupt::default();
+
+    py.allow_threads(|| {
+        std::thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let worker = scope.spawn(move || {
+                let result = pool.install(|| op(interrupt));
+                let _ = sender.send(result);
+            });
+            loop {
+                match receiver.recv_timeout(SIGNAL_POLL_INTERVAL) {
+                    Ok(result) => return result,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Err(e) = Python::with_gil(|py| py.check_signals()) {
+                            // Let the workers wind down before reporting the signal
+                            interrupt.set();
+                            let _ = receiver.recv();
+                            return Err(e);
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        // Joining consumes the panic so the scope does not re-raise it
+                        let _ = worker.join();
+                        return Err(PyRuntimeError::new_err("Worker thread panicked"));
+                    }
+                }
+            }
+        })
+    })
+}
@@ -0,0 +1,244 @@
+//! File arguments accepted by the Python API
+//!
+//! Inputs can be paths, `"-"` for stdin, `bytes` holding the file content or
+//! binary file-like objects (`io.BytesIO`, `gzip.open` handles, S3 streams).
+//! Outputs can be paths, `"-"` for stdout or writable binary file objects.
+
+use pyo3::exceptions::{PyIOError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyString};
+use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::path::PathBuf;
+
+use crate::compression::{wrap_input, Compression, OutputStream};
+
+/// Where input data is read from
+pub enum InputSource {
+    Path(String),
+    Stdin,
+    Bytes(Vec<u8>),
+    PyFile { file: Py<PyAny>, name: String },
+}
+
+impl<'py> FromPyObject<'py> for InputSource {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(path) = ob.downcast::<PyString>() {
+            let path = path.to_str()?;
+            return Ok(if path == "-" {
+                InputSource::Stdin
+            } else {
+                InputSource::Path(path.to_string())
+            });
+        }
+        if let Ok(bytes) = ob.downcast::<PyBytes>() {
+            return Ok(InputSource::Bytes(bytes.as_bytes().to_vec()));
+        }
+        if let Ok(bytes) = ob.downcast::<PyByteArray>() {
+            return Ok(InputSource::Bytes(bytes.to_vec()));
+        }
+        if ob.hasattr("read")? {
+            return Ok(InputSource::PyFile {
+                file: ob.clone().unbind(),
+                name: file_object_name(ob),
+            });
+        }
+        if let Ok(path) = ob.extract::<PathBuf>() {
+            return Ok(InputSource::Path(path.to_string_lossy().into_owned()));
+        }
+        Err(PyTypeError::new_err(
+            "Expected a path, '-', bytes or a binary file-like object with a read() method",
+        ))
+    }
+}
+
+impl InputSource {
+    /// A short description used in error messages
+    pub fn name(&self) -> String {
+        match self {
+            InputSource::Path(path) => path.clone(),
+            InputSource::Stdin => "<stdin>".to_string(),
+            InputSource::Bytes(_) => "<bytes>".to_string(),
+            InputSource::PyFile { name, .. } => name.clone(),
+        }
+    }
+
+    /// Open the source for reading, decompressing it if needed
+    pub fn open(self) -> PyResult<Box<dyn Read + Send>> {
+        let name = self.name();
+        let reader = match self {
+            InputSource::Path(path) => {
+                let file = File::open(&path).map_err(|e| {
+                    PyIOError::new_err(format!("Failed to open input file {}: {}", path, e))
+                })?;
+                wrap_input(file)
+            }
+            InputSource::Stdin => wrap_input(io::stdin()),
+            InputSource::Bytes(bytes) => wrap_input(Cursor::new(bytes)),
+            InputSource::PyFile { file, .. } => wrap_input(PyFileReader::new(file)),
+        };
+        reader.map_err(|e| PyIOError::new_err(format!("Failed to open input {}: {}", name, e)))
+    }
+}
+
+/// Where output data is written to
+pub enum OutputTarget {
+    Path(String),
+    Stdout,
+    PyFile { file: Py<PyAny>, name: String },
+}
+
+impl<'py> FromPyObject<'py> for OutputTarget {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(path) = ob.downcast::<PyString>() {
+            let path = path.to_str()?;
+            return Ok(if path == "-" {
+                OutputTarget::Stdout
+            } else {
+                OutputTarget::Path(path.to_string())
+            });
+        }
+        if ob.hasattr("write")? {
+            return Ok(OutputTarget::PyFile {
+                file: ob.clone().unbind(),
+                name: file_object_name(ob),
+            });
+        }
+        if let Ok(path) = ob.extract::<PathBuf>() {
+            return Ok(OutputTarget::Path(path.to_string_lossy().into_owned()));
+        }
+        Err(PyTypeError::new_err(
+            "Expected a path, '-' or a binary file-like object with a write() method",
+        ))
+    }
+}
+
+impl OutputTarget {
+    /// A short description used in error messages
+    pub fn name(&self) -> String {
+        match self {
+            OutputTarget::Path(path) => path.clone(),
+            OutputTarget::Stdout => "<stdout>".to_string(),
+            OutputTarget::PyFile { name, .. } => name.clone(),
+        }
+    }
+
+    /// Open the target for writing with `compression`, or the codec implied by
+    /// the file extension when it is `None`
+    pub fn create(self, compression: Option<&str>) -> PyResult<OutputStream> {
+        let name = self.name();
+        let compression = match (&self, compression) {
+            (OutputTarget::Path(path), None) => Compression::from_path(path),
+            (_, Some(codec)) => Compression::from_name(codec)?,
+            (_, None) => Compression::None,
+        };
+        let inner: Box<dyn Write + Send> = match self {
+            OutputTarget::Path(path) => {
+                let file = File::create(&path).map_err(|e| {
+                    PyIOError::new_err(format!("Failed to create output file {}: {}", path, e))
+                })?;
+                Box::new(BufWriter::new(file))
+            }
+            OutputTarget::Stdout => Box::new(BufWriter::new(io::stdout())),
+            OutputTarget::PyFile { file, .. } => Box::new(BufWriter::new(PyFileWriter { file })),
+        };
+        OutputStream::new(inner, compression)
+            .map_err(|e| PyIOError::new_err(format!("Failed to create output {}: {}", name, e)))
+    }
+}
+
+fn file_object_name(ob: &Bound<'_, PyAny>) -> String {
+    ob.getattr("name")
+        .and_then(|name| name.str())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| "<file object>".to_string())
+}
+
+fn py_io_error(e: PyErr) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+/// Adapts a Python binary file object to `Read`, taking the GIL per call
+pub struct PyFileReader {
+    file: Py<PyAny>,
+    pending: Vec<u8>,
+    offset: usize,
+}
+
+impl PyFileReader {
+    pub fn new(file: Py<PyAny>) -> Self {
+        Self {
+            file,
+            pending: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Copy the start of a chunk read from Python into `buf`, keeping the
+    /// rest for later reads
+    fn take(&mut self, chunk: &[u8], buf: &mut [u8]) -> usize {
+        let n = buf.len().min(chunk.len());
+        buf[..n].copy_from_slice(&chunk[..n]);
+        self.pending.clear();
+        self.pending.extend_from_slice(&chunk[n..]);
+        self.offset = 0;
+        n
+    }
+}
+
+impl Read for PyFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.pending.len() {
+            return Python::with_gil(|py| -> PyResult<usize> {
+                let data = self
+                    .file
+                    .bind(py)
+                    .call_method1("read", (buf.len().max(8192),))?;
+                if let Ok(bytes) = data.downcast::<PyBytes>() {
+                    Ok(self.take(bytes.as_bytes(), buf))
+                } else if let Ok(text) = data.downcast::<PyString>() {
+                    // Tolerate text-mode handles
+                    Ok(self.take(text.to_str()?.as_bytes(), buf))
+                } else {
+                    Ok(self.take(&data.extract::<Vec<u8>>()?, buf))
+                }
+            })
+            .map_err(py_io_error);
+        }
+        let n = buf.len().min(self.pending.len() - self.offset);
+        buf[..n].copy_from_slice(&self.pending[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// Adapts a Python binary file object to `Write`, taking the GIL per call
+pub struct PyFileWriter {
+    file: Py<PyAny>,
+}
+
+impl Write for PyFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Report short writes; objects returning `None` wrote everything
+        Python::with_gil(|py| -> PyResult<usize> {
+            let written = self
+                .file
+                .bind(py)
+                .call_method1("write", (PyBytes::new(py, buf),))?
+                .extract::<Option<usize>>()?;
+            Ok(written.map_or(buf.len(), |n| n.min(buf.len())))
+        })
+        .map_err(py_io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Python::with_gil(|py| -> PyResult<()> {
+            let file = self.file.bind(py);
+            if file.hasattr("flush")? {
+                file.call_method0("flush")?;
+            }
+            Ok(())
+        })
+        .map_err(py_io_error)
+    }
+}
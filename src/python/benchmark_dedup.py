@@ -0,0 +1,71 @@
+"""Scaling benchmark for deduplicate_by_containment.
+
+Writes random contigs, a share of them substrings of others, to temporary
+FASTA files of growing size and times containment deduplication on each. A
+second set gives every contig the same low-complexity prefix, which must not
+make the seed index quadratic. Fails when the time per contig grows more
+than `--max-growth` times from the smallest to the largest input.
+
+    python benchmark_dedup.py --contigs 5000 10000 20000 40000 --threads 4
+"""
+
+import argparse
+import os
+import random
+import tempfile
+import time
+
+from capibex import deduplicate_by_containment
+
+
+def write_contigs(path, contigs, length, prefix, seed):
+    rng = random.Random(seed)
+    seqs = []
+    with open(path, "w") as f:
+        for i in range(contigs):
+            if seqs and rng.random() < 0.2:
+                # A substring of an earlier contig
+                parent = rng.choice(seqs)
+                start = rng.randrange(len(parent) // 2)
+                seq = parent[start:start + len(parent) // 2]
+            else:
+                size = rng.randint(length // 2, length * 3 // 2)
+                seq = prefix + "".join(rng.choices("ACGT", k=size))
+                seqs.append(seq)
+            f.write(f">contig_{i}\n{seq}\n")
+
+
+def run(fasta, threads, k):
+    start = time.perf_counter()
+    count = deduplicate_by_containment([fasta], os.devnull, threads=threads, k=k)
+    return count, time.perf_counter() - start
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--contigs", type=int, nargs="+", default=[5000, 10000, 20000, 40000])
+    parser.add_argument("--length", type=int, default=1000, help="mean contig length")
+    parser.add_argument("--threads", type=int, default=os.cpu_count() or 1)
+    parser.add_argument("-k", type=int, default=31)
+    parser.add_argument("--max-growth", type=float, default=3.0)
+    parser.add_argument("--seed", type=int, default=42)
+    args = parser.parse_args()
+
+    with tempfile.TemporaryDirectory() as tmp:
+        for label, prefix in [("random", ""), ("40xA prefix", "A" * 40)]:
+            per_contig = []
+            for contigs in args.contigs:
+                fasta = os.path.join(tmp, f"contigs_{contigs}.fasta")
+                write_contigs(fasta, contigs, args.length, prefix, args.seed)
+                count, elapsed = run(fasta, args.threads, args.k)
+                per_contig.append(elapsed / contigs)
+                print(f"{label:<12} {contigs:>8} contigs: {count} kept in {elapsed:.2f} s")
+            growth = per_contig[-1] / per_contig[0]
+            print(f"{label:<12} time per contig grew {growth:.1f}x")
+            if growth > args.max_growth:
+                raise SystemExit(f"{label}: time per contig grew {growth:.1f}x, more than {args.max_growth}x")
+        print("Containment deduplication scales linearly")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,107 @@
+"""Throughput benchmark for translate_file.
+
+Writes random contigs, with a sprinkling of IUPAC codes, to a temporary FASTA
+file, translates them in all six frames with every thread count given, checks
+that the outputs are identical and reports the throughput in megabases per
+second.
+
+With --baseline, the same file is also translated by a capibex build from
+before the codon lookup table (translating codon by codon), whose output must
+be identical, and the speedup over it is reported. Build one from the commit
+before "Translate through a precomputed codon lookup" with:
+
+    BASE=$(git log -1 --format=%H --grep="Translate through a precomputed codon lookup")^
+    git worktree add ../capibex-baseline "$BASE"
+    cd ../capibex-baseline && maturin build --release --out dist
+    pip install --target DIR --no-deps dist/capibex-*.whl
+
+    python benchmark_translate.py --contigs 20000 --length 5000 --threads 1 4 8 --baseline DIR
+"""
+
+import argparse
+import filecmp
+import os
+import random
+import subprocess
+import sys
+import tempfile
+import time
+
+from capibex import translate_file
+
+
+def write_contigs(path, contigs, length, seed):
+    rng = random.Random(seed)
+    with open(path, "w") as f:
+        for i in range(contigs):
+            # Contig lengths vary so that batches are unevenly sized
+            size = rng.randint(length // 2, length * 3 // 2)
+            seq = "".join(rng.choices("ACGTACGTACGTACGTNRY", k=size))
+            f.write(f">contig_{i}\n{seq}\n")
+
+
+def run(fasta, output, threads, frames, genetic_code):
+    start = time.perf_counter()
+    count = translate_file(
+        fasta, frames=frames, genetic_code=genetic_code, output_file=output, threads=threads
+    )
+    return count, time.perf_counter() - start
+
+
+def run_baseline(fasta, output, frames, genetic_code, baseline):
+    """Time translate_file of the capibex build in `baseline`, in a separate
+    interpreter so that both builds can be imported"""
+    script = (
+        "import sys, time\n"
+        f"sys.path.insert(0, {baseline!r})\n"
+        "from capibex import translate_file\n"
+        "start = time.perf_counter()\n"
+        f"translate_file({fasta!r}, frames={frames!r}, genetic_code={genetic_code}, output_file={output!r})\n"
+        "print(time.perf_counter() - start)\n"
+    )
+    result = subprocess.run([sys.executable, "-c", script], capture_output=True, text=True, check=True)
+    return float(result.stdout.split()[-1])
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--contigs", type=int, default=20000)
+    parser.add_argument("--length", type=int, default=5000, help="mean contig length")
+    parser.add_argument("--threads", type=int, nargs="+", default=[1, os.cpu_count() or 1])
+    parser.add_argument("--genetic-code", type=int, default=11)
+    parser.add_argument("--seed", type=int, default=42)
+    parser.add_argument("--baseline", help="directory with a capibex build from before the codon lookup table")
+    args = parser.parse_args()
+
+    with tempfile.TemporaryDirectory() as tmp:
+        fasta = os.path.join(tmp, "contigs.fasta")
+        write_contigs(fasta, args.contigs, args.length, args.seed)
+        bases = os.path.getsize(fasta)
+        print(f"{args.contigs} contigs, {bases / 1e6:.1f} Mb, six frames")
+
+        reference = None
+        baseline_time = None
+        if args.baseline:
+            reference = os.path.join(tmp, "proteins_baseline.faa")
+            baseline_time = run_baseline(fasta, reference, "all", args.genetic_code, args.baseline)
+            print(f"baseline    codon by codon in {baseline_time:.2f} s ({bases / baseline_time / 1e6:.1f} Mb/s)")
+        for threads in args.threads:
+            output = os.path.join(tmp, f"proteins_{threads}.faa")
+            count, elapsed = run(fasta, output, threads, "all", args.genetic_code)
+            if reference is None:
+                reference = output
+            elif not filecmp.cmp(reference, output, shallow=False):
+                raise SystemExit(f"Output with {threads} threads differs from the reference run")
+            speedup = f", {baseline_time / elapsed:.1f}x the baseline" if baseline_time else ""
+            print(
+                f"threads={threads:<3} {count} translations in {elapsed:.2f} s "
+                f"({bases / elapsed / 1e6:.1f} Mb/s{speedup})"
+            )
+        if baseline_time:
+            print("Outputs identical to the baseline and across thread counts")
+        else:
+            print("Outputs identical across thread counts")
+
+
+if __name__ == "__main__":
+    main()
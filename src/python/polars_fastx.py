@@ -6,6 +6,7 @@ from collections import defaultdict
 import polars as pl
 from polars.io.plugins import register_io_source
 from needletail import parse_fastx_file
+from capibex import read_gff, write_gff, codon_usage_columns, GeneticCode
 
 
 #TODO: drop all map_elements and use polars native fucntions.
@@ -29,18 +30,48 @@ class SequenceExpr:
         """Get sequence length"""
         return self._expr.str.len_chars()
 
-    def codon_usage(self) -> pl.Expr:
-        """Calculate codon usage frequencies"""
-        def _calc_codons(seq: str) -> dict:
-            codons = defaultdict(int)
-            for i in range(0, len(seq) - 2, 3):
-                codon = seq[i : i + 3].upper()
-                if "N" not in codon:
-                    codons[codon] += 1
-            total = sum(codons.values())
-            return {k: v / total for k, v in codons.items()} if total > 0 else {}
-
-        return self._expr.map_elements(_calc_codons, return_dtype=pl.Struct)
+    def _codon_statistic(self, field: str, dtype: pl.DataType, genetic_code=1, reference=None) -> pl.Expr:
+        """One column of `codon_usage_columns`, computed in Rust a batch at a time"""
+        def _compute(batch: pl.Series) -> pl.Series:
+            columns = codon_usage_columns(batch.to_list(), genetic_code, reference)
+            return pl.Series(batch.name, columns[field], dtype=dtype)
+
+        return self._expr.map_batches(_compute, return_dtype=dtype)
+
+    def codon_usage(self, genetic_code=1) -> pl.Expr:
+        """Calculate codon usage frequencies, as a struct with one field per codon"""
+        codons = list(GeneticCode(genetic_code).codons)
+
+        def _frequencies(batch: pl.Series) -> pl.Series:
+            counts = pl.Series(
+                batch.name,
+                codon_usage_columns(batch.to_list(), genetic_code)["counts"],
+                dtype=pl.Array(pl.UInt64, 64),
+            )
+            totals = counts.arr.sum()
+            frame = pl.DataFrame(
+                {codon: counts.arr.get(i) / totals for i, codon in enumerate(codons)}
+            )
+            return frame.to_struct(batch.name)
+
+        dtype = pl.Struct({codon: pl.Float64 for codon in codons})
+        return self._expr.map_batches(_frequencies, return_dtype=dtype)
+
+    def rscu(self, genetic_code=1) -> pl.Expr:
+        """Relative synonymous codon usage of the 64 codons, in NCBI order"""
+        return self._codon_statistic("rscu", pl.Array(pl.Float64, 64), genetic_code)
+
+    def enc(self, genetic_code=1) -> pl.Expr:
+        """Effective number of codons"""
+        return self._codon_statistic("enc", pl.Float64, genetic_code)
+
+    def gc3(self, genetic_code=1) -> pl.Expr:
+        """GC content of the third position of sense codons"""
+        return self._codon_statistic("gc3", pl.Float64, genetic_code)
+
+    def cai(self, reference, genetic_code=1) -> pl.Expr:
+        """Codon adaptation index against a reference CodonUsage or codon counts"""
+        return self._codon_statistic("cai", pl.Float64, genetic_code, reference)
 
     def generate_hash(self, length: int = 32) -> pl.Expr:
         """Generate a hash for a sequence"""
@@ -137,45 +168,65 @@ def init(file: Union[str, Path], batch_size: int = 512) -> pl.DataFrame:
 
 
 
+GFF_SCHEMA = pl.Schema([('seqid', pl.String),
+    ('source', pl.String),
+    ('type', pl.String),
+    ('start', pl.UInt64),
+    ('end', pl.UInt64),
+    ('score', pl.Float64),
+    ('strand', pl.String),
+    ('phase', pl.UInt8),
+    ('attributes', pl.List(pl.Struct({'key': pl.String, 'value': pl.List(pl.String)}))),
+    ('id', pl.String),
+    ('parents', pl.List(pl.UInt32)),
+    ('children', pl.List(pl.UInt32))])
+
+
+def _gff_frame(columns: dict) -> pl.DataFrame:
+    """Build a DataFrame from the columns returned by `capibex.read_gff`"""
+    columns = dict(columns)
+    columns["attributes"] = [
+        [{"key": key, "value": values} for key, values in attrs.items()]
+        for attrs in columns["attributes"]
+    ]
+    return pl.DataFrame(columns, schema=GFF_SCHEMA)
+
+
 @pl.api.register_lazyframe_namespace("from_gff")
-def init(input_file: Union[str, Path]) -> pl.LazyFrame:
-    """Scan a gff(3) file into a lazy polars DataFrame.
+def init(input_file: Union[str, Path], format: Optional[str] = None) -> pl.LazyFrame:
+    """Scan a GFF3/GTF file into a lazy polars DataFrame.
 
     Args:
-        path (Union[str, Path]): Path to the FASTA/FASTQ file
+        input_file (Union[str, Path]): Path to the GFF3/GTF file, optionally compressed
+        format (str, optional): "gff3" or "gtf". Guessed from the file by default.
 
     Returns:
-        pl.LazyFrame: Lazy DataFrame with columns as gff3 specs.
+        pl.LazyFrame: Lazy DataFrame with the GFF columns, `attributes` as a list of
+        key/value structs, the feature `id`, and `parents`/`children` row indices.
     """
-
-    schema = pl.Schema([('seqid', pl.String),
-        ('source', pl.String),
-        ('type', pl.String),
-        ('start', pl.UInt32),
-        ('end', pl.UInt32),
-        ('score', pl.Float32),
-        ('strand', pl.String),
-        ('phase', pl.UInt32),
-        ('attributes', pl.String)])
-    
-    spattern = r'(?P<key>\w+)=(?P<value>[^;]+)'
-    reader = pl.scan_csv(input_file, has_header=False, separator="\t", comment_prefix="#", schema=schema,null_values=["."])
-    reader = reader.with_columns(pl.col("attributes").str.extract_all(spattern))
-    return reader
+    return _gff_frame(read_gff(input_file, format=format)).lazy()
 
 @pl.api.register_dataframe_namespace("from_gff")
-def init(gff_file: Union[str, Path],unnest_attributes: bool = False) -> pl.DataFrame:
-    lf = pl.LazyFrame.from_gff(gff_file)
-    df = lf.collect()
+def init(gff_file: Union[str, Path], unnest_attributes: bool = False, format: Optional[str] = None) -> pl.DataFrame:
+    columns = read_gff(gff_file, format=format)
+    df = _gff_frame(columns)
     if unnest_attributes:
+        # One list column per attribute key, in order of first appearance
+        attributes = columns["attributes"]
+        keys = list(dict.fromkeys(key for attrs in attributes for key in attrs))
         df = df.with_columns(
-        pl.col("attributes").list.eval(pl.element().str.split("=").list.to_struct()
-                                       )
-        # .list.to_struct()
+            pl.Series(key, [attrs.get(key) for attrs in attributes], dtype=pl.List(pl.String))
+            for key in keys
+            if key not in df.columns
         )
     return df
 
 
+def write_gff_frame(df: pl.DataFrame, output_file: Union[str, Path], format: str = "gff3") -> int:
+    """Write a DataFrame from `pl.DataFrame.from_gff` back to a GFF3/GTF file"""
+    return write_gff(df.to_dict(as_series=False), output_file, format=format)
+
+
 ### example usage
 def fasta_stats(
     input_file: str,
@@ -1,3 +1,9 @@
+import gzip
+import io
+import math
+import os
+import random
+import tempfile
 from capibex import *
 # (
 #     translate,
@@ -28,6 +34,198 @@ def test_translate():
     # assert "frame_-1" in result  # Verify reverse frames exist
     print("✓ translate test passed")
 
+def test_ambiguous_translation():
+    """Test IUPAC codons, alignment gaps and lenient translation"""
+    assert translate("GCNYTRTAR") == "AL*"
+    assert translate("GCNRTGNNN") == "AXX"
+    try:
+        translate("ATG---AAA")
+        assert False, "gaps should be rejected by default"
+    except ValueError:
+        pass
+    assert translate("ATG---AAA", gaps="keep") == "M-K"
+    assert translate("ATG-A-AAA", gaps="keep") == "MXK"
+    assert translate("ATG---AAA", gaps="remove") == "MK"
+    assert translate("TTT---CAT", frame=-1, gaps="keep") == "M-K"
+    assert translate("atgXXXaaa", lenient=True) == "MXK"
+    print("✓ ambiguous translation test passed")
+
+def test_genetic_code():
+    """Test genetic code lookup, reassignment and readthrough"""
+    code = GeneticCode(11)
+    assert code.id == 11 and code == GeneticCode("Bacterial")
+    assert code.stops == ["TAA", "TAG", "TGA"]
+    assert "GTG" in code.starts and code.codons["ATG"] == "M"
+    assert GeneticCode("vertebrate mitochondrial").id == 2
+    for unknown in (7, "no such code"):
+        try:
+            GeneticCode(unknown)
+            assert False, "unknown codes should raise"
+        except ValueError:
+            pass
+
+    pyl = GeneticCode().reassign({"UGA": "W", "UAG": "O"})
+    assert pyl.id is None and pyl.stops == ["TAA"]
+    assert translate("ATGTGATAGTAA", genetic_code=pyl) == "MWO*"
+    assert translate("ATGTAAAAA", genetic_code=GeneticCode().readthrough()) == "MXK"
+
+    custom = GeneticCode.custom(GeneticCode(4).amino_acids, starts=["TTG"], name="mine")
+    assert custom.starts == ["TTG"] and custom.name == "mine"
+    assert [orf.protein for orf in find_orfs("TTGAAATAA", min_length=0, starts="alternative", genetic_code=custom)] == ["MK"]
+    print("✓ genetic code test passed")
+
+def test_six_frames():
+    """Test six-frame translation of sequences and files"""
+    frames = translate_six_frames("ATGAAATAG")
+    assert [frame for frame, _ in frames] == [1, 2, 3, -1, -2, -3]
+    assert frames == [(frame, translate("ATGAAATAG", frame=frame)) for frame in [1, 2, 3, -1, -2, -3]]
+
+    with open("test_frames.fasta", "w") as f:
+        f.write(">seq1 desc\nATGAAATAG\n")
+    results = translate_file("test_frames.fasta", frames="all")
+    assert results[0] == ("seq1_frame=1 desc", "MK*")
+    assert results[4] == ("seq1_frame=-2 desc", "YF")
+    assert translate_file("test_frames.fasta", frames="all", threads=2) == results
+
+    out = io.BytesIO()
+    assert translate_file("test_frames.fasta", frames=[1, -1], output_file=out) == 2
+    assert out.getvalue() == b">seq1_frame=1 desc\nMK*\n>seq1_frame=-1 desc\nLFH\n"
+    out = io.BytesIO()
+    written = translate_file(
+        "test_frames.fasta", frames="all", output_file=out, header="{id}_f{frame}",
+        trim_stops=True, min_length=3, compression="gzip",
+    )
+    assert written == 1 and gzip.decompress(out.getvalue()) == b">seq1_f-1\nLFH\n"
+
+    # Short writes are retried and bytearray reads are accepted
+    class Trickle(io.RawIOBase):
+        def __init__(self, data=b""):
+            self.data = bytearray(data)
+        def writable(self):
+            return True
+        def write(self, b):
+            self.data += bytes(b)[:5]
+            return min(len(b), 5)
+        def read(self, size=-1):
+            chunk, self.data = self.data[:7], self.data[7:]
+            return chunk
+    out = Trickle()
+    assert translate_file(Trickle(b">seq1 desc\nATGAAATAG\n"), frames=[1, -1], output_file=out) == 2
+    assert bytes(out.data) == b">seq1_frame=1 desc\nMK*\n>seq1_frame=-1 desc\nLFH\n"
+    print("✓ six-frame translation test passed")
+
+def test_translation_report():
+    """Test translation reports and frameshift translation"""
+    report = translate("ATGAAATAGCCCTTTTAA", report=True)
+    assert report.protein == "MK*PF*" and report.internal_stops == [2] and report.terminal_stop
+    assert report.codons[:2] == [(0, 3), (3, 6)] and report.longest_stop_free == (0, 2)
+    # Input coordinates survive removed gaps and reverse complementing
+    assert translate("ATG-AAATAGCC", frame=-1, gaps="remove", report=True).codons == [(9, 12), (6, 9), (2, 6)]
+    assert [r.protein for _, r in translate_six_frames("ATGAAATAG", report=True)][:2] == ["MK*", "*N"]
+
+    # A -1 slippery site T TTT TTA bypasses the stop of frame 1
+    seq = "ATGGCTTTTTTAGGGTGACCCAAATAA"
+    shifted = translate_frameshifts(seq, shifts=[-1])
+    assert [(f.start, f.end, f.motif) for f in shifted] == [(5, 12, "TTTTTTA")]
+    assert shifted[0].translation.protein == "MAFLRVTQI" and shifted[0].translation.codons[4] == (11, 14)
+    reverse = translate_frameshifts(reverse_complement(seq), frame=-1, shifts=[-1])
+    assert (reverse[0].start, reverse[0].translation.protein) == (15, "MAFLRVTQI")
+    print("✓ translation report test passed")
+
+def test_translate_codons():
+    """Test codon-level translation with input coordinates"""
+    rows = translate_codons("CCATGAAATAGCC", frame=-2, start=1, stop=12)
+    assert rows == [(8, 11, "CTA", "L"), (5, 8, "TTT", "F"), (2, 5, "CAT", "H")]
+    columns = translate_codons("ATGAAATAG", columns=True)
+    assert columns == {"nt_start": [0, 3, 6], "nt_end": [3, 6, 9], "codon": ["ATG", "AAA", "TAG"], "amino_acid": ["M", "K", "*"]}
+    print("✓ codon translation test passed")
+
+def test_orfs():
+    """Test ORF calling with alternative starts, partial and circular ORFs"""
+    seq = "CCTTGAAACCCGGGTAAATGGCATTTAG"
+    assert find_orfs(seq, min_length=0) == []
+    orfs = find_orfs(seq, min_length=0, partial=True)
+    assert [(o.start, o.end, o.strand, o.frame) for o in orfs][:2] == [(0, 6, "+", 1), (0, 24, "-", -2)]
+    assert orfs[-1].protein == "MAF" and orfs[-1].partial_end
+
+    alternative = find_orfs(seq, min_length=0, starts="alternative", genetic_code=11)
+    first = alternative[0]
+    assert (first.start, first.end, first.sequence, first.protein) == (2, 17, "TTGAAACCCGGGTAA", "MKPG")
+
+    # An ORF spanning the origin of a circular sequence
+    circular = find_orfs("AAATAGATGAAA", min_length=0, circular=True)
+    assert [(o.start, o.end, o.protein) for o in circular if o.strand == "+"] == [(6, 18, "MKK")]
+
+    out = io.BytesIO()
+    assert find_orfs_file(b">c1\nATGAAATAGATGCCC\n", out, output_format="bed", min_length=0) == 1
+    assert out.getvalue() == b"c1\t0\t9\tc1_ORF1\t0\t+\n"
+
+    # ORFs across the origin are split in BED and end past a circular region in GFF3
+    out = io.BytesIO()
+    assert find_orfs_file(b">c1\nAAATAGATGAAA\n", out, output_format="bed", circular=True, min_length=0) == 1
+    assert out.getvalue() == b"c1\t6\t12\tc1_ORF1\t0\t+\nc1\t0\t6\tc1_ORF1\t0\t+\n"
+    out = io.BytesIO()
+    assert find_orfs_file(b">c1\nAAATAGATGAAA\n", out, output_format="gff3", circular=True, min_length=0) == 1
+    assert out.getvalue().decode().splitlines()[1:] == [
+        "##sequence-region c1 1 12",
+        "c1\tcapibex\tregion\t1\t12\t.\t.\t.\tID=c1;Is_circular=true",
+        "c1\tcapibex\tORF\t7\t18\t.\t+\t.\tID=c1_ORF1",
+    ]
+    print("✓ ORF test passed")
+
+def test_codon_usage():
+    """Test codon counts, RSCU, ENC, GC3 and CAI"""
+    usage = codon_usage("ATGGCTGCTGCCGCGTAANNN")
+    assert usage.total == 6 and usage.counts["GCT"] == 2
+    rscu = usage.rscu()
+    assert rscu["GCT"] == 2.0 and rscu["GCC"] == 1.0 and rscu["GCA"] == 0.0
+    assert usage.gc3() == 0.6
+
+    # One codon per amino acid gives the minimum ENC and a CAI of 1 against itself
+    first = {}
+    for codon, amino_acid in GeneticCode(1).codons.items():
+        first.setdefault(amino_acid, codon)
+    biased = codon_usage("".join(c for aa, c in first.items() if aa != "*") * 10)
+    assert biased.enc() == 20.0 and biased.cai(biased) == 1.0
+
+    # TGA is a tryptophan codon in code 4
+    assert codon_usage("TGGTGA", genetic_code=4).rscu()["TGA"] == 1.0
+    total = codon_usage_file(b">a\nATGGCT\n>b\nGCC\n")
+    assert total.total == 3 and (codon_usage("ATG") + codon_usage("GCTGCC")).counts == total.counts
+
+    columns = codon_usage_columns(["ATGGCT", None, "GCC"], reference={"GCT": 10, "GCC": 5})
+    assert columns["cai"] == [1.0, None, 0.5] and columns["counts"][1] is None
+
+    # Tables without sense codons have no ENC, GC3 or CAI
+    for empty in [codon_usage(""), codon_usage("TAATAG"), codon_usage("NNN")]:
+        assert math.isnan(empty.enc()) and math.isnan(empty.gc3()) and math.isnan(empty.cai(biased))
+    print("✓ codon usage test passed")
+
+def test_back_translate():
+    """Test degenerate and most-frequent-codon back-translation and primers"""
+    assert back_translate("MSLW*X-") == "ATGWSNYTNTGGTRRNNN---"
+    assert back_translate("W*", genetic_code=4) == "TGRTAR"
+    assert translate(back_translate("MKFW")) == "MKFW"
+    usage = codon_usage("ATGAAGAAAAAGGTCGTT")
+    assert back_translate("MKV", mode="most_frequent", codon_usage=usage) == "ATGAAGGTT"
+
+    primers = degenerate_primers("MKWCHF", length=15, max_degeneracy=8, exact_3prime=2)
+    assert [(p.start, p.strand, p.sequence, p.degeneracy) for p in primers][2] == (2, "+", "GAARTGGTGYCAYTT", 8)
+    assert all(set(p.sequence[-2:]) <= set("ACGT") for p in primers)
+    print("✓ back-translation test passed")
+
+def test_parse_fastx():
+    """Test FASTA/FASTQ parsing"""
+    records = list(parse_fastx_string(">seq1 desc\nACGT\n>seq2\nGG\nCC\n"))
+    assert [r.name for r in records] == ["seq1", "seq2"]
+    assert records[0].description == "desc"
+    assert records[1].seq == "GGCC"
+
+    fastq = next(parse_fastx_string("@read1\nACGT\n+\nII#I\n"))
+    assert fastq.is_fastq()
+    assert decode_phred(fastq.qual) == (40, 40, 2, 40)
+    print("✓ parse_fastx test passed")
+
 def test_filter():
     """Test sequence filtering"""
     
@@ -46,9 +244,254 @@ def test_filter():
         assert ">seq1 test" not in content
     print("✓ filter test passed")
 
+def test_dedup():
+    """Test deduplication (repeated calls with different thread counts)"""
+    with open("test_dedup.fasta", "w") as f:
+        f.write(">seq1\nATGC\n>seq2\natgc\n>seq3\nGGGG\n>seq1\nCCCC\n")
+
+    assert deduplicate_by_seq(["test_dedup.fasta"], "dedup_seq.fasta", threads=1) == 3
+    assert deduplicate_by_id(["test_dedup.fasta"], "dedup_id.fasta", threads=2) == 3
+
+    # Reverse complements and RNA copies collapse on request
+    strands = b">a\nAACGT\n>b\nacgtt\n>c\nAACGU\n>d\nACGUU\n"
+    assert deduplicate_by_seq([io.BytesIO(strands)], io.BytesIO()) == 4
+    assert deduplicate_by_seq([io.BytesIO(strands)], io.BytesIO(), canonical=True) == 2
+    assert deduplicate_by_seq([io.BytesIO(strands)], io.BytesIO(), u_as_t=True) == 2
+    assert deduplicate_by_seq([io.BytesIO(strands)], io.BytesIO(), canonical=True, u_as_t=True) == 1
+
+    # Bytes without a complement and RNA without A keep distinct keys
+    others = b">a\nPPPP\n>b\nQQQQ\n>c\nTTTTX\n>d\nTTTTZ\n"
+    assert deduplicate_by_seq([io.BytesIO(others)], io.BytesIO(), canonical=True) == 4
+    rna = b">a\nUUUU\n>b\nTTTT\n>c\nUUCG\n>d\nTTCG\n>e\nCGAA\n"
+    assert deduplicate_by_seq([io.BytesIO(rna)], io.BytesIO(), canonical=True) == 4
+    assert deduplicate_by_seq([io.BytesIO(rna)], io.BytesIO(), canonical=True, u_as_t=True) == 2
+
+    # Rotations of a circular sequence, on either strand
+    circles = b">a\nAACGTT\n>b\nCGTTAA\n>c\nTTAACG\n>d\nAAGGTT\n"
+    duplicates = io.BytesIO()
+    assert deduplicate_by_seq([io.BytesIO(circles)], io.BytesIO(), circular=True, duplicates_file=duplicates) == 2
+    assert duplicates.getvalue() == b"duplicate\tkept\tstrand\toffset\nb\ta\t+\t2\nc\ta\t+\t4\n"
+    duplicates = io.BytesIO()
+    circles = b">a\nAACGGT\n>b\nCCGTTA\n"
+    assert deduplicate_by_seq([io.BytesIO(circles)], io.BytesIO(), canonical=True, circular=True, duplicates_file=duplicates) == 1
+    assert duplicates.getvalue().splitlines()[1] == b"b\ta\t-\t5"
+
+    # Substrings of longer sequences, on either strand
+    contigs = b">long\nAACCGGTTAC\n>inner\nCCGGT\n>reverse\nGTAACC\n>other\nGGGA\n"
+    unique, duplicates = io.BytesIO(), io.BytesIO()
+    assert deduplicate_by_containment([io.BytesIO(contigs)], unique, k=3, duplicates_file=duplicates) == 2
+    assert unique.getvalue() == b">long\nAACCGGTTAC\n>other\nGGGA\n"
+    assert duplicates.getvalue().splitlines()[1:] == [b"inner\tlong\t+\t2", b"reverse\tlong\t-\t4"]
+    assert deduplicate_by_containment([io.BytesIO(contigs)], io.BytesIO(), canonical=False) == 3
+    print("✓ dedup test passed")
+
+def test_dedup_spill():
+    """Test that dedup keeps the same records in memory, spilled and across thread counts"""
+    rng = random.Random(7)
+    seqs = ["".join(rng.choices("ACGT", k=rng.randint(1, 6))) for _ in range(3000)]
+    records = [f">r{i}\n{seq}\n".encode() for i, seq in enumerate(seqs)]
+    data = b"".join(records)
+    expected = "".join(f">r{i}\n{seq}\n" for i, seq in enumerate(seqs) if seq not in seqs[:i]).encode()
+
+    with tempfile.TemporaryDirectory() as tmp:
+        outputs = []
+        for threads, spill_dir in [(1, None), (4, None), (1, tmp), (4, tmp)]:
+            out, duplicates = io.BytesIO(), io.BytesIO()
+            halves = [b"".join(records[:1000]), b"".join(records[1000:])]
+            deduplicate_by_seq([io.BytesIO(half) for half in halves], out, threads=threads,
+                               canonical=True, duplicates_file=duplicates, spill_dir=spill_dir)
+            outputs.append((out.getvalue(), duplicates.getvalue()))
+        assert all(output == outputs[0] for output in outputs)
+        # Without a duplicates table only hashes are held
+        out = io.BytesIO()
+        deduplicate_by_seq([io.BytesIO(half) for half in halves], out, threads=4, canonical=True)
+        assert out.getvalue() == outputs[0][0]
+        assert os.listdir(tmp) == []
+
+        out = io.BytesIO()
+        deduplicate_by_seq([io.BytesIO(data)], out, spill_dir=tmp)
+        assert out.getvalue() == expected
+        out = io.BytesIO()
+        assert deduplicate_by_id([io.BytesIO(data + data)], out, spill_dir=tmp) == len(seqs)
+        assert out.getvalue() == data
+    print("✓ dedup spill test passed")
+
+def test_dedup_keep():
+    """Test which duplicate the keep policies choose"""
+    reads = b"@a\nACGT\n+\n!!!!\n@b long\nACGT\n+\nIIII\n@c\nacgt\n+\n5555\n@d\nGG\n+\nII\n"
+    other = b"@e\nACGT\n+\n!!!!\n"
+
+    def kept(keep, inputs=(reads,)):
+        out = io.BytesIO()
+        deduplicate_by_seq([io.BytesIO(data) for data in inputs], out, keep=keep)
+        return [line[1:] for line in out.getvalue().splitlines()[::4]]
+
+    assert kept("first") == [b"a", b"d"]
+    assert kept("last") == [b"c", b"d"]
+    assert kept("longest_header") == [b"b long", b"d"]
+    assert kept("best_quality") == [b"b long", b"d"]
+    assert kept(1, (reads, other)) == [b"d", b"e"]
+    assert kept(0, (reads, other)) == [b"a", b"d"]
+
+    # The duplicates table names the record finally kept
+    duplicates = io.BytesIO()
+    deduplicate_by_seq([io.BytesIO(reads)], io.BytesIO(), keep="last", duplicates_file=duplicates)
+    assert duplicates.getvalue().splitlines()[1:] == [b"a\tc\t+\t0", b"b\tc\t+\t0"]
+
+    for keep, data in [("best_quality", b">a\nACGT\n"), ("longest", reads), (2, reads), (-1, reads), (True, reads), (1.0, reads)]:
+        try:
+            deduplicate_by_seq([io.BytesIO(data)], io.BytesIO(), keep=keep)
+            assert False, f"keep={keep!r} should fail"
+        except ValueError:
+            pass
+    print("✓ dedup keep test passed")
+
+def test_writer():
+    """Test FastxWriter formatting options"""
+    out = io.BytesIO()
+    with FastxWriter(out, line_length=2, case="upper") as writer:
+        writer.write(("seq1", "acgt"))
+        writer.write(Record("seq2", "ac", "II"))
+    assert writer.closed
+    assert out.getvalue() == b">seq1\nAC\nGT\n@seq2\nAC\n+\nII\n"
+
+    out = io.BytesIO()
+    with FastxWriter(out, format="fasta", line_length=0, output_phred=64) as writer:
+        assert writer.write_records([("seq1", "ACGT", "IIII"), ("seq2", "A")]) == 2
+    assert out.getvalue() == b">seq1\nACGT\n>seq2\nA\n"
+    print("✓ writer test passed")
+
+def test_indexed_fasta():
+    """Test .fai indexing and random access on plain and BGZF FASTA"""
+    with open("test_faidx.fasta", "w") as f:
+        f.write(">chr1 first\nACGTA\nCCGGT\nAC\n>chr2\nGGGG\n")
+    assert index_fasta("test_faidx.fasta") == "test_faidx.fasta.fai"
+    with open("test_faidx.fasta.fai") as f:
+        assert f.read() == "chr1\t12\t12\t5\t6\nchr2\t4\t33\t4\t5\n"
+
+    with FastxWriter("test_faidx.fasta.bgz", line_length=3) as writer:
+        writer.write_records(parse_fastx_file("test_faidx.fasta"))
+    for path in ["test_faidx.fasta", "test_faidx.fasta.bgz"]:
+        fasta = IndexedFasta(path)
+        assert fasta.keys() == ["chr1", "chr2"]
+        assert fasta.lengths == {"chr1": 12, "chr2": 4}
+        assert fasta["chr2"] == "GGGG"
+        assert fasta.fetch("chr1", 3, 8) == "TACCG"
+        assert fasta.fetch("chr1", 3, 8, strand="-") == "CGGTA"
+
+    # Corrupt and truncated BGZF blocks raise instead of panicking
+    with open("test_faidx.fasta.bgz", "rb") as f:
+        bgzf = f.read()
+    corrupt = bgzf[:16] + bytes([5, 0]) + bgzf[18:]
+    for path, data in [("test_corrupt.fasta.bgz", corrupt), ("test_truncated.fasta.bgz", bgzf[:30])]:
+        with open(path, "wb") as f:
+            f.write(data)
+        try:
+            index_fasta(path)
+            assert False, "Expected an error for a damaged BGZF file"
+        except OSError as e:
+            assert "panicked" not in str(e)
+    print("✓ indexed FASTA test passed")
+
+def test_extract_regions():
+    """Test BED and GFF3 region extraction"""
+    with open("test_regions.fasta", "w") as f:
+        f.write(">chr1\nAAAACCCCGG\nGGTTTTACGT\n")
+    with open("test_regions.bed", "w") as f:
+        f.write("chr1\t0\t4\tfirst\t0\t-\nchr1\t8\t12\n")
+    assert extract_regions("test_regions.fasta", "test_regions.bed", "regions.fasta", flank=1) == 2
+    with open("regions.fasta") as f:
+        assert f.read() == ">first chr1:1-5(-)\nGTTTT\n>chr1:8-13(+)\nCGGGGT\n"
+
+    with open("test_regions.gff3", "w") as f:
+        f.write(
+            "##gff-version 3\n"
+            "chr1\t.\tCDS\t2\t4\t.\t-\t0\tID=cds1;Parent=tx1\n"
+            "chr1\t.\tCDS\t9\t12\t.\t-\t0\tID=cds2;Parent=tx1\n"
+        )
+    out = io.BytesIO()
+    assert extract_regions("test_regions.fasta", "test_regions.gff3", out, join_cds=True) == 1
+    assert out.getvalue() == b">tx1 chr1:2-4,9-12(-)\nCCCCTTT\n"
+
+    # Without a file name the format is guessed from the first data line
+    gtf = b'# header\nchr1\t.\tCDS\t2\t4\t.\t+\t0\tgene_id "g1"; transcript_id "t1";\n'
+    out = io.BytesIO()
+    assert extract_regions("test_regions.fasta", io.BytesIO(gtf), out) == 1
+    assert out.getvalue() == b">chr1:2-4(+)\nAAA\n"
+    out = io.BytesIO()
+    assert extract_regions("test_regions.fasta", io.BytesIO(b"track name=x\nchr1\t0\t4\n"), out) == 1
+    assert out.getvalue() == b">chr1:1-4(+)\nAAAA\n"
+    print("✓ extract regions test passed")
+
+def test_gff():
+    """Test GFF3/GTF parsing, hierarchy and round-tripping"""
+    gff = (
+        b"##gff-version 3\n"
+        b"chr1\t.\tgene\t1\t20\t.\t+\t.\tID=g1;Name=a%3Bb;Alias=x,y\n"
+        b"chr1\t.\tmRNA\t1\t20\t.\t+\t.\tID=m1;Parent=g1\n"
+        b"chr1\t.\tCDS\t2\t4\t.\t+\t0\tParent=m1\n"
+        b"##FASTA\n>chr1\nACGT\n"
+    )
+    features = read_gff(gff)
+    assert features["type"] == ["gene", "mRNA", "CDS"]
+    assert features["attributes"][0] == {"ID": ["g1"], "Name": ["a;b"], "Alias": ["x", "y"]}
+    assert features["parents"] == [[], [0], [1]]
+    assert features["children"] == [[1], [2], []]
+    assert read_gff_sequences(gff) == {"chr1": "ACGT"}
+
+    out = io.BytesIO()
+    assert write_gff(features, out, sequences={"chr1": "ACGT"}) == 3
+    assert out.getvalue() == gff
+
+    gtf = b'chr1\t.\texon\t1\t4\t.\t-\t.\tgene_id "G1"; transcript_id "T1"; tag "a"; tag "b";\n'
+    features = read_gff(gtf)
+    assert features["attributes"][0]["tag"] == ["a", "b"]
+    out = io.BytesIO()
+    write_gff(features, out, format="gtf")
+    assert out.getvalue() == gtf
+
+    # Genes and transcripts may share an ID without becoming their own parent
+    gtf = (
+        b'chr1\t.\tgene\t1\t20\t.\t+\t.\tgene_id "X";\n'
+        b'chr1\t.\ttranscript\t1\t20\t.\t+\t.\tgene_id "X"; transcript_id "X";\n'
+        b'chr1\t.\texon\t1\t4\t.\t+\t.\tgene_id "X"; transcript_id "X";\n'
+    )
+    features = read_gff(gtf)
+    assert features["id"] == ["X", "X", None]
+    assert features["parents"] == [[], [0], [1]]
+    assert features["children"] == [[1], [2], []]
+    print("✓ GFF test passed")
+
+def test_file_objects():
+    """Test bytes and file-like objects as inputs and outputs"""
+    data = b">seq1\nATGC\n>seq2\nATGC\n>seq3\nGGGG\n"
+    assert [r.id for r in parse_fastx_file(data)] == ["seq1", "seq2", "seq3"]
+
+    out = io.BytesIO()
+    assert deduplicate_by_seq([io.BytesIO(data)], out) == 2
+    assert out.getvalue() == b">seq1\nATGC\n>seq3\nGGGG\n"
+    print("✓ file object test passed")
+
 if __name__ == "__main__":
     print("Running tests...")
     test_reverse_complement()
     test_translate()
-    # test_filter() # ValueError: Failed to set up thread pool: The global thread pool has already been initialized.
+    test_ambiguous_translation()
+    test_genetic_code()
+    test_six_frames()
+    test_translation_report()
+    test_translate_codons()
+    test_orfs()
+    test_codon_usage()
+    test_back_translate()
+    test_parse_fastx()
+    test_filter()
+    test_dedup()
+    test_dedup_spill()
+    test_dedup_keep()
+    test_writer()
+    test_indexed_fasta()
+    test_extract_regions()
+    test_gff()
+    test_file_objects()
     print("\nAll tests passed! 🎉") 
\ No newline at end of file
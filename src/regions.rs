@@ -0,0 +1,423 @@
+//! Extraction of BED/GFF3/GTF regions from a FASTA file
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use crate::faidx::IndexedFasta;
+use crate::fastx_io::{RecordReader, RecordWriter, DEFAULT_LINE_LENGTH};
+use crate::gff::{Annotation, AnnotationFormat, Feature};
+use crate::pool::{self, Interrupt};
+use crate::pyfile::{InputSource, OutputTarget};
+use crate::seq_utils::reverse_complement;
+
+/// Interval file formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RegionFormat {
+    Bed,
+    Annotation(AnnotationFormat),
+}
+
+impl RegionFormat {
+    fn from_name(name: &str) -> PyResult<Self> {
+        if name.eq_ignore_ascii_case("bed") {
+            return Ok(RegionFormat::Bed);
+        }
+        AnnotationFormat::from_name(name)
+            .map(RegionFormat::Annotation)
+            .map_err(|_| {
+                PyValueError::new_err(format!(
+                    "Unknown region format '{}'. Use 'bed', 'gff3' or 'gtf'.",
+                    name
+                ))
+            })
+    }
+
+    /// Guess the format from the file name, ignoring compression suffixes
+    fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        let stem = [".gz", ".bgz", ".bz2", ".xz", ".zst"]
+            .iter()
+            .find_map(|ext| lower.strip_suffix(ext))
+            .unwrap_or(&lower);
+        if stem.ends_with(".bed") {
+            Some(RegionFormat::Bed)
+        } else {
+            AnnotationFormat::from_path(path).map(RegionFormat::Annotation)
+        }
+    }
+
+    /// Guess the format from the first data line
+    fn from_line(line: &str) -> Self {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let is_number = |i: usize| fields.get(i).is_some_and(|f| f.parse::<u64>().is_ok());
+        if fields.len() >= 9 && is_number(3) && is_number(4) {
+            RegionFormat::Annotation(AnnotationFormat::from_attributes(fields[8]))
+        } else {
+            RegionFormat::Bed
+        }
+    }
+}
+
+/// A 0-based, half-open interval on one strand of a sequence
+#[derive(Clone, Debug)]
+struct Region {
+    seqid: String,
+    start: u64,
+    end: u64,
+    reverse: bool,
+    name: Option<String>,
+    feature_type: Option<String>,
+    parent: Option<String>,
+}
+
+impl Region {
+    fn location(&self) -> String {
+        format!("{}:{}-{}", self.seqid, self.start + 1, self.end)
+    }
+}
+
+fn strand_char(reverse: bool) -> char {
+    if reverse {
+        '-'
+    } else {
+        '+'
+    }
+}
+
+fn parse_coordinate(value: &str, line_number: usize) -> PyResult<u64> {
+    value.parse().map_err(|_| {
+        PyValueError::new_err(format!(
+            "Invalid coordinate '{}' on line {}",
+            value, line_number
+        ))
+    })
+}
+
+fn parse_bed_line(line: &str, line_number: usize) -> PyResult<Region> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 3 {
+        return Err(PyValueError::new_err(format!(
+            "Expected at least 3 BED columns on line {}",
+            line_number
+        )));
+    }
+    let name = fields
+        .get(3)
+        .filter(|name| !name.is_empty() && **name != ".")
+        .map(|name| name.to_string());
+    Ok(Region {
+        seqid: fields[0].to_string(),
+        start: parse_coordinate(fields[1], line_number)?,
+        end: parse_coordinate(fields[2], line_number)?,
+        reverse: fields.get(5) == Some(&"-"),
+        name,
+        feature_type: None,
+        parent: None,
+    })
+}
+
+impl From<Feature> for Region {
+    fn from(feature: Feature) -> Self {
+        let name = feature
+            .id
+            .clone()
+            .or_else(|| feature.first_attribute("Name").map(str::to_string));
+        Region {
+            start: feature.start - 1,
+            end: feature.end,
+            reverse: feature.strand == Some('-'),
+            name,
+            // Only the first parent is used to group features
+            parent: feature.parents.into_iter().next(),
+            feature_type: Some(feature.feature_type),
+            seqid: feature.seqid,
+        }
+    }
+}
+
+/// Read the intervals of a BED, GFF3 or GTF file
+fn read_regions(
+    source: InputSource,
+    format: Option<&str>,
+    interrupt: &Interrupt,
+) -> PyResult<Vec<Region>> {
+    let name = source.name();
+    let format = match format {
+        Some(format) => Some(RegionFormat::from_name(format)?),
+        None => RegionFormat::from_path(&name),
+    };
+    let read_error =
+        |e: std::io::Error| PyIOError::new_err(format!("Error reading {}: {}", name, e));
+    let is_data = |line: &&str| {
+        !(line.trim().is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser"))
+    };
+    // Read up to the first data line to guess the format, then stream the
+    // lines read so far followed by the rest of the input
+    let mut reader = BufReader::new(source.open()?);
+    let mut head = String::new();
+    let format = match format {
+        Some(format) => format,
+        None => loop {
+            let start = head.len();
+            if reader.read_line(&mut head).map_err(read_error)? == 0 {
+                break RegionFormat::Bed;
+            }
+            let line = head[start..].trim_end_matches(['\n', '\r']);
+            if is_data(&line) {
+                break RegionFormat::from_line(line);
+            }
+        },
+    };
+    let content = Cursor::new(head.into_bytes()).chain(reader);
+
+    if let RegionFormat::Annotation(format) = format {
+        let annotation = Annotation::from_reader(content, &name, Some(format), false, interrupt)?;
+        return Ok(annotation.features.into_iter().map(Region::from).collect());
+    }
+
+    let mut regions = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.map_err(read_error)?;
+        let line = line.trim_end_matches('\r');
+        if !is_data(&line) {
+            continue;
+        }
+        let region = parse_bed_line(line, i + 1)?;
+        if region.start > region.end {
+            return Err(PyValueError::new_err(format!(
+                "Region {} on line {} ends before it starts",
+                region.location(),
+                i + 1
+            )));
+        }
+        regions.push(region);
+    }
+    Ok(regions)
+}
+
+/// The sequences regions are extracted from
+enum Genome {
+    /// A FASTA file with a `.fai` index, read on demand
+    Indexed(IndexedFasta),
+    /// The sequences referenced by the regions, loaded in memory
+    Loaded(HashMap<String, Vec<u8>>),
+}
+
+impl Genome {
+    /// Use the index of `fasta` if it has one, otherwise load the sequences
+    /// named in `seqids`
+    fn open(fasta: InputSource, seqids: &HashSet<&str>, interrupt: &Interrupt) -> PyResult<Self> {
+        if let InputSource::Path(path) = &fasta {
+            if let Ok(Some(indexed)) = IndexedFasta::open_indexed(path) {
+                return Ok(Genome::Indexed(indexed));
+            }
+        }
+        let mut sequences = HashMap::new();
+        for record in RecordReader::open(fasta)? {
+            interrupt.check()?;
+            let record = record?;
+            let name = String::from_utf8_lossy(record.name()).into_owned();
+            if seqids.contains(name.as_str()) && !sequences.contains_key(&name) {
+                sequences.insert(name, record.seq);
+            }
+        }
+        Ok(Genome::Loaded(sequences))
+    }
+
+    fn length(&self, seqid: &str) -> Option<u64> {
+        match self {
+            Genome::Indexed(fasta) => fasta.length(seqid),
+            Genome::Loaded(sequences) => sequences.get(seqid).map(|seq| seq.len() as u64),
+        }
+    }
+
+    /// The bases of `seqid` in `[start, end)`, after checking that they exist
+    fn fetch(&self, seqid: &str, start: u64, end: u64) -> PyResult<Vec<u8>> {
+        match self {
+            Genome::Indexed(fasta) => fasta.fetch_bytes(seqid, start, end),
+            Genome::Loaded(sequences) => {
+                Ok(sequences[seqid][start as usize..end as usize].to_vec())
+            }
+        }
+    }
+
+    /// Extend `region` by `flank` on both sides and clip it to its sequence
+    fn clip(&self, region: &Region, flank: u64) -> PyResult<(u64, u64)> {
+        let length = self.length(&region.seqid).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "Sequence '{}' of region {} not found in the FASTA file",
+                region.seqid,
+                region.location()
+            ))
+        })?;
+        if region.start > length {
+            return Err(PyValueError::new_err(format!(
+                "Region {} starts beyond the end of '{}' ({} bp)",
+                region.location(),
+                region.seqid,
+                length
+            )));
+        }
+        Ok((
+            region.start.saturating_sub(flank),
+            (region.end + flank).min(length),
+        ))
+    }
+}
+
+/// A sequence to write: one region, or the CDS parts of one parent
+struct Extract {
+    name: Option<String>,
+    parts: Vec<Region>,
+}
+
+/// Group the selected regions into extracts, in file order
+fn plan_extracts(
+    regions: Vec<Region>,
+    feature_types: Option<&HashSet<String>>,
+    join_cds: bool,
+) -> Vec<Extract> {
+    let mut extracts: Vec<Extract> = Vec::new();
+    let mut by_parent: HashMap<String, usize> = HashMap::new();
+    for region in regions {
+        let feature_type = region.feature_type.as_deref();
+        let selected = match (feature_types, feature_type) {
+            (Some(types), Some(feature_type)) => types.contains(feature_type),
+            (None, Some(feature_type)) => !join_cds || feature_type == "CDS",
+            (_, None) => true,
+        };
+        if !selected {
+            continue;
+        }
+        if join_cds && feature_type == Some("CDS") {
+            let parent = region.parent.clone().or_else(|| region.name.clone());
+            if let Some(parent) = parent {
+                if let Some(&i) = by_parent.get(&parent) {
+                    extracts[i].parts.push(region);
+                } else {
+                    by_parent.insert(parent.clone(), extracts.len());
+                    extracts.push(Extract {
+                        name: Some(parent),
+                        parts: vec![region],
+                    });
+                }
+                continue;
+            }
+        }
+        extracts.push(Extract {
+            name: region.name.clone(),
+            parts: vec![region],
+        });
+    }
+    extracts
+}
+
+/// Fetch the sequence of an extract and build its header
+fn extract_sequence(
+    genome: &Genome,
+    extract: &mut Extract,
+    flank: u64,
+) -> PyResult<(String, String)> {
+    let parts = &mut extract.parts;
+    parts.sort_by_key(|part| part.start);
+    let first = &parts[0];
+    if let Some(part) = parts
+        .iter()
+        .find(|part| part.seqid != first.seqid || part.reverse != first.reverse)
+    {
+        return Err(PyValueError::new_err(format!(
+            "CDS parts of '{}' lie on different sequences or strands ({} and {})",
+            extract.name.as_deref().unwrap_or_default(),
+            first.location(),
+            part.location()
+        )));
+    }
+
+    let last = parts.len() - 1;
+    let mut seq = Vec::new();
+    let mut spans = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let (start, end) = genome.clip(part, flank)?;
+        // Flanks only extend the outer ends of joined parts
+        let start = if i == 0 { start } else { part.start };
+        let end = if i == last { end } else { part.end.min(end) };
+        seq.extend(genome.fetch(&part.seqid, start, end)?);
+        spans.push(format!("{}-{}", start + 1, end));
+    }
+    let seq = String::from_utf8_lossy(&seq).into_owned();
+    let seq = if first.reverse {
+        reverse_complement(&seq)
+    } else {
+        seq
+    };
+
+    let location = format!(
+        "{}:{}({})",
+        first.seqid,
+        spans.join(","),
+        strand_char(first.reverse)
+    );
+    let header = match &extract.name {
+        Some(name) => format!("{} {}", name, location),
+        None => location,
+    };
+    Ok((header, seq))
+}
+
+/// Extract the sequences of BED, GFF3 or GTF regions from a FASTA file.
+///
+/// Minus-strand regions are reverse complemented. Headers hold the region
+/// name (BED name, GFF `ID` or `Name`) followed by its 1-based location
+/// including flanks, e.g. `gene1 chr1:101-200(-)`.
+///
+/// If `fasta` is a path with a `.fai` index next to it, only the regions are
+/// read from it; otherwise the referenced sequences are loaded into memory.
+///
+/// With `join_cds=True`, `CDS` features are joined per `Parent` (or GTF
+/// transcript) in genomic order, and only CDS features are extracted unless `feature_types`
+/// says otherwise. `flank` extends every region (or joined CDS) on both sides,
+/// clipped to the sequence ends.
+///
+/// Returns the number of sequences written.
+#[pyfunction]
+#[pyo3(signature = (fasta, regions, output_file, format=None, feature_types=None, join_cds=false, flank=0, line_length=DEFAULT_LINE_LENGTH, compression=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn extract_regions(
+    py: Python<'_>,
+    fasta: InputSource,
+    regions: InputSource,
+    output_file: OutputTarget,
+    format: Option<&str>,
+    feature_types: Option<Vec<String>>,
+    join_cds: bool,
+    flank: u64,
+    line_length: usize,
+    compression: Option<&str>,
+) -> PyResult<usize> {
+    let feature_types: Option<HashSet<String>> =
+        feature_types.map(|types| types.into_iter().collect());
+    let mut writer = RecordWriter::create(output_file, line_length, compression)?;
+
+    pool::run(py, Some(1), |interrupt| {
+        let regions = read_regions(regions, format, interrupt)?;
+        let mut extracts = plan_extracts(regions, feature_types.as_ref(), join_cds);
+        let seqids: HashSet<&str> = extracts
+            .iter()
+            .flat_map(|extract| extract.parts.iter().map(|part| part.seqid.as_str()))
+            .collect();
+        let genome = Genome::open(fasta, &seqids, interrupt)?;
+
+        for extract in &mut extracts {
+            interrupt.check()?;
+            let (header, seq) = extract_sequence(&genome, extract, flank)?;
+            writer.write_parts(header.as_bytes(), seq.as_bytes(), None)?;
+        }
+        writer.finish()?;
+        Ok(extracts.len())
+    })
+}
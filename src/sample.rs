@@ -2,95 +2,79 @@ use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use rand::seq::SliceRandom;
 // use rand::rng;
-use std::fs::File;
-use std::io::{self, BufRead, BufWriter, Write};
-use std::path::Path;
+
+use crate::fastx_io::{RecordReader, RecordWriter, SeqRecord, DEFAULT_LINE_LENGTH};
+use crate::pool::{self, Interrupt};
+use crate::pyfile::{InputSource, OutputTarget};
 
 /// Sample a specific number of sequences
 #[pyfunction]
+#[pyo3(signature = (input_file, output_file, n, line_length=DEFAULT_LINE_LENGTH, compression=None))]
 pub fn sample_sequences(
-    input_file: &str,
-    output_file: &str,
+    py: Python<'_>,
+    input_file: InputSource,
+    output_file: OutputTarget,
     n: usize,
     line_length: usize,
+    compression: Option<&str>,
 ) -> PyResult<usize> {
-    // Read all sequences into memory
-    let mut sequences = Vec::new();
-    let mut current_header = String::new();
-    let mut current_sequence = String::new();
-    
-    // Open the input file
-    let input_path = Path::new(input_file);
-    let file = File::open(input_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to open input file: {}", e)))?;
-    
-    let reader = io::BufReader::new(file);
-    for line in reader.lines() {
-        let line = line.map_err(|e| PyValueError::new_err(format!("Error reading line: {}", e)))?;
-        if line.starts_with('>') {
-            if !current_header.is_empty() {
-                sequences.push((current_header.clone(), current_sequence.clone()));
-                current_sequence = String::new();
-            }
-            current_header = line[1..].trim().to_string();
-        } else {
-            current_sequence.push_str(line.trim());
-        }
-    }
-    
-    // Add the last sequence
-    if !current_header.is_empty() {
-        sequences.push((current_header, current_sequence));
-    }
-    
-    // Sample sequences
-    let mut rng = rand::rng();
-    let sample_size = n.min(sequences.len());
-    sequences.shuffle(&mut rng);
-    let sampled = &sequences[..sample_size];
-    
-    // Write sampled sequences
-    let output_path = Path::new(output_file);
-    let output_file = File::create(output_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to create output file: {}", e)))?;
-    let mut writer = BufWriter::new(output_file);
-    
-    for (header, sequence) in sampled {
-        writeln!(writer, ">{}", header)
-            .map_err(|e| PyValueError::new_err(format!("Error writing header: {}", e)))?;
-        
-        // Write sequence with line breaks every 60 characters
-        for chunk in sequence.as_bytes().chunks(line_length) {
-            writeln!(writer, "{}", std::str::from_utf8(chunk).unwrap())
-                .map_err(|e| PyValueError::new_err(format!("Error writing sequence: {}", e)))?;
-        }
-    }
-    
-    Ok(sample_size)
+    let writer = RecordWriter::create(output_file, line_length, compression)?;
+    pool::run(py, Some(1), |interrupt| {
+        let sequences = read_all(input_file, interrupt)?;
+        write_sample(sequences, n, writer, interrupt)
+    })
 }
 
 /// Sample a proportion of sequences
 #[pyfunction]
+#[pyo3(signature = (input_file, output_file, proportion, line_length=DEFAULT_LINE_LENGTH, compression=None))]
 pub fn sample_sequences_by_proportion(
-    input_file: &str,
-    output_file: &str,
+    py: Python<'_>,
+    input_file: InputSource,
+    output_file: OutputTarget,
     proportion: f64,
     line_length: usize,
+    compression: Option<&str>,
 ) -> PyResult<usize> {
     if proportion <= 0.0 || proportion > 1.0 {
         return Err(PyValueError::new_err("Proportion must be between 0 and 1"));
     }
-    
-    // Count total sequences first
-    let input_path = Path::new(input_file);
-    let file = File::open(input_path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to open input file: {}", e)))?;
-    
-    let reader = io::BufReader::new(file);
-    let total_sequences = reader.lines()
-        .filter(|line| line.as_ref().map_or(false, |l| l.starts_with('>')))
-        .count();
-    
-    let n = (total_sequences as f64 * proportion).round() as usize;
-    sample_sequences(input_file, output_file, n, line_length)
+
+    let writer = RecordWriter::create(output_file, line_length, compression)?;
+    pool::run(py, Some(1), |interrupt| {
+        // The input is read once so streams such as stdin work too
+        let sequences = read_all(input_file, interrupt)?;
+        let n = (sequences.len() as f64 * proportion).round() as usize;
+        write_sample(sequences, n, writer, interrupt)
+    })
+}
+
+/// Read all sequences into memory
+fn read_all(input_file: InputSource, interrupt: &Interrupt) -> PyResult<Vec<SeqRecord>> {
+    let mut sequences = Vec::new();
+    for record in RecordReader::open(input_file)? {
+        interrupt.check()?;
+        sequences.push(record?);
+    }
+    Ok(sequences)
+}
+
+/// Shuffle the sequences and write the first `n`
+fn write_sample(
+    mut sequences: Vec<SeqRecord>,
+    n: usize,
+    mut writer: RecordWriter,
+    interrupt: &Interrupt,
+) -> PyResult<usize> {
+    let mut rng = rand::rng();
+    let sample_size = n.min(sequences.len());
+    sequences.shuffle(&mut rng);
+
+    for record in &sequences[..sample_size] {
+        interrupt.check()?;
+        writer.write_record(record)?;
+    }
+    writer.finish()?;
+
+    Ok(sample_size)
 }
@@ -54,6 +54,7 @@ pub fn complement_base(base: u8) -> u8 {
         b'D' | b'd' => b'H', // D = A, G, or T; H = A, C, or T
         b'H' | b'h' => b'D',
         b'N' | b'n' => b'N', //  N  is wildcard, reverse of it is also wildcard
+        b'-' => b'-', // alignment gaps stay gaps
         _ => b'N' // Default to N for any other character
     }
 }
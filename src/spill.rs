@@ -0,0 +1,285 @@
+//! Temporary partition files for inputs larger than memory
+//!
+//! Entries are written to partition files tagged with their input index,
+//! processed one partition at a time and merged back in input order. Integers
+//! are little-endian and byte strings are prefixed with their length.
+
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fastx_io::SeqRecord;
+
+/// Number of spill directories named by this process, making names unique
+static SPILL_DIRS: AtomicU64 = AtomicU64::new(0);
+
+pub fn spill_error(e: io::Error) -> PyErr {
+    PyIOError::new_err(format!("Error with spill files: {}", e))
+}
+
+/// A temporary directory of partition files, removed with its content when
+/// dropped
+pub struct SpillDir {
+    path: PathBuf,
+}
+
+impl SpillDir {
+    /// Create a uniquely named directory inside `parent`, creating `parent`
+    /// if needed
+    ///
+    /// Directories are never shared: names that exist already, say from a
+    /// concurrent call, are skipped.
+    pub fn create(parent: &Path) -> PyResult<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        fs::create_dir_all(parent).map_err(spill_error)?;
+        loop {
+            let attempt = SPILL_DIRS.fetch_add(1, Ordering::Relaxed);
+            let name = format!("capibex-spill-{}-{}-{}", std::process::id(), nanos, attempt);
+            let path = parent.join(name);
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(SpillDir { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(spill_error(e)),
+            }
+        }
+    }
+
+    /// Create `count` partition files named `<prefix><n>`
+    pub fn partitions(&self, prefix: &str, count: usize) -> PyResult<Vec<Partition>> {
+        (0..count)
+            .map(|n| Partition::create(self.path.join(format!("{}{}", prefix, n))))
+            .collect()
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A partition file being written
+pub struct Partition {
+    pub path: PathBuf,
+    pub out: BufWriter<File>,
+}
+
+impl Partition {
+    fn create(path: PathBuf) -> PyResult<Self> {
+        let out = BufWriter::new(File::create(&path).map_err(spill_error)?);
+        Ok(Partition { path, out })
+    }
+
+    /// Flush the partition, returning its path
+    pub fn finish(mut self) -> PyResult<PathBuf> {
+        self.out.flush().map_err(spill_error)?;
+        Ok(self.path)
+    }
+}
+
+/// Open a finished partition file for reading
+pub fn open_partition(path: &Path) -> PyResult<BufReader<File>> {
+    Ok(BufReader::new(File::open(path).map_err(spill_error)?))
+}
+
+pub fn write_u64<W: Write>(out: &mut W, value: u64) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Read the index starting an entry, `None` at the end of the file
+///
+/// Only a file ending between entries is complete: an index cut short, say
+/// by a full disk, is an error rather than a silent end of the partition.
+pub fn read_index<R: Read>(input: &mut R) -> io::Result<Option<u64>> {
+    let mut bytes = [0; 8];
+    let first = loop {
+        match input.read(&mut bytes[..1]) {
+            Ok(n) => break n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    if first == 0 {
+        return Ok(None);
+    }
+    input
+        .read_exact(&mut bytes[1..])
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                io::Error::new(io::ErrorKind::InvalidData, "truncated spill file")
+            }
+            _ => e,
+        })?;
+    Ok(Some(u64::from_le_bytes(bytes)))
+}
+
+pub fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u64(out, bytes.len() as u64)?;
+    out.write_all(bytes)
+}
+
+pub fn read_bytes<R: Read>(input: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; read_u64(input)? as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn write_record<W: Write>(out: &mut W, record: &SeqRecord) -> io::Result<()> {
+    write_bytes(out, &record.id)?;
+    write_bytes(out, &record.seq)?;
+    match &record.qual {
+        Some(qual) => out.write_all(&[1]).and_then(|_| write_bytes(out, qual)),
+        None => out.write_all(&[0]),
+    }
+}
+
+pub fn read_record<R: Read>(input: &mut R) -> io::Result<SeqRecord> {
+    let id = read_bytes(input)?;
+    let seq = read_bytes(input)?;
+    let mut has_qual = [0];
+    input.read_exact(&mut has_qual)?;
+    let qual = match has_qual[0] {
+        0 => None,
+        _ => Some(read_bytes(input)?),
+    };
+    Ok(SeqRecord { id, seq, qual })
+}
+
+/// Merge partition files whose entries are sorted by index, passing the
+/// entries to `emit` in index order
+///
+/// `read` reads the rest of an entry after its index.
+pub fn merge_by_index<T>(
+    paths: &[PathBuf],
+    read: impl Fn(&mut BufReader<File>) -> io::Result<T>,
+    mut emit: impl FnMut(T) -> PyResult<()>,
+) -> PyResult<()> {
+    let next_entry = |input: &mut BufReader<File>| -> PyResult<Option<(u64, T)>> {
+        match read_index(input).map_err(spill_error)? {
+            Some(index) => Ok(Some((index, read(input).map_err(spill_error)?))),
+            None => Ok(None),
+        }
+    };
+    let mut readers = paths
+        .iter()
+        .map(|path| open_partition(path))
+        .collect::<PyResult<Vec<_>>>()?;
+    let mut pending: Vec<Option<T>> = Vec::with_capacity(readers.len());
+    let mut heap = BinaryHeap::new();
+    for (partition, input) in readers.iter_mut().enumerate() {
+        let entry = next_entry(input)?;
+        if let Some((index, _)) = entry {
+            heap.push(Reverse((index, partition)));
+        }
+        pending.push(entry.map(|(_, entry)| entry));
+    }
+    while let Some(Reverse((_, partition))) = heap.pop() {
+        if let Some(entry) = pending[partition].take() {
+            emit(entry)?;
+        }
+        if let Some((index, entry)) = next_entry(&mut readers[partition])? {
+            heap.push(Reverse((index, partition)));
+            pending[partition] = Some(entry);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, seq: &str, qual: Option<&str>) -> SeqRecord {
+        SeqRecord {
+            id: id.as_bytes().to_vec(),
+            seq: seq.as_bytes().to_vec(),
+            qual: qual.map(|qual| qual.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let records = [
+            record("read1 sample=a", "ACGT", Some("II#I")),
+            record("contig", "", None),
+            record("", "NNNN", Some("")),
+        ];
+        let mut bytes = Vec::new();
+        for (index, record) in records.iter().enumerate() {
+            write_u64(&mut bytes, index as u64).unwrap();
+            write_record(&mut bytes, record).unwrap();
+        }
+        let mut input = bytes.as_slice();
+        for (index, expected) in records.iter().enumerate() {
+            assert_eq!(read_index(&mut input).unwrap(), Some(index as u64));
+            assert_eq!(&read_record(&mut input).unwrap(), expected);
+        }
+        assert_eq!(read_index(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_entries_fail() {
+        let mut bytes = Vec::new();
+        write_record(&mut bytes, &record("read", "ACGT", Some("IIII"))).unwrap();
+        bytes.pop();
+        assert!(read_record(&mut bytes.as_slice()).is_err());
+
+        let mut bytes = Vec::new();
+        write_u64(&mut bytes, 7).unwrap();
+        for cut in 1..8 {
+            let error = read_index(&mut &bytes[..cut]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        assert_eq!(read_index(&mut &bytes[..0]).unwrap(), None);
+        assert_eq!(read_index(&mut bytes.as_slice()).unwrap(), Some(7));
+    }
+
+    #[test]
+    fn partitions_merge_in_index_order() {
+        let parent = std::env::temp_dir();
+        let dir = SpillDir::create(&parent).unwrap();
+        let other = SpillDir::create(&parent).unwrap();
+        assert_ne!(dir.path, other.path);
+
+        let mut partitions = dir.partitions("part", 3).unwrap();
+        for index in 0..20u64 {
+            let out = &mut partitions[(index * 7 % 3) as usize].out;
+            write_u64(out, index).unwrap();
+            write_bytes(out, format!("entry{}", index).as_bytes()).unwrap();
+        }
+        let paths: Vec<PathBuf> = partitions
+            .into_iter()
+            .map(|partition| partition.finish().unwrap())
+            .collect();
+
+        let mut merged = Vec::new();
+        merge_by_index(&paths, read_bytes, |entry| {
+            merged.push(entry);
+            Ok(())
+        })
+        .unwrap();
+        let expected: Vec<Vec<u8>> = (0..20)
+            .map(|index| format!("entry{}", index).into_bytes())
+            .collect();
+        assert_eq!(merged, expected);
+
+        let path = dir.path.clone();
+        drop(dir);
+        assert!(!path.exists());
+        assert!(other.path.exists());
+    }
+}
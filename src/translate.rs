@@ -1,173 +1,258 @@
 use pyo3::prelude::*;
-use needletail::parse_fastx_file;
-use std::collections::HashMap;
-use crate::seq_utils::{reverse_complement, is_dna_string};
-
-
-// Genetic codes / variables sourced from Seals2 by Yuri Wolf (https://github.com/YuriWolf-ncbi/seals-2/blob/master/bin/misc/orf)
-// Original Perl implementation for comprehensive genetic code support
-
-// #[pyclass]
-// /// Common names names to number, fetched on 03/07/2025 from https://www.ncbi.nlm.nih.gov/Taxonomy/Utils/wprintgc.cgi 
-// pub enum GeneticCode {
-//     Standard = 1, // The Standard Code
-//     Vertebrate = 2, // The Vertebrate Mitochondrial Code
-//     Yeast = 3, // The Yeast Mitochondrial Code
-//     MoldProt = 4, // The Mold, Protozoan, and Coelenterate Mitochondrial Code and the Mycoplasma/Spiroplasma Code
-//     Invertebrate = 5, // The Invertebrate Mitochondrial Code
-//     Ciliate = 6, // The Ciliate, Dasycladacean and Hexamita Nuclear Code
-//     Echinoderm = 9, // The Echinoderm and Flatworm Mitochondrial Code
-//     Euplotid = 10, // The Euplotid Nuclear Code
-//     Bacterial = 11, // The Bacterial, Archaeal and Plant Plastid Code
-//     AltYeast = 12, // The Alternative Yeast Nuclear Code
-//     Ascidian = 13, // The Ascidian Mitochondrial Code
-//     AltFlatWorm = 14, // The Alternative Flatworm Mitochondrial Code
-//     Blepharisma = 15, // Blepharisma Nuclear Code
-//     Chlorophycean = 16, // Chlorophycean Mitochondrial Code
-//     Trematode = 21, // Trematode Mitochondrial Code
-//     Scenedesmus = 22, // Scenedesmus obliquus Mitochondrial Code
-//     Thraustochytrium = 23, // Thraustochytrium Mitochondrial Code
-//     Rhabdopleuridae = 24, // Rhabdopleuridae Mitochondrial Code
-//     CandidateDivision = 25, // Candidate Division SR1 and Gracilibacteria Code
-//     Pachysolen = 26, // Pachysolen tannophilus Nuclear Code
-//     Karyorelict = 27, // Karyorelict Nuclear Code
-//     Condylostoma = 28, // Condylostoma Nuclear Code
-//     Mesodinium = 29, // Mesodinium Nuclear Code
-//     Peritrich = 30, // Peritrich Nuclear Code
-//     Blastocrithidia = 31, // Blastocrithidia Nuclear Code
-//     Balanophoraceae = 32, // Balanophoraceae Plastid Code
-//     Cephalodiscidae = 33, // Cephalodiscidae Mitochondrial UAA-Tyr Code
-// }
-
-
-/// Genetic code amino acid sequences (64 codons in order: TTT, TTC, TTA, TTG, TCT, TCC, TCA, TCG, TAT, TAC, TAA, TAG, TGT, TGC, TGA, TGG, CTT, CTC, CTA, CTG, CCT, CCC, CCA, CCG, CAT, CAC, CAA, CAG, CGT, CGC, CGA, CGG, ATT, ATC, ATA, ATG, ACT, ACC, ACA, ACG, AAT, AAC, AAA, AAG, AGT, AGC, AGA, AGG, GTT, GTC, GTA, GTG, GCT, GCC, GCA, GCG, GAT, GAC, GAA, GAG, GGT, GGC, GGA, GGG)
-fn get_genetic_code_table() -> HashMap<u32, &'static str> {
-    let mut code_table = HashMap::new();
-    
-    code_table.insert(1, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(2, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"); 
-    code_table.insert(3, "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"); 
-    code_table.insert(4, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"); 
-    code_table.insert(5, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"); 
-    code_table.insert(6, "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"); 
-    code_table.insert(9, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"); 
-    code_table.insert(10, "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(11, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(12, "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(13, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG");
-    code_table.insert(14, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG");
-    code_table.insert(15, "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(16, "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(21, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG");
-    code_table.insert(22, "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(23, "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(24, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG");
-    code_table.insert(25, "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(26, "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(27, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(28, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(29, "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(30, "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(31, "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG");
-    code_table.insert(33, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG");
-    
-    code_table
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use crate::seq_utils::{complement_base, AMBIGUOUS_ALPHABET};
+use crate::genetic_code::{CodeSelection, GeneticCode};
+use crate::fastx_io::{RecordReader, RecordWriter, DEFAULT_LINE_LENGTH};
+use crate::pool;
+use crate::pyfile::{InputSource, OutputTarget};
+
+
+/// Maximum number of records translated per parallel batch
+const BATCH_SIZE: usize = 4096;
+
+/// Maximum number of bases translated per parallel batch
+const BATCH_BASES: usize = 1 << 26;
+
+/// Frames in the order they are reported by the six-frame functions
+const SIX_FRAMES: [i8; 6] = [1, 2, 3, -1, -2, -3];
+
+/// How alignment gaps (`-` or `.`) in nucleotide sequences are handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapMode {
+    /// Gaps are invalid characters
+    Error,
+    /// `---` translates to `-` and codons with one or two gaps to X
+    Keep,
+    /// Gaps are removed before translating
+    Remove,
+}
+
+impl GapMode {
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Ok(GapMode::Error),
+            "keep" => Ok(GapMode::Keep),
+            "remove" => Ok(GapMode::Remove),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unknown gap handling '{}'. Use 'error', 'keep' or 'remove'.",
+                name
+            ))),
+        }
+    }
+}
+
+/// Uppercase a nucleotide sequence, turn U into T and check it holds IUPAC
+/// nucleotides. With `lenient`, any other character is read as N.
+fn prepare_sequence(sequence: &[u8], gaps: GapMode, lenient: bool) -> PyResult<Vec<u8>> {
+    let mut seq = Vec::with_capacity(sequence.len());
+    for &base in sequence {
+        match base.to_ascii_uppercase() {
+            base @ (b'A' | b'C' | b'G' | b'T') => seq.push(base),
+            b'U' => seq.push(b'T'),
+            b'-' | b'.' if gaps == GapMode::Remove => {}
+            b'-' | b'.' if gaps == GapMode::Keep => seq.push(b'-'),
+            base if AMBIGUOUS_ALPHABET.as_bytes().contains(&base) => seq.push(base),
+            _ if lenient => seq.push(b'N'),
+            base => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Sequence contains invalid nucleotide '{}'. Only IUPAC nucleotides are allowed; use gaps='keep' or 'remove' for alignments and lenient=True to read other characters as N.",
+                    base as char
+                )))
+            }
+        }
+    }
+    Ok(seq)
+}
+
+fn check_frame(frame: i8) -> PyResult<()> {
+    if !(1..=3).contains(&frame.abs()) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Frame must be between -3 and 3 (excluding 0)."
+        ));
+    }
+    Ok(())
+}
+
+/// Translate an already prepared and oriented sequence from `offset` on
+fn translate_from(seq: &[u8], offset: usize, to_protein: bool, code: &GeneticCode) -> String {
+    if offset >= seq.len() {
+        return String::new();
+    }
+    let seq = &seq[offset..];
+
+    let translated = if to_protein {
+        code.translate_codons(seq)
+    } else {
+        let mut codons = Vec::with_capacity(seq.len() / 3 * 4);
+        for codon in seq.chunks_exact(3) {
+            if !codons.is_empty() {
+                codons.push(b' ');
+            }
+            codons.extend_from_slice(codon);
+        }
+        codons
+    };
+    String::from_utf8(translated).unwrap_or_default()
+}
+
+/// Translate a prepared sequence in `frames`, reverse complementing it at
+/// most once
+fn translate_frames(seq: &[u8], frames: &[i8], to_protein: bool, code: &GeneticCode) -> Vec<(i8, String)> {
+    let mut reverse: Option<Vec<u8>> = None;
+    frames
+        .iter()
+        .map(|&frame| {
+            let oriented = if frame < 0 {
+                reverse.get_or_insert_with(|| reverse_complement_bytes(seq))
+            } else {
+                seq
+            };
+            let offset = (frame.unsigned_abs() - 1) as usize;
+            (frame, translate_from(oriented, offset, to_protein, code))
+        })
+        .collect()
+}
+
+/// Input coordinate of every base kept by `prepare_sequence`
+fn input_positions(sequence: &[u8], gaps: GapMode) -> Vec<usize> {
+    (0..sequence.len())
+        .filter(|&i| gaps != GapMode::Remove || !matches!(sequence[i], b'-' | b'.'))
+        .collect()
+}
+
+/// Reverse complement a prepared sequence
+fn reverse_complement_bytes(seq: &[u8]) -> Vec<u8> {
+    seq.iter().rev().map(|&base| complement_base(base)).collect()
+}
+
+/// Offsets of the complete codons of `frame` in a sequence of `len` bases
+/// oriented for it
+fn frame_offsets(len: usize, frame: i8) -> std::iter::StepBy<std::ops::Range<usize>> {
+    ((frame.unsigned_abs() - 1) as usize..len.saturating_sub(2)).step_by(3)
 }
 
+/// Input coordinates `(start, end)` of the codon at `offset` of a sequence
+/// of `len` bases oriented for `frame`
+fn codon_span(len: usize, offset: usize, frame: i8, positions: &[usize]) -> (usize, usize) {
+    let (first, third) = if frame < 0 {
+        (len - offset - 3, len - offset - 1)
+    } else {
+        (offset, offset + 2)
+    };
+    (positions[first], positions[third] + 1)
+}
 
-// function `get_start_codon_table` is never used - TODO: add support for direct/explicit alt. start codons?
-// `#[warn(dead_code)]`
-/// Genetic code start codon patterns (M for start codons, - for others)  
-fn get_start_codon_table() -> HashMap<u32, &'static str> {
-    let mut start_table = HashMap::new();
-    
-    start_table.insert(1, "---M---------------M---------------M----------------------------");
-    start_table.insert(2, "--------------------------------MMMM---------------M------------");
-    start_table.insert(3, "----------------------------------MM----------------------------");
-    start_table.insert(4, "--MM---------------M------------MMMM---------------M------------");
-    start_table.insert(5, "---M----------------------------MMMM---------------M------------");
-    start_table.insert(6, "-----------------------------------M----------------------------");
-    start_table.insert(9, "-----------------------------------M---------------M------------");
-    start_table.insert(10, "-----------------------------------M----------------------------");
-    start_table.insert(11, "---M---------------M------------MMMM---------------M------------");
-    start_table.insert(12, "-------------------M---------------M----------------------------");
-    start_table.insert(13, "-----------------------------------M----------------------------");
-    start_table.insert(14, "-----------------------------------M----------------------------");
-    start_table.insert(15, "-----------------------------------M----------------------------");
-    start_table.insert(16, "-----------------------------------M----------------------------");
-    start_table.insert(21, "-----------------------------------M---------------M------------");
-    start_table.insert(22, "-----------------------------------M----------------------------");
-    start_table.insert(23, "--------------------------------M--M---------------M------------");
-    start_table.insert(24, "---M---------------M---------------M---------------M------------");
-    start_table.insert(25, "---M-------------------------------M---------------M------------");
-    start_table.insert(26, "-------------------M---------------M----------------------------");
-    start_table.insert(27, "-----------------------------------M----------------------------");
-    start_table.insert(28, "-----------------------------------M----------------------------");
-    start_table.insert(29, "-----------------------------------M----------------------------");
-    start_table.insert(30, "-----------------------------------M----------------------------");
-    start_table.insert(31, "-----------------------------------M----------------------------");
-    start_table.insert(33, "---M---------------M---------------M---------------M------------");
-    
-    start_table
+/// A translation with the input coordinates of every codon
+///
+/// * `protein` - The amino acid sequence, stops included as `*`
+/// * `frame` - The reading frame translated
+/// * `codons` - `(start, end)` of the codon of each amino acid in the input
+///   sequence, 0-based and half-open. Codons of negative frames run from
+///   the end of the sequence towards its start
+/// * `internal_stops` - Indices of the stops before the last codon
+/// * `terminal_stop` - Whether the last codon is a stop
+/// * `longest_stop_free` - `(start, end)` amino acid indices of the longest
+///   stretch without stops, the first one on ties
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct TranslationReport {
+    #[pyo3(get)]
+    pub protein: String,
+    #[pyo3(get)]
+    pub frame: i8,
+    #[pyo3(get)]
+    pub codons: Vec<(usize, usize)>,
+    #[pyo3(get)]
+    pub internal_stops: Vec<usize>,
+    #[pyo3(get)]
+    pub terminal_stop: bool,
+    #[pyo3(get)]
+    pub longest_stop_free: (usize, usize),
 }
 
-/// Genetic code names
-fn get_genetic_code_names() -> HashMap<u32, &'static str> {
-    let mut names = HashMap::new();
-    
-    names.insert(1, "Standard");
-    names.insert(2, "Vertebrate Mitochondrial");
-    names.insert(3, "Yeast Mitochondrial");
-    names.insert(4, "Mold Mitochondrial; Protozoan Mitochondrial; Coelenterate Mitochondrial; Mycoplasma; Spiroplasma");
-    names.insert(5, "Invertebrate Mitochondrial");
-    names.insert(6, "Ciliate Nuclear; Dasycladacean Nuclear; Hexamita Nuclear");
-    names.insert(9, "Echinoderm Mitochondrial; Flatworm Mitochondrial");
-    names.insert(10, "Euplotid Nuclear");
-    names.insert(11, "Bacterial and Plant Plastid");
-    names.insert(12, "Alternative Yeast Nuclear");
-    names.insert(13, "Ascidian Mitochondrial");
-    names.insert(14, "Alternative Flatworm Mitochondrial");
-    names.insert(15, "Blepharisma Macronuclear");
-    names.insert(16, "Chlorophycean Mitochondrial");
-    names.insert(21, "Trematode Mitochondrial");
-    names.insert(22, "Scenedesmus obliquus mitochondrial");
-    names.insert(23, "Thraustochytrium mitochondrial");
-    names.insert(24, "Pterobranchia mitochondrial");
-    names.insert(25, "Candidate Division SR1 and Gracilibacteria");
-    names.insert(26, "Pachysolen tannophilus Nuclear Code");
-    names.insert(27, "Karyorelict Nuclear Code");
-    names.insert(28, "Condylostoma Nuclear Code");
-    names.insert(29, "Mesodinium Nuclear Code");
-    names.insert(30, "Peritrich Nuclear Code");
-    names.insert(31, "Blastocrithidia Nuclear Code");
-    names.insert(33, "Cephalodiscidae Mitochondrial UAA-Tyr Code");
-    
-    names
+#[pymethods]
+impl TranslationReport {
+    fn __len__(&self) -> usize {
+        self.protein.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TranslationReport(frame={}, length={}, internal_stops={}, longest_stop_free={:?})",
+            self.frame,
+            self.protein.len(),
+            self.internal_stops.len(),
+            self.longest_stop_free
+        )
+    }
 }
 
-/// Convert codon index to amino acid using genetic code table
-fn codon_to_amino_acid(codon: &str, genetic_code_table: &str) -> char {
-    // Codon order: TTT, TTC, TTA, TTG, TCT, TCC, TCA, TCG, TAT, TAC, TAA, TAG, TGT, TGC, TGA, TGG, 
-    //              CTT, CTC, CTA, CTG, CCT, CCC, CCA, CCG, CAT, CAC, CAA, CAG, CGT, CGC, CGA, CGG,
-    //              ATT, ATC, ATA, ATG, ACT, ACC, ACA, ACG, AAT, AAC, AAA, AAG, AGT, AGC, AGA, AGG,
-    //              GTT, GTC, GTA, GTG, GCT, GCC, GCA, GCG, GAT, GAC, GAA, GAG, GGT, GGC, GGA, GGG
-    
-    let bases = ['T', 'C', 'A', 'G'];
-    let codon_chars: Vec<char> = codon.chars().collect();
-    
-    if codon_chars.len() != 3 {
-        return 'X';
+impl TranslationReport {
+    /// Translate the codons of `seq` starting at `offsets`
+    ///
+    /// `seq` is oriented for `frame` and `positions` holds the input
+    /// coordinate of every base of the forward sequence.
+    fn from_codons(
+        seq: &[u8],
+        offsets: impl Iterator<Item = usize>,
+        frame: i8,
+        positions: &[usize],
+        code: &GeneticCode,
+    ) -> Self {
+        let mut protein = Vec::new();
+        let mut codons = Vec::new();
+        for offset in offsets {
+            protein.push(code.amino_acid(&seq[offset..offset + 3]));
+            codons.push(codon_span(seq.len(), offset, frame, positions));
+        }
+
+        let mut stops: Vec<usize> = (0..protein.len()).filter(|&i| protein[i] == b'*').collect();
+        let terminal_stop = protein.last() == Some(&b'*');
+        let mut longest_stop_free = (0, 0);
+        let mut stretch_start = 0;
+        for stop in stops.iter().copied().chain([protein.len()]) {
+            if stop - stretch_start > longest_stop_free.1 - longest_stop_free.0 {
+                longest_stop_free = (stretch_start, stop);
+            }
+            stretch_start = stop + 1;
+        }
+        if terminal_stop {
+            stops.pop();
+        }
+
+        TranslationReport {
+            protein: String::from_utf8(protein).unwrap_or_default(),
+            frame,
+            codons,
+            internal_stops: stops,
+            terminal_stop,
+            longest_stop_free,
+        }
+    }
+
+    /// Translate an oriented sequence in `frame`
+    fn in_frame(seq: &[u8], frame: i8, positions: &[usize], code: &GeneticCode) -> Self {
+        Self::from_codons(seq, frame_offsets(seq.len(), frame), frame, positions, code)
     }
-    
-    let pos1 = bases.iter().position(|&b| b == codon_chars[0]).unwrap_or(0);
-    let pos2 = bases.iter().position(|&b| b == codon_chars[1]).unwrap_or(0);
-    let pos3 = bases.iter().position(|&b| b == codon_chars[2]).unwrap_or(0);
-    
-    let index = pos1 * 16 + pos2 * 4 + pos3;
-    
-    genetic_code_table.chars().nth(index).unwrap_or('X')
 }
 
+/// Report the translation of a prepared sequence in `frames`, reverse
+/// complementing it at most once
+fn report_frames(seq: &[u8], frames: &[i8], positions: &[usize], code: &GeneticCode) -> Vec<(i8, TranslationReport)> {
+    let mut reverse: Option<Vec<u8>> = None;
+    frames
+        .iter()
+        .map(|&frame| {
+            let oriented = if frame < 0 {
+                reverse.get_or_insert_with(|| reverse_complement_bytes(seq))
+            } else {
+                seq
+            };
+            (frame, TranslationReport::in_frame(oriented, frame, positions, code))
+        })
+        .collect()
+}
 
 /// Translate a DNA or RNA sequence into amino acid sequence
 /// 
@@ -176,84 +261,410 @@ fn codon_to_amino_acid(codon: &str, genetic_code_table: &str) -> char {
 /// * `frame` - Reading frame (-3, -2, -1, 1, 2, 3). Negative frames use reverse complement
 /// * `start` - Start position in the sequence (0-based)
 /// * `stop` - Stop position in the sequence (0-based, exclusive). If None, uses end of sequence
-/// * `to_protein` - If true, return amino acid sequence. If false, return codons
-/// * `genetic_code` - Genetic code table number (default: 1 for standard code)
+/// * `to_protein` - If true, return amino acid sequence. If false, return
+///   codons joined by spaces; `translate_codons` returns them as tuples
+/// * `genetic_code` - NCBI genetic code id or name, or a `GeneticCode` (default: 1 for standard code)
+/// * `gaps` - How alignment gaps (`-`, `.`) are handled: `"error"`, `"keep"`
+///   (`---` translates to `-`) or `"remove"`
+/// * `lenient` - Read characters that are not IUPAC nucleotides as N instead
+///   of raising an error
+/// * `report` - Return a `TranslationReport` with the codon coordinates,
+///   internal stops and longest stop-free stretch instead of a string
+///
+/// Ambiguous codons translate to the amino acid all their expansions share
+/// (`GCN` is A), otherwise to X.
 #[pyfunction]
-#[pyo3(signature = (sequence, frame=1, start=0, stop=None, to_protein=true, genetic_code=1))]
+#[pyo3(signature = (sequence, frame=1, start=0, stop=None, to_protein=true, genetic_code=CodeSelection::Id(1), gaps="error", lenient=false, report=false))]
+#[allow(clippy::too_many_arguments)]
 pub fn translate(
-    sequence: String,
+    py: Python<'_>,
+    sequence: &str,
     frame: i8,
     start: usize,
     stop: Option<usize>,
     to_protein: bool,
-    genetic_code: u32,
-) -> PyResult<String> {
-    if sequence.is_empty() {
-        return Ok(String::new());
-    }
-    
-    // Convert to uppercase and replace U with T for RNA
-    let mut seq = sequence.to_uppercase().replace('U', "T");
-
-    if !is_dna_string(&seq) {
+    genetic_code: CodeSelection,
+    gaps: &str,
+    lenient: bool,
+    report: bool,
+) -> PyResult<PyObject> {
+    if report && !to_protein {
         return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            "Sequence contains invalid nucleotides. Only A, T, C, G, U are allowed."
+            "report=True needs to_protein=True."
         ));
     }
 
-    
-    // Apply start and stop positions  
-    let end_pos = stop.unwrap_or(seq.len());
-    if start < seq.len() && start < end_pos {
-        seq = seq[start..end_pos.min(seq.len())].to_string();
-    } else {
-        return Ok(String::new());
+    // Convert to uppercase and replace U with T for RNA
+    let gaps = GapMode::from_name(gaps)?;
+    let seq = prepare_sequence(sequence.as_bytes(), gaps, lenient)?;
+
+    // Apply start and stop positions
+    let end_pos = stop.unwrap_or(seq.len()).min(seq.len());
+    let start = start.min(end_pos);
+    let seq = &seq[start..end_pos];
+
+    check_frame(frame)?;
+    let code = genetic_code.resolve()?;
+    if report {
+        let positions = &input_positions(sequence.as_bytes(), gaps)[start..end_pos];
+        let (_, report) = report_frames(seq, &[frame], positions, &code).remove(0);
+        return Ok(report.into_pyobject(py)?.into_any().unbind());
     }
-    
-    // Handle negative frames (reverse complement)
-    let frame_abs = if frame < 0 {
-        seq = reverse_complement(&seq);
-        frame.abs()
+    let (_, translated) = translate_frames(seq, &[frame], to_protein, &code)
+        .pop()
+        .unwrap_or_default();
+    Ok(translated.into_pyobject(py)?.into_any().unbind())
+}
+
+/// Translate a DNA or RNA sequence codon by codon, with the coordinates of
+/// every codon
+///
+/// # Arguments
+/// * `sequence` - DNA or RNA sequence string
+/// * `frame` - Reading frame (-3, -2, -1, 1, 2, 3). Negative frames use reverse complement
+/// * `start` - Start position in the sequence (0-based)
+/// * `stop` - Stop position in the sequence (0-based, exclusive). If None, uses end of sequence
+/// * `genetic_code` - NCBI genetic code id or name, or a `GeneticCode` (default: 1 for standard code)
+/// * `gaps` - How alignment gaps (`-`, `.`) are handled: `"error"`, `"keep"`
+///   (`---` translates to `-`) or `"remove"`
+/// * `lenient` - Read characters that are not IUPAC nucleotides as N instead
+///   of raising an error
+/// * `columns` - Return a dict of columns instead of tuples
+///
+/// # Returns
+/// `(nt_start, nt_end, codon, amino_acid)` tuples in translation order, or
+/// with `columns` a dict of these four lists, ready for `polars.DataFrame`
+/// or `pyarrow.table`. Coordinates are 0-based and half-open in the input
+/// sequence, whatever `start` and `frame`; codons of negative frames are
+/// reverse complemented and run from the end of the sequence towards its
+/// start.
+#[pyfunction]
+#[pyo3(signature = (sequence, frame=1, start=0, stop=None, genetic_code=CodeSelection::Id(1), gaps="error", lenient=false, columns=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn translate_codons(
+    py: Python<'_>,
+    sequence: &str,
+    frame: i8,
+    start: usize,
+    stop: Option<usize>,
+    genetic_code: CodeSelection,
+    gaps: &str,
+    lenient: bool,
+    columns: bool,
+) -> PyResult<PyObject> {
+    let gaps = GapMode::from_name(gaps)?;
+    let seq = prepare_sequence(sequence.as_bytes(), gaps, lenient)?;
+    let end_pos = stop.unwrap_or(seq.len()).min(seq.len());
+    let start = start.min(end_pos);
+    let positions = &input_positions(sequence.as_bytes(), gaps)[start..end_pos];
+    let seq = &seq[start..end_pos];
+
+    check_frame(frame)?;
+    let code = genetic_code.resolve()?;
+    let reverse;
+    let oriented = if frame < 0 {
+        reverse = reverse_complement_bytes(seq);
+        &reverse
     } else {
-        frame
+        seq
     };
-    
-    // Validate frame is in range 1-3
-    if !(1..=3).contains(&frame_abs) {
+    let rows: Vec<(usize, usize, String, char)> = frame_offsets(oriented.len(), frame)
+        .map(|offset| {
+            let codon = &oriented[offset..offset + 3];
+            let (nt_start, nt_end) = codon_span(oriented.len(), offset, frame, positions);
+            let codon_str = String::from_utf8_lossy(codon).into_owned();
+            (nt_start, nt_end, codon_str, code.amino_acid(codon) as char)
+        })
+        .collect();
+
+    if !columns {
+        return Ok(rows.into_pyobject(py)?.into_any().unbind());
+    }
+    let table = PyDict::new(py);
+    table.set_item("nt_start", rows.iter().map(|row| row.0).collect::<Vec<_>>())?;
+    table.set_item("nt_end", rows.iter().map(|row| row.1).collect::<Vec<_>>())?;
+    table.set_item("codon", rows.iter().map(|row| row.2.as_str()).collect::<Vec<_>>())?;
+    table.set_item("amino_acid", rows.iter().map(|row| row.3).collect::<Vec<_>>())?;
+    Ok(table.into_any().unbind())
+}
+
+/// Translate a DNA or RNA sequence in all six reading frames
+///
+/// The sequence is validated and reverse complemented only once.
+///
+/// # Arguments
+/// * `sequence` - DNA or RNA sequence string
+/// * `to_protein` - If true, return amino acid sequences. If false, return codons
+/// * `genetic_code` - NCBI genetic code id or name, or a `GeneticCode` (default: 1 for standard code)
+/// * `gaps` - How alignment gaps (`-`, `.`) are handled: `"error"`, `"keep"`
+///   (`---` translates to `-`) or `"remove"`
+/// * `lenient` - Read characters that are not IUPAC nucleotides as N instead
+///   of raising an error
+/// * `report` - Return a `TranslationReport` per frame instead of strings
+///
+/// # Returns
+/// `(frame, translation)` pairs for frames 1, 2, 3, -1, -2 and -3
+#[pyfunction]
+#[pyo3(signature = (sequence, to_protein=true, genetic_code=CodeSelection::Id(1), gaps="error", lenient=false, report=false))]
+pub fn translate_six_frames(
+    py: Python<'_>,
+    sequence: &str,
+    to_protein: bool,
+    genetic_code: CodeSelection,
+    gaps: &str,
+    lenient: bool,
+    report: bool,
+) -> PyResult<PyObject> {
+    if report && !to_protein {
         return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            "Frame must be between -3 and 3 (excluding 0)."
+            "report=True needs to_protein=True."
         ));
     }
-    
-    // Adjust for reading frame (1-based to 0-based)
-    let frame_offset = (frame_abs - 1) as usize;
-    if frame_offset >= seq.len() {
-        return Ok(String::new());
+    let gaps = GapMode::from_name(gaps)?;
+    let seq = prepare_sequence(sequence.as_bytes(), gaps, lenient)?;
+    let code = genetic_code.resolve()?;
+    if report {
+        let positions = input_positions(sequence.as_bytes(), gaps);
+        let reports = report_frames(&seq, &SIX_FRAMES, &positions, &code);
+        return Ok(reports.into_pyobject(py)?.into_any().unbind());
     }
-    
-    seq = seq[frame_offset..].to_string();
-    
-    // Get genetic code table
-    let code_tables = get_genetic_code_table();
-    let genetic_code_str = code_tables.get(&genetic_code).unwrap_or(&code_tables[&1]);
-    
-    // Translate sequence
-    let mut result = Vec::new();
-    let mut codons = Vec::new();
-    
-    for i in (0..seq.len()).step_by(3) {
-        if i + 2 < seq.len() {
-            let codon = &seq[i..i+3];
-            codons.push(codon.to_string());
-            let amino_acid = codon_to_amino_acid(codon, genetic_code_str);
-            result.push(amino_acid);
+    let translations = translate_frames(&seq, &SIX_FRAMES, to_protein, &code);
+    Ok(translations.into_pyobject(py)?.into_any().unbind())
+}
+
+/// Whether a heptamer is a -1 slippery site `X XXY YYZ`: X any base, Y A or
+/// T and Z anything but G
+fn is_slippery(site: &[u8]) -> bool {
+    match site {
+        [x1, x2, x3, y1, y2, y3, z] => {
+            x1 == x2
+                && x2 == x3
+                && b"ACGT".contains(x1)
+                && y1 == y2
+                && y2 == y3
+                && matches!(y1, b'A' | b'T')
+                && matches!(z, b'A' | b'C' | b'T')
         }
+        _ => false,
     }
-    
-    if to_protein {
-        Ok(result.into_iter().collect())
-    } else {
-        Ok(codons.join(" "))
+}
+
+/// A translation through a programmed frameshift, found by
+/// `translate_frameshifts`
+///
+/// * `start`, `end` - Input coordinates of the slippery heptamer
+/// * `frame` - The frame translated up to the heptamer
+/// * `shift` - The frameshift, -1 or 1
+/// * `motif` - The heptamer, as read in `frame`
+/// * `translation` - The `TranslationReport` of the fused protein. Its
+///   codon coordinates step back or skip a base after the heptamer
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Frameshift {
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    #[pyo3(get)]
+    pub frame: i8,
+    #[pyo3(get)]
+    pub shift: i8,
+    #[pyo3(get)]
+    pub motif: String,
+    #[pyo3(get)]
+    pub translation: TranslationReport,
+}
+
+#[pymethods]
+impl Frameshift {
+    fn __repr__(&self) -> String {
+        format!(
+            "Frameshift(start={}, end={}, frame={}, shift={}, motif='{}', length={})",
+            self.start,
+            self.end,
+            self.frame,
+            self.shift,
+            self.motif,
+            self.translation.protein.len()
+        )
+    }
+}
+
+/// Translate a sequence across candidate ribosomal frameshifts
+///
+/// Slippery heptamers `X XXY YYZ` (X any base, Y A or U, Z not G) whose
+/// `XXY YYZ` are codons of `frame` are candidate sites. At each site the
+/// translation continues after `YYZ` in the shifted frame, so a -1 shift
+/// reads the last base of the heptamer twice and a +1 shift skips the next
+/// base.
+///
+/// # Arguments
+/// * `sequence` - DNA or RNA sequence string
+/// * `frame` - Reading frame up to the sites (-3, -2, -1, 1, 2, 3)
+/// * `shifts` - Frameshifts tried at every site, -1 and/or 1
+/// * `genetic_code` - NCBI genetic code id or name, or a `GeneticCode` (default: 1 for standard code)
+/// * `gaps` - How alignment gaps (`-`, `.`) are handled: `"error"`, `"keep"`
+///   (`---` translates to `-`) or `"remove"`
+/// * `lenient` - Read characters that are not IUPAC nucleotides as N instead
+///   of raising an error
+///
+/// # Returns
+/// A `Frameshift` per site and shift, ordered by site start and shift.
+/// Compare their `translation.longest_stop_free` with that of
+/// `translate(..., report=True)` to spot shifts that bypass a stop.
+#[pyfunction]
+#[pyo3(signature = (sequence, frame=1, shifts=vec![-1, 1], genetic_code=CodeSelection::Id(1), gaps="error", lenient=false))]
+pub fn translate_frameshifts(
+    sequence: &str,
+    frame: i8,
+    shifts: Vec<i8>,
+    genetic_code: CodeSelection,
+    gaps: &str,
+    lenient: bool,
+) -> PyResult<Vec<Frameshift>> {
+    check_frame(frame)?;
+    if let Some(shift) = shifts.iter().find(|shift| shift.abs() != 1) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Invalid frameshift {}. Shifts must be -1 or 1.",
+            shift
+        )));
+    }
+    let gaps = GapMode::from_name(gaps)?;
+    let seq = prepare_sequence(sequence.as_bytes(), gaps, lenient)?;
+    let positions = input_positions(sequence.as_bytes(), gaps);
+    let code = genetic_code.resolve()?;
+    let seq = if frame < 0 { reverse_complement_bytes(&seq) } else { seq };
+    let len = seq.len();
+    let offset = (frame.unsigned_abs() - 1) as usize;
+
+    let mut frameshifts = Vec::new();
+    // Heptamers start one base before a codon boundary of the frame
+    for site in ((offset + 2) % 3..len.saturating_sub(6)).step_by(3) {
+        if !is_slippery(&seq[site..site + 7]) {
+            continue;
+        }
+        let (start, end) = if frame < 0 {
+            (positions[len - 7 - site], positions[len - 1 - site] + 1)
+        } else {
+            (positions[site], positions[site + 6] + 1)
+        };
+        let site_end = site + 7;
+        for &shift in &shifts {
+            let resume = (site_end as isize + shift as isize) as usize;
+            let offsets = (offset..site_end)
+                .step_by(3)
+                .chain((resume..len.saturating_sub(2)).step_by(3));
+            frameshifts.push(Frameshift {
+                start,
+                end,
+                frame,
+                shift,
+                motif: String::from_utf8_lossy(&seq[site..site_end]).into_owned(),
+                translation: TranslationReport::from_codons(&seq, offsets, frame, &positions, &code),
+            });
+        }
+    }
+    frameshifts.sort_by_key(|frameshift| (frameshift.start, frameshift.shift));
+    Ok(frameshifts)
+}
+
+/// Frames requested from `translate_file`: `"all"` or a list of frames
+#[derive(FromPyObject)]
+pub enum FrameSelection {
+    Name(String),
+    List(Vec<i8>),
+}
+
+impl FrameSelection {
+    fn frames(self) -> PyResult<Vec<i8>> {
+        let frames = match self {
+            FrameSelection::Name(name) if name == "all" => SIX_FRAMES.to_vec(),
+            FrameSelection::Name(name) => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown frames '{}'. Use 'all' or a list of frames.",
+                    name
+                )))
+            }
+            FrameSelection::List(frames) => frames,
+        };
+        frames.iter().try_for_each(|&frame| check_frame(frame))?;
+        Ok(frames)
+    }
+}
+
+/// Label a translated record with its frame: `name_frame=-2 description`
+fn frame_label(id: &str, frame: i8) -> String {
+    match id.split_once(char::is_whitespace) {
+        Some((name, description)) => format!("{}_frame={} {}", name, frame, description),
+        None => format!("{}_frame={}", id, frame),
+    }
+}
+
+/// A field of a header template
+#[derive(Clone, Debug)]
+enum HeaderPart {
+    Text(String),
+    Id,
+    Description,
+    Frame,
+    Length,
+}
+
+/// A translated record header such as `{id}_f{frame}`
+struct HeaderTemplate {
+    parts: Vec<HeaderPart>,
+}
+
+impl HeaderTemplate {
+    /// Parse `{id}`, `{description}`, `{frame}` and `{length}` fields out of
+    /// a template
+    fn parse(template: &str) -> PyResult<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(HeaderPart::Text(rest[..open].to_string()));
+            }
+            let close = rest[open..].find('}').map(|close| open + close).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unclosed '{{' in header template '{}'",
+                    template
+                ))
+            })?;
+            parts.push(match &rest[open + 1..close] {
+                "id" => HeaderPart::Id,
+                "description" => HeaderPart::Description,
+                "frame" => HeaderPart::Frame,
+                "length" => HeaderPart::Length,
+                field => {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "Unknown field '{{{}}}' in header template. Use {{id}}, {{description}}, {{frame}} or {{length}}.",
+                        field
+                    )))
+                }
+            });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(HeaderPart::Text(rest.to_string()));
+        }
+        Ok(HeaderTemplate { parts })
+    }
+
+    /// The header of the translation of record `header` in `frame`
+    fn render(&self, header: &str, frame: i8, length: usize) -> String {
+        let (id, description) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                HeaderPart::Text(text) => rendered.push_str(text),
+                HeaderPart::Id => rendered.push_str(id),
+                HeaderPart::Description => rendered.push_str(description),
+                HeaderPart::Frame => rendered.push_str(&frame.to_string()),
+                HeaderPart::Length => rendered.push_str(&length.to_string()),
+            }
+        }
+        rendered
     }
 }
 
@@ -263,41 +674,124 @@ pub fn translate(
 /// * `file_path` - Path to the FASTA/FASTQ file
 /// * `frame` - Reading frame (-3, -2, -1, 1, 2, 3)
 /// * `to_protein` - If true, return amino acid sequences. If false, return codons
-/// * `genetic_code` - Genetic code table number (default: 1 for standard code)
+/// * `genetic_code` - NCBI genetic code id or name, or a `GeneticCode` (default: 1 for standard code)
+/// * `frames` - `"all"` or a list of frames to translate instead of `frame`.
+///   Each translation is labelled `name_frame=N`
+/// * `output_file` - If given, write the translations there as FASTA and
+///   return their number instead of a list
+/// * `gaps` - How alignment gaps (`-`, `.`) are handled: `"error"`, `"keep"`
+///   (`---` translates to `-`) or `"remove"`
+/// * `lenient` - Read characters that are not IUPAC nucleotides as N instead
+///   of raising an error
+/// * `threads` - Number of worker threads. Records are translated in parallel
+///   batches and returned or written in input order
+/// * `header` - Template of the translation headers, with the fields `{id}`
+///   (first word of the record header), `{description}` (the rest),
+///   `{frame}` and `{length}` (of the translation), e.g. `{id}_f{frame}`
+/// * `min_length` - Skip translations with fewer amino acids, after trimming
+/// * `trim_stops` - Remove trailing stops from the translations
+/// * `line_length` - FASTA line width of `output_file`, 0 for one line per
+///   sequence
+/// * `compression` - Compression codec of `output_file`. Inferred from its
+///   extension by default
 #[pyfunction]
-#[pyo3(signature = (file_path, frame=1, to_protein=true, genetic_code=1))]
+#[pyo3(signature = (file_path, frame=1, to_protein=true, genetic_code=CodeSelection::Id(1), frames=None, output_file=None, gaps="error", lenient=false, threads=None, header=None, min_length=0, trim_stops=false, line_length=DEFAULT_LINE_LENGTH, compression=None))]
+#[allow(clippy::too_many_arguments)]
 pub fn translate_file(
-    file_path: String,
+    py: Python<'_>,
+    file_path: InputSource,
     frame: i8,
     to_protein: bool,
-    genetic_code: u32,
-) -> PyResult<Vec<(String, String)>> {
-    let mut results = Vec::new();
-    
-    let mut reader = parse_fastx_file(&file_path)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Error reading file: {}", e)))?;
-    
-    while let Some(record) = reader.next() {
-        let record = record
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Error parsing record: {}", e)))?;
-        
-        let id = String::from_utf8_lossy(record.id()).to_string();
-        let sequence = String::from_utf8_lossy(&record.seq()).to_string();
-        
-        let translated = translate(sequence, frame, 0, None, to_protein, genetic_code)?;
-        results.push((id, translated));
+    genetic_code: CodeSelection,
+    frames: Option<FrameSelection>,
+    output_file: Option<OutputTarget>,
+    gaps: &str,
+    lenient: bool,
+    threads: Option<usize>,
+    header: Option<&str>,
+    min_length: usize,
+    trim_stops: bool,
+    line_length: usize,
+    compression: Option<&str>,
+) -> PyResult<PyObject> {
+    if !to_protein && (min_length > 0 || trim_stops) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "min_length and trim_stops need to_protein=True."
+        ));
     }
-    
-    Ok(results)
-}
+    let gaps = GapMode::from_name(gaps)?;
+    let template = header.map(HeaderTemplate::parse).transpose()?;
+    let labelled = frames.is_some();
+    let frames = match frames {
+        Some(frames) => frames.frames()?,
+        None => vec![frame],
+    };
+    check_frame(frame)?;
+    let code = genetic_code.resolve()?;
+    let mut writer = output_file
+        .map(|output| RecordWriter::create(output, line_length, compression))
+        .transpose()?;
+    let to_file = writer.is_some();
 
-/// Get available genetic code tables
-#[pyfunction]
-pub fn list_genetic_codes() -> PyResult<Vec<(u32, String)>> {
-    let names = get_genetic_code_names();
-    let mut codes: Vec<(u32, String)> = names.iter()
-        .map(|(&code, &name)| (code, name.to_string()))
-        .collect();
-    codes.sort_by_key(|&(code, _)| code);
-    Ok(codes)
+    let (results, count) = pool::run(py, threads, |interrupt| {
+        let mut results = Vec::new();
+        let mut count: usize = 0;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut batch_bases = 0;
+        let mut reader = RecordReader::open(file_path)?.peekable();
+
+        while let Some(record) = reader.next() {
+            interrupt.check()?;
+            let record = record?;
+            batch_bases += record.seq.len();
+            batch.push(record);
+            if batch.len() < BATCH_SIZE && batch_bases < BATCH_BASES && reader.peek().is_some() {
+                continue;
+            }
+
+            let translations: Vec<Vec<(i8, String)>> = batch
+                .par_iter()
+                .map(|record| {
+                    let seq = prepare_sequence(&record.seq, gaps, lenient)?;
+                    let mut translated = translate_frames(&seq, &frames, to_protein, &code);
+                    if trim_stops {
+                        for (_, protein) in translated.iter_mut() {
+                            protein.truncate(protein.trim_end_matches('*').len());
+                        }
+                    }
+                    translated.retain(|(_, protein)| protein.len() >= min_length);
+                    Ok(translated)
+                })
+                .collect::<PyResult<_>>()?;
+
+            for (record, translated) in batch.iter().zip(translations) {
+                let id = String::from_utf8_lossy(&record.id);
+                for (frame, translated) in translated {
+                    let label = match &template {
+                        Some(template) => template.render(&id, frame, translated.len()),
+                        None if labelled => frame_label(&id, frame),
+                        None => id.to_string(),
+                    };
+                    match writer.as_mut() {
+                        Some(writer) => writer.write_parts(label.as_bytes(), translated.as_bytes(), None)?,
+                        None => results.push((label, translated)),
+                    }
+                    count += 1;
+                }
+            }
+            batch.clear();
+            batch_bases = 0;
+        }
+
+        if let Some(writer) = writer {
+            writer.finish()?;
+        }
+        Ok((results, count))
+    })?;
+
+    if to_file {
+        Ok(count.into_pyobject(py)?.into_any().unbind())
+    } else {
+        Ok(results.into_pyobject(py)?.into_any().unbind())
+    }
 }